@@ -0,0 +1,893 @@
+//! This module implements the coin, i.e. the transcript of the transfers of a coin from the withdrawal
+//! to the deposit.
+//!
+//! The bank signs the message [M, g1^sk] of the withdrawal with the tag-based LHSPS, under the tag
+//! derived from the serial number of the first hop (see `Hop::bank_tag`). The coin does not carry the
+//! signature, but a zero-knowledge GS proof of a valid signature on [M, g1^sk] with the signature
+//! and g1^sk committed, which every receiver verifies against the public M.
+//!
+//! Every transfer appends a hop for the new holder, with
+//! - the serial number of the holder and a GS proof of its well-formedness, where the
+//!   `SerialNumberProof` and the public key of the holder are committed,
+//! - the double-spending tag of the previous holder on the serial number (none for the withdrawal),
+//!   with a GS proof that it is consistent with the serial number of the previous hop, where the
+//!   `TagProof` and the public key of the previous holder are committed,
+//! - the identity of the holder encrypted under the tracing key of the bank (scheme E), which the
+//!   GS proof of the serial number also shows to be the identity of the committed public key, so the
+//!   ciphertext cannot be copied from the hop of another holder,
+//! - the serial number encrypted under the RCCA key of the bank with the label of the hop (see
+//!   `deposit_label`), which the bank decrypts and compares with the serial number on deposit.
+//!
+//! A coin grows with every transfer, so the number of hops is capped by `MAX_HOPS`.
+//! The holder also keeps the secret part of the coin, i.e. the nonce of its serial number,
+//! which is needed to generate the tag on the next transfer.
+//!
+//! The serialized coin starts with the format version `COIN_VERSION` (u16, little endian).
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::rand::RngCore;
+use ark_std::{UniformRand, Zero};
+use groth_sahai::CRS;
+use std::ops::{Mul, Neg};
+
+use crate::double_spending::{
+    public_key::PublicKey, secret_key::SecretKey, serial_number::SerialNumber, tag::Tag,
+};
+use crate::encrypt_e::{
+    self,
+    ciphertext::Ciphertext,
+    identity::{encrypt_identity_with, verify_identity, IdentityProof},
+};
+use crate::lhsps::{
+    tagged::{signature::Signature, verifying_key::VerifyKey, Tagged},
+    LinearlyHomomorphicSignature,
+};
+use crate::params::Params;
+use crate::proof::{
+    shared::{self, SharedProof},
+    zk::{self, ZkProof},
+    Equation, PairingProduct,
+    Term::{Const, Var},
+};
+use crate::rcca::{self, labelled_ciphertext::LabelledCompactCiphertext};
+
+/// The current version of the coin format.
+pub const COIN_VERSION: u16 = 1;
+/// The maximum number of hops of a coin, including the withdrawal.
+pub const MAX_HOPS: usize = 64;
+
+// the dimension of the RCCA key of the bank, i.e. the serial number (m, n).
+const DEPOSIT_DIM: usize = 2;
+// the domain of the labels of the RCCA ciphertexts of the hops.
+const DEPOSIT_LABEL: &[u8] = b"transferable-ecash/coin/deposit";
+// the dimension of the signing key of the bank, i.e. the message [M, g1^sk].
+const BANK_DIM: usize = 2;
+
+#[derive(Debug)]
+pub enum CoinError {
+    /// The coin was serialized by an unknown version of the format.
+    UnsupportedVersion(u16),
+    /// The coin has reached `MAX_HOPS` hops.
+    TooManyHops,
+    /// The holder does not have the secret part of the coin.
+    MissingSecret,
+    /// A hop cannot be created, e.g. the keys of the context are inconsistent, or the tag cannot be
+    /// generated because the secret key is not the one of the holder.
+    InvalidHop,
+    /// The signature of the bank on the withdrawal is not valid.
+    InvalidBankSignature,
+    Serialization(SerializationError),
+}
+
+impl From<SerializationError> for CoinError {
+    fn from(e: SerializationError) -> Self {
+        CoinError::Serialization(e)
+    }
+}
+
+/// The public parameters and keys with which the hops of coins are created and checked.
+pub struct CoinContext<E: Pairing> {
+    pub(crate) params: Params<E>,
+    pub(crate) crs: CRS<E>,
+    // the tracing key of the bank (scheme E), for the identities of the holders.
+    pub(crate) tracing_key: encrypt_e::encrypt_key::EncryptKey<E>,
+    // the RCCA key of the bank, for the serial numbers.
+    pub(crate) deposit_key: rcca::encrypt_key::EncryptKey<E>,
+    // the tag-based LHSPS key of the bank, for the withdrawals.
+    pub(crate) bank_vk: VerifyKey<E>,
+}
+
+impl<E: Pairing> CoinContext<E> {
+    /// It fails if the tracing key is not built on `params` (see `encrypt_e::key_gen_with_params`),
+    /// or if the RCCA key or the verification key of the bank does not have dimension 2.
+    pub fn new(
+        params: Params<E>,
+        crs: CRS<E>,
+        tracing_key: encrypt_e::encrypt_key::EncryptKey<E>,
+        deposit_key: rcca::encrypt_key::EncryptKey<E>,
+        bank_vk: VerifyKey<E>,
+    ) -> Result<Self, ()> {
+        if !tracing_key.check_params(&params)
+            || deposit_key.layout().n() != DEPOSIT_DIM
+            || bank_vk.pk.len() != BANK_DIM
+        {
+            return Err(());
+        }
+        Ok(Self {
+            params,
+            crs,
+            tracing_key,
+            deposit_key,
+            bank_vk,
+        })
+    }
+
+    pub fn params(&self) -> &Params<E> {
+        &self.params
+    }
+}
+
+/// A transfer of the coin to a holder.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Hop<E: Pairing> {
+    pub(crate) sn: SerialNumber<E>,
+    // GS proof of the serial number equations and the identity equations with X = [y1^v] and
+    // Y = [sn-pf, pk, g^v], where v is the randomness of `identity` (see `hop_equations`).
+    pub(crate) sn_proof: SharedProof<E>,
+    // the tag of the previous holder on `sn`, with the GS proof of the tag equations with
+    // Y = [tag-pf, pk] (see `tag_equations`).
+    pub(crate) tag: Option<(Tag<E>, SharedProof<E>)>,
+    // the identity of the holder encrypted under the tracing key, with its proof of consistency.
+    pub(crate) identity: Ciphertext<E>,
+    pub(crate) identity_proof: IdentityProof<E>,
+    // (M, N) of `sn` encrypted under the RCCA key of the bank, with the label of the hop (see `deposit_label`).
+    pub(crate) deposit: LabelledCompactCiphertext<E>,
+}
+
+impl<E: Pairing> Hop<E> {
+    /// Creates the hop to the holder with the secret key `sk` and the nonce `n` of its serial number.
+    /// The tag is added by the previous holder in `Coin::transfer`.
+    pub fn new<R: RngCore>(
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        sk: &SecretKey<E>,
+        n: E::ScalarField,
+    ) -> Result<Self, CoinError> {
+        let params = &ctx.params;
+        let pk = sk.public_key(params);
+        let (sn, sn_pf) = sk.generate_serial_number(params, n);
+
+        let v = E::ScalarField::rand(rng);
+        let (identity, identity_proof) =
+            encrypt_identity_with(&ctx.tracing_key, params, &pk, sk.identity(params), v, rng)
+                .map_err(|_| CoinError::InvalidHop)?;
+
+        let sn_proof = shared::prove(
+            rng,
+            &ctx.crs,
+            &hop_equations(ctx, &sn, &identity),
+            &[ctx.tracing_key.y.0.mul(v).into()],
+            &[sn_pf.sn_pf, pk.pk, params.g.mul(v).into()],
+        )
+        .map_err(|_| CoinError::InvalidHop)?;
+
+        let deposit = ctx
+            .deposit_key
+            .encrypt_compact_labelled(rng, &[sn.m, sn.n], &deposit_label(&sn, &identity))
+            .map_err(|_| CoinError::InvalidHop)?;
+
+        Ok(Self {
+            sn,
+            sn_proof,
+            tag: None,
+            identity,
+            identity_proof,
+            deposit,
+        })
+    }
+
+    pub fn serial_number(&self) -> &SerialNumber<E> {
+        &self.sn
+    }
+
+    /// The tag under which the bank signs the withdrawal of the hop, i.e. the compressed M of its
+    /// serial number. The bank must not sign two withdrawals under the same tag.
+    pub fn bank_tag(&self) -> Vec<u8> {
+        let mut tag = Vec::new();
+        self.sn
+            .m
+            .serialize_compressed(&mut tag)
+            .expect("serialization to vec should not fail");
+        tag
+    }
+
+    /// The message [M, g1^sk] signed by the bank on the withdrawal of the hop, given the identity of the holder.
+    pub fn bank_message(&self, identity: E::G1Affine) -> [E::G1Affine; 2] {
+        [self.sn.m, identity]
+    }
+
+    /// Checks the proofs of the hop which do not depend on the holder, i.e. the well-formedness
+    /// of the serial number, that the identity ciphertext encrypts the identity of the holder of the
+    /// serial number, and the validity of the RCCA ciphertext under the label of the hop.
+    pub fn check(&self, ctx: &CoinContext<E>) -> bool {
+        shared::verify(
+            &ctx.crs,
+            &hop_equations(ctx, &self.sn, &self.identity),
+            &self.sn_proof,
+        ) && self
+            .deposit
+            .check_proofs(&ctx.deposit_key, &deposit_label(&self.sn, &self.identity))
+            .is_ok()
+    }
+
+    /// Checks on deposit, with the RCCA decryption key of the bank, that the RCCA ciphertext of the
+    /// hop decrypts to the serial number of the hop under the label of the hop.
+    pub fn check_deposit(&self, deposit_key: &rcca::decrypt_key::DecryptKey<E>) -> bool {
+        deposit_key
+            .decrypt_compact_labelled(&self.deposit, &deposit_label(&self.sn, &self.identity))
+            .is_ok_and(|m| m == [self.sn.m, self.sn.n])
+    }
+
+    /// Checks that the hop encrypts the identity of the holder with the public key `pk`. Together with
+    /// `check`, the holder of the serial number is the user with `pk`.
+    pub fn verify(&self, ctx: &CoinContext<E>, pk: &PublicKey<E>) -> bool {
+        verify_identity(
+            &ctx.tracing_key,
+            &ctx.params,
+            pk,
+            &self.identity,
+            &self.identity_proof,
+        )
+    }
+
+    // Checks the tag of the previous holder against the serial number `prev` of the previous hop.
+    fn check_tag(&self, ctx: &CoinContext<E>, prev: &SerialNumber<E>) -> bool {
+        match &self.tag {
+            Some((tag, proof)) => shared::verify(
+                &ctx.crs,
+                &tag_equations(&ctx.params, prev, &self.sn, tag),
+                proof,
+            ),
+            None => false,
+        }
+    }
+}
+
+// The equations of `PublicKey::verify_serial_number` with Y = [sn-pf, pk]:
+// e(g1^-1, sn-pf) = e(M, g)^-1 and e(g2^-1, sn-pf) e(g2^-1, pk) = e(N, g)^-1
+fn serial_number_equations<E: Pairing>(
+    params: &Params<E>,
+    sn: &SerialNumber<E>,
+) -> [Equation<E, PairingProduct>; 2] {
+    let g1_neg: E::G1Affine = params.g1.into_group().neg().into();
+    let g2_neg: E::G1Affine = params.g2.into_group().neg().into();
+    [
+        Equation::new()
+            .pair(Const(g1_neg), Var(0))
+            .target(E::pairing(sn.m, params.g).neg()),
+        Equation::new()
+            .pair(Const(g2_neg), Var(0))
+            .pair(Const(g2_neg), Var(1))
+            .target(E::pairing(sn.n, params.g).neg()),
+    ]
+}
+
+// The label of the RCCA ciphertext of a hop, i.e. its serial number and its identity ciphertext. The
+// serial number is bound to the coin by the bank proof (withdrawal) or by the tag of the previous hop,
+// so the ciphertext cannot be moved to another hop or another coin.
+pub(crate) fn deposit_label<E: Pairing>(sn: &SerialNumber<E>, identity: &Ciphertext<E>) -> Vec<u8> {
+    let mut label = DEPOSIT_LABEL.to_vec();
+    (sn, identity)
+        .serialize_compressed(&mut label)
+        .expect("serialization to vec should not fail");
+    label
+}
+
+// The equations of the proof of a hop with X = [y1^v] and Y = [sn-pf, pk, g^v]: the serial number
+// equations of `sn`, and the equations showing that the ciphertext c = (g1^v, P y1^v, y2^v) encrypts
+// the identity P of pk under the tracing key (y1, y2):
+// e(g1^-1, g^v) = e(c0, g)^-1, e(y2^-1, g^v) = e(c2, g)^-1, e(y1^v, g) e(y1^-1, g^v) = 1 and
+// e(g1, pk) e(y1^v, g) = e(c1, g).
+fn hop_equations<E: Pairing>(
+    ctx: &CoinContext<E>,
+    sn: &SerialNumber<E>,
+    c: &Ciphertext<E>,
+) -> Vec<Equation<E, PairingProduct>> {
+    let (params, y) = (&ctx.params, &ctx.tracing_key.y);
+    let g1_neg: E::G1Affine = params.g1.into_group().neg().into();
+    let y1_neg: E::G1Affine = y.0.into_group().neg().into();
+    let y2_neg: E::G1Affine = y.1.into_group().neg().into();
+    let mut equs = Vec::from(serial_number_equations(params, sn));
+    equs.push(
+        Equation::new()
+            .pair(Const(g1_neg), Var(2))
+            .target(E::pairing(c.c0, params.g).neg()),
+    );
+    equs.push(
+        Equation::new()
+            .pair(Const(y2_neg), Var(2))
+            .target(E::pairing(c.c2, params.g).neg()),
+    );
+    equs.push(
+        Equation::new()
+            .pair(Var(0), Const(params.g))
+            .pair(Const(y1_neg), Var(2))
+            .target(PairingOutput::zero()),
+    );
+    equs.push(
+        Equation::new()
+            .pair(Const(params.g1), Var(1))
+            .pair(Var(0), Const(params.g))
+            .target(E::pairing(c.c1, params.g)),
+    );
+    equs
+}
+
+// The equations of `PublicKey::verify_tag` on the serial numbers `sn` and `sn_d` with Y = [tag-pf, pk],
+// where tag-pf = g^n is the `SerialNumberProof` of `sn`:
+// the serial number equations of `sn`, e(h1, tag-pf) e(M_d, pk) = e(A, g) and e(h2, tag-pf) e(N_d, pk) = e(B, g).
+// The serial number equations bind pk to the holder of `sn`.
+fn tag_equations<E: Pairing>(
+    params: &Params<E>,
+    sn: &SerialNumber<E>,
+    sn_d: &SerialNumber<E>,
+    tag: &Tag<E>,
+) -> Vec<Equation<E, PairingProduct>> {
+    let mut equs = Vec::from(serial_number_equations(params, sn));
+    equs.push(
+        Equation::new()
+            .pair(Const(params.h1), Var(0))
+            .pair(Const(sn_d.m), Var(1))
+            .target(E::pairing(tag.a, params.g)),
+    );
+    equs.push(
+        Equation::new()
+            .pair(Const(params.h2), Var(0))
+            .pair(Const(sn_d.n), Var(1))
+            .target(E::pairing(tag.b, params.g)),
+    );
+    equs
+}
+
+// The signature equation of the bank on [M, g1^sk] with X = [s_1, ..., s_k, g1^sk]:
+// Π e(s_k, B_k) e(g1^sk, pk_1^-1) = e(M, pk_0), as the bases B and the target pairs of `zk::prove`.
+fn bank_equation<E: Pairing>(
+    vk: &VerifyKey<E>,
+    hop: &Hop<E>,
+) -> (Vec<E::G2Affine>, [(E::G1Affine, E::G2Affine); 1]) {
+    let pk = <Tagged as LinearlyHomomorphicSignature<E>>::message_bases(vk);
+    let mut b = <Tagged as LinearlyHomomorphicSignature<E>>::equation_bases(vk, &hop.bank_tag());
+    b.push(pk[1].into_group().neg().into());
+    (b, [(hop.sn.m, pk[0])])
+}
+
+/// The secret part of a coin kept by its holder, i.e. the nonce of the serial number of the last hop.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CoinSecret<E: Pairing> {
+    pub(crate) n: E::ScalarField,
+}
+
+impl<E: Pairing> CoinSecret<E> {
+    pub fn new(n: E::ScalarField) -> Self {
+        Self { n }
+    }
+}
+
+/// A coin with the proof of the signature of the bank and the list of its hops, starting from the withdrawal.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::{test_rng, UniformRand};
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use transferable_ecash::coin::{Coin, CoinContext, CoinSecret, Hop};
+/// use transferable_ecash::lhsps::tagged;
+/// use transferable_ecash::{double_spending, encrypt_e, params::Params, rcca};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type Fr = <E as ark_ec::pairing::Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (_, tracing_key) = encrypt_e::key_gen_with_params(rng, &params);
+/// let (_, deposit_key) = rcca::key_gen::<E, _>(rng, 2);
+/// let (bank_sk, bank_vk) = tagged::setup::<E, _>(rng, 2);
+/// let crs = CRS::generate_crs(rng);
+/// let ctx = CoinContext::new(params, crs, tracing_key, deposit_key, bank_vk).unwrap();
+///
+/// // withdrawal
+/// let (sk, _) = double_spending::key_gen(rng, ctx.params());
+/// let n = Fr::rand(rng);
+/// let hop = Hop::new(rng, &ctx, &sk, n).unwrap();
+/// let message = hop.bank_message(sk.identity(ctx.params()));
+/// let bank_sig = bank_sk.sign(rng, &hop.bank_tag(), &message).unwrap();
+/// let coin = Coin::new(rng, &ctx, &sk, &bank_sig, hop, CoinSecret::new(n)).unwrap();
+/// assert!(coin.check(&ctx));
+///
+/// // transfer
+/// let (sk2, _) = double_spending::key_gen(rng, ctx.params());
+/// let n2 = Fr::rand(rng);
+/// let hop = Hop::new(rng, &ctx, &sk2, n2).unwrap();
+/// let coin = coin.transfer(rng, &ctx, &sk, hop).unwrap().with_secret(CoinSecret::new(n2));
+/// assert_eq!(coin.len_hops(), 2);
+/// assert!(coin.check(&ctx));
+///
+/// let bytes = coin.to_bytes().unwrap();
+/// assert_eq!(bytes.len(), coin.serialized_size());
+/// let coin = Coin::<E>::from_bytes(&bytes).unwrap();
+/// assert_eq!(coin.len_hops(), 2);
+/// ```
+#[derive(Clone)]
+pub struct Coin<E: Pairing> {
+    // the proof of the signature of the bank on [M, g1^sk] of the withdrawal (see `bank_equation`).
+    pub(crate) bank_proof: ZkProof<E>,
+    pub(crate) hops: Vec<Hop<E>>,
+    pub(crate) secret: Option<CoinSecret<E>>,
+}
+
+impl<E: Pairing> Coin<E> {
+    /// Creates a withdrawn coin with the hop of its withdrawal, where `bank_sig` is the signature of
+    /// the bank on `hop.bank_message` under `hop.bank_tag`, and `sk` is the secret key of the holder.
+    ///
+    /// It fails if the signature is not valid.
+    pub fn new<R: RngCore>(
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        sk: &SecretKey<E>,
+        bank_sig: &Signature<E>,
+        hop: Hop<E>,
+        secret: CoinSecret<E>,
+    ) -> Result<Self, CoinError> {
+        let identity = sk.identity(&ctx.params);
+        if !ctx
+            .bank_vk
+            .verify(&hop.bank_tag(), &hop.bank_message(identity), bank_sig)
+        {
+            return Err(CoinError::InvalidBankSignature);
+        }
+
+        let (b, target) = bank_equation(&ctx.bank_vk, &hop);
+        let mut x = <Tagged as LinearlyHomomorphicSignature<E>>::signature_elements(bank_sig);
+        x.push(identity);
+        let bank_proof = zk::prove(rng, &ctx.crs, &x, &b, &target)
+            .map_err(|_| CoinError::InvalidBankSignature)?;
+
+        Ok(Self {
+            bank_proof,
+            hops: vec![hop],
+            secret: Some(secret),
+        })
+    }
+
+    /// The number of hops, i.e. the number of transfers plus one.
+    pub fn len_hops(&self) -> usize {
+        self.hops.len()
+    }
+
+    pub fn hops(&self) -> &[Hop<E>] {
+        &self.hops
+    }
+
+    /// Whether the holder has the secret part of the coin.
+    pub fn has_secret(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// Returns the coin without the secret part, i.e. what is sent to the next holder or the bank.
+    pub fn without_secret(&self) -> Self {
+        Self {
+            bank_proof: self.bank_proof.clone(),
+            hops: self.hops.clone(),
+            secret: None,
+        }
+    }
+
+    /// Attaches the secret part of the receiver after a transfer.
+    pub fn with_secret(mut self, secret: CoinSecret<E>) -> Self {
+        self.secret = Some(secret);
+        self
+    }
+
+    /// Transfers the coin by the holder with the secret key `sk` to the hop of the next holder.
+    ///
+    /// The holder generates its double-spending tag on the serial number of the new hop, with the proof
+    /// of its consistency. The output coin does not have the secret part, which the receiver attaches
+    /// by `with_secret`.
+    pub fn transfer<R: RngCore>(
+        &self,
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        sk: &SecretKey<E>,
+        mut hop: Hop<E>,
+    ) -> Result<Self, CoinError> {
+        let secret = self.secret.as_ref().ok_or(CoinError::MissingSecret)?;
+        if self.hops.len() >= MAX_HOPS {
+            return Err(CoinError::TooManyHops);
+        }
+        let params = &ctx.params;
+        let prev = &self.hops[self.hops.len() - 1].sn;
+        let (tag, tag_pf) = sk.generate_tag(params, secret.n, &hop.sn);
+        let tag_proof = shared::prove(
+            rng,
+            &ctx.crs,
+            &tag_equations(params, prev, &hop.sn, &tag),
+            &[],
+            &[tag_pf.t_pf, sk.public_key(params).pk],
+        )
+        .map_err(|_| CoinError::InvalidHop)?;
+        hop.tag = Some((tag, tag_proof));
+
+        let mut coin = self.without_secret();
+        coin.hops.push(hop);
+        Ok(coin)
+    }
+
+    /// Checks the structure of the coin, the proof of the signature of the bank, the proofs of all
+    /// hops (see `Hop::check`), and the tag of every transfer against the serial number of the previous hop.
+    pub fn check(&self, ctx: &CoinContext<E>) -> bool {
+        let first = match self.hops.first() {
+            Some(first) => first,
+            None => return false,
+        };
+        self.hops.len() <= MAX_HOPS
+            && first.tag.is_none()
+            && first.check(ctx)
+            && self.check_bank_proof(ctx)
+            && self
+                .hops
+                .windows(2)
+                .all(|w| w[1].check(ctx) && w[1].check_tag(ctx, &w[0].sn))
+    }
+
+    /// Checks the proof of the signature of the bank on the withdrawal, with the tag and M of the first hop.
+    pub fn check_bank_proof(&self, ctx: &CoinContext<E>) -> bool {
+        match self.hops.first() {
+            Some(first) => {
+                let (b, target) = bank_equation(&ctx.bank_vk, first);
+                zk::verify(&ctx.crs, &self.bank_proof, &b, &target)
+            }
+            None => false,
+        }
+    }
+
+    /// The size in bytes of the serialized coin (see `to_bytes`).
+    pub fn serialized_size(&self) -> usize {
+        COIN_VERSION.compressed_size() + self.body().compressed_size()
+    }
+
+    /// Serializes the coin, prefixed with the format version.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CoinError> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        COIN_VERSION.serialize_compressed(&mut bytes)?;
+        self.body().serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes a coin serialized by `to_bytes`. It fails on an unknown format version, or
+    /// if the coin has more than `MAX_HOPS` hops, which is checked before the hops are decoded.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, CoinError> {
+        let version = u16::deserialize_compressed(&mut bytes)?;
+        if version != COIN_VERSION {
+            return Err(CoinError::UnsupportedVersion(version));
+        }
+        let bank_proof = ZkProof::<E>::deserialize_compressed(&mut bytes)?;
+
+        // the length prefix of the hops
+        let len = u64::deserialize_compressed(&mut bytes)?;
+        if len == 0 {
+            return Err(SerializationError::InvalidData.into());
+        }
+        if len > MAX_HOPS as u64 {
+            return Err(CoinError::TooManyHops);
+        }
+        let hops = (0..len)
+            .map(|_| Hop::<E>::deserialize_compressed(&mut bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let secret = Option::<CoinSecret<E>>::deserialize_compressed(&mut bytes)?;
+        Ok(Self {
+            bank_proof,
+            hops,
+            secret,
+        })
+    }
+
+    fn body(&self) -> (&ZkProof<E>, &Vec<Hop<E>>, &Option<CoinSecret<E>>) {
+        (&self.bank_proof, &self.hops, &self.secret)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::{rand::RngCore, test_rng, UniformRand};
+    use groth_sahai::{AbstractCrs, CRS};
+
+    use super::{
+        deposit_label, Coin, CoinContext, CoinError, CoinSecret, Hop, COIN_VERSION, MAX_HOPS,
+    };
+    use crate::double_spending::{self, secret_key::SecretKey};
+    use crate::encrypt_e::{self, decrypt_key::DecryptKey};
+    use crate::lhsps::tagged::{self, signing_key::SigningKey};
+    use crate::params::Params;
+    use crate::rcca;
+
+    type E = ark_bls12_381::Bls12_381;
+    type Fr = <E as Pairing>::ScalarField;
+
+    // returns the context with the tracing key and the signing key of the bank.
+    pub(crate) fn context<R: RngCore>(
+        rng: &mut R,
+    ) -> (DecryptKey<E>, SigningKey<E>, CoinContext<E>) {
+        let (tracing_dk, _, bank_sk, ctx) = bank_context(rng);
+        (tracing_dk, bank_sk, ctx)
+    }
+
+    // returns the context with the tracing key, the RCCA decryption key and the signing key of the bank.
+    pub(crate) fn bank_context<R: RngCore>(
+        rng: &mut R,
+    ) -> (
+        DecryptKey<E>,
+        rcca::decrypt_key::DecryptKey<E>,
+        SigningKey<E>,
+        CoinContext<E>,
+    ) {
+        let params = Params::<E>::rand(rng);
+        let (tracing_dk, tracing_key) = encrypt_e::key_gen_with_params(rng, &params);
+        let (deposit_dk, deposit_key) = rcca::key_gen::<E, _>(rng, 2);
+        let (bank_sk, bank_vk) = tagged::setup::<E, _>(rng, 2);
+        let crs = CRS::generate_crs(rng);
+        let ctx = CoinContext::new(params, crs, tracing_key, deposit_key, bank_vk).unwrap();
+        (tracing_dk, deposit_dk, bank_sk, ctx)
+    }
+
+    pub(crate) fn withdraw<R: RngCore>(
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        bank_sk: &SigningKey<E>,
+        sk: &SecretKey<E>,
+    ) -> Coin<E> {
+        let n = Fr::rand(rng);
+        let hop = Hop::new(rng, ctx, sk, n).unwrap();
+        let bank_sig = bank_sk
+            .sign(
+                rng,
+                &hop.bank_tag(),
+                &hop.bank_message(sk.identity(&ctx.params)),
+            )
+            .unwrap();
+        Coin::new(rng, ctx, sk, &bank_sig, hop, CoinSecret::new(n)).unwrap()
+    }
+
+    // withdraws a coin by holders[0] and transfers it along the holders.
+    pub(crate) fn coin_held_by<R: RngCore>(
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        bank_sk: &SigningKey<E>,
+        holders: &[&SecretKey<E>],
+    ) -> Coin<E> {
+        let mut coin = withdraw(rng, ctx, bank_sk, holders[0]);
+        for w in holders.windows(2) {
+            let n = Fr::rand(rng);
+            let hop = Hop::new(rng, ctx, w[1], n).unwrap();
+            coin = coin
+                .transfer(rng, ctx, w[0], hop)
+                .unwrap()
+                .with_secret(CoinSecret::new(n));
+        }
+        coin
+    }
+
+    #[test]
+    fn test_coin_transfers() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let users: Vec<_> = (0..4)
+            .map(|_| double_spending::key_gen::<E, _>(rng, &ctx.params))
+            .collect();
+
+        let mut coin = withdraw(rng, &ctx, &bank_sk, &users[0].0);
+        assert!(coin.check(&ctx));
+        assert!(coin.check_bank_proof(&ctx));
+
+        let mut sizes = vec![coin.serialized_size()];
+        for i in 1..4 {
+            let n = Fr::rand(rng);
+            let hop = Hop::new(rng, &ctx, &users[i].0, n).unwrap();
+            // only the holder can generate the tag
+            assert!(matches!(
+                coin.transfer(rng, &ctx, &users[i].0, hop.clone()),
+                Err(CoinError::InvalidHop)
+            ));
+            let sent = coin.transfer(rng, &ctx, &users[i - 1].0, hop).unwrap();
+            assert!(!sent.has_secret());
+            // the coin cannot be transferred again without the secret part
+            assert!(matches!(
+                sent.transfer(rng, &ctx, &users[i - 1].0, sent.hops[0].clone()),
+                Err(CoinError::MissingSecret)
+            ));
+            coin = sent.with_secret(CoinSecret::new(n));
+            sizes.push(coin.serialized_size());
+
+            assert!(coin.check(&ctx));
+            assert!(coin.hops()[i].verify(&ctx, &users[i].1));
+            assert!(!coin.hops()[i].verify(&ctx, &users[i - 1].1));
+        }
+        assert_eq!(coin.len_hops(), 4);
+
+        // every hop adds the same number of bytes
+        let hop_size = sizes[1] - sizes[0];
+        assert!(sizes.windows(2).all(|w| w[1] - w[0] == hop_size));
+
+        let bytes = coin.to_bytes().unwrap();
+        assert_eq!(bytes.len(), coin.serialized_size());
+        let coin_d = Coin::<E>::from_bytes(&bytes).unwrap();
+        assert_eq!(coin_d.to_bytes().unwrap(), bytes);
+        assert!(coin_d.check(&ctx));
+
+        // unknown version
+        let mut bytes_v = bytes.clone();
+        bytes_v[..2].copy_from_slice(&(COIN_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Coin::<E>::from_bytes(&bytes_v),
+            Err(CoinError::UnsupportedVersion(v)) if v == COIN_VERSION + 1
+        ));
+        assert!(Coin::<E>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // a swapped hop fails the check
+        let mut bad = coin.clone();
+        bad.hops.swap(0, 1);
+        assert!(!bad.check(&ctx));
+
+        // a tag on another serial number fails the check
+        let mut bad = coin.clone();
+        bad.hops[2].tag = coin.hops[3].tag.clone();
+        assert!(!bad.check(&ctx));
+
+        // a hop removed from the middle breaks the chain of tags
+        let mut bad = coin.clone();
+        bad.hops.remove(1);
+        assert!(!bad.check(&ctx));
+    }
+
+    #[test]
+    fn test_copied_identity() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (payer, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+        let (receiver, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+        let (victim, victim_pk) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+
+        // the receiver copies the identity of the victim from another hop into its own hop
+        let other = Hop::new(rng, &ctx, &victim, Fr::rand(rng)).unwrap();
+        let mut hop = Hop::new(rng, &ctx, &receiver, Fr::rand(rng)).unwrap();
+        assert!(hop.check(&ctx));
+        hop.identity = other.identity.clone();
+        hop.identity_proof = other.identity_proof;
+        assert!(hop.verify(&ctx, &victim_pk));
+        assert!(!hop.check(&ctx));
+
+        let coin = withdraw(rng, &ctx, &bank_sk, &payer);
+        let coin = coin.transfer(rng, &ctx, &payer, hop).unwrap();
+        assert!(!coin.check(&ctx));
+    }
+
+    #[test]
+    fn test_deposit_ciphertext() {
+        let rng = &mut test_rng();
+        let (_, deposit_dk, bank_sk, ctx) = bank_context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+        let (sk2, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[&sk, &sk2, &sk]);
+        assert!(coin.hops().iter().all(|hop| hop.check_deposit(&deposit_dk)));
+
+        // the ciphertext of another hop does not verify under the label of the hop
+        let mut bad = coin.clone();
+        bad.hops[1].deposit = coin.hops[2].deposit.clone();
+        assert!(!bad.hops[1].check(&ctx));
+        assert!(!bad.hops[1].check_deposit(&deposit_dk));
+        assert!(!bad.check(&ctx));
+
+        // a ciphertext of another serial number under the label of the hop is caught on deposit
+        let hop = &mut bad.hops[1];
+        let other = &coin.hops[2].sn;
+        hop.deposit = ctx
+            .deposit_key
+            .encrypt_compact_labelled(
+                rng,
+                &[other.m, other.n],
+                &deposit_label(&hop.sn, &hop.identity),
+            )
+            .unwrap();
+        assert!(bad.check(&ctx));
+        assert!(!bad.hops[1].check_deposit(&deposit_dk));
+
+        // nor does it decrypt under another key
+        let (_, deposit_dk2, _, _) = bank_context(rng);
+        assert!(!coin.hops[0].check_deposit(&deposit_dk2));
+    }
+
+    #[test]
+    fn test_bank_signature() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+        let (sk2, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+
+        let n = Fr::rand(rng);
+        let hop = Hop::new(rng, &ctx, &sk, n).unwrap();
+        let identity = sk.identity(&ctx.params);
+        let m = hop.bank_message(identity);
+
+        // a signature under another tag, on another identity, or by another key
+        let (bank_sk2, _) = tagged::setup::<E, _>(rng, 2);
+        let bad_sigs = [
+            bank_sk.sign(rng, b"another coin", &m).unwrap(),
+            bank_sk
+                .sign(
+                    rng,
+                    &hop.bank_tag(),
+                    &hop.bank_message(sk2.identity(&ctx.params)),
+                )
+                .unwrap(),
+            bank_sk2.sign(rng, &hop.bank_tag(), &m).unwrap(),
+        ];
+        for sig in &bad_sigs {
+            assert!(matches!(
+                Coin::new(rng, &ctx, &sk, sig, hop.clone(), CoinSecret::new(n)),
+                Err(CoinError::InvalidBankSignature)
+            ));
+        }
+
+        let sig = bank_sk.sign(rng, &hop.bank_tag(), &m).unwrap();
+        let coin = Coin::new(rng, &ctx, &sk, &sig, hop, CoinSecret::new(n)).unwrap();
+        assert!(coin.check(&ctx));
+
+        // the proof of the bank signature does not move to another coin
+        let other = withdraw(rng, &ctx, &bank_sk, &sk);
+        let mut bad = coin.clone();
+        bad.bank_proof = other.bank_proof.clone();
+        assert!(!bad.check_bank_proof(&ctx));
+        assert!(!bad.check(&ctx));
+
+        // nor to another bank
+        let (_, _, ctx2) = context(rng);
+        assert!(!coin.check_bank_proof(&ctx2));
+    }
+
+    #[test]
+    fn test_max_hops() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, &ctx.params);
+
+        let mut coin = withdraw(rng, &ctx, &bank_sk, &sk);
+        let hop = coin.hops[0].clone();
+        coin.hops = vec![hop.clone(); MAX_HOPS];
+        assert!(matches!(
+            coin.transfer(rng, &ctx, &sk, hop),
+            Err(CoinError::TooManyHops)
+        ));
+
+        coin.hops.push(coin.hops[0].clone());
+        let bytes = coin.to_bytes().unwrap();
+        assert!(matches!(
+            Coin::<E>::from_bytes(&bytes),
+            Err(CoinError::TooManyHops)
+        ));
+
+        // the number of hops is checked before the hops are decoded
+        let mut bytes = Vec::new();
+        COIN_VERSION.serialize_compressed(&mut bytes).unwrap();
+        coin.bank_proof.serialize_compressed(&mut bytes).unwrap();
+        u64::MAX.serialize_compressed(&mut bytes).unwrap();
+        assert!(matches!(
+            Coin::<E>::from_bytes(&bytes),
+            Err(CoinError::TooManyHops)
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! This module provides generation of the Groth-Sahai common reference string (CRS)
+//! together with its trapdoor, which the bank keeps for extraction in the security proofs.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::{UniformRand, Zero};
+use groth_sahai::data_structures::{Com1, Com2};
+use groth_sahai::CRS;
+use std::ops::Mul;
+
+/// The trapdoor of a binding CRS generated by `generate_crs_with_trapdoor`.
+///
+/// The CRS is u = [(p1, p1^a1), (p1^t1, p1^(a1 t1))] and v = [(p2, p2^a2), (p2^t2, p2^(a2 t2))].
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CrsTrapdoor<E: Pairing> {
+    pub(crate) a1: E::ScalarField,
+    pub(crate) t1: E::ScalarField,
+    pub(crate) a2: E::ScalarField,
+    pub(crate) t2: E::ScalarField,
+}
+
+/// Generates a binding CRS for the Groth-Sahai proof system, and its trapdoor.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use transferable_ecash::crs;
+///
+/// let rng = &mut test_rng();
+/// let (crs, td) = crs::generate_crs_with_trapdoor::<ark_bls12_381::Bls12_381, _>(rng);
+/// ```
+pub fn generate_crs_with_trapdoor<E: Pairing, R: RngCore>(rng: &mut R) -> (CRS<E>, CrsTrapdoor<E>) {
+    generate(rng, false)
+}
+
+/// Generates a hiding CRS for the Groth-Sahai proof system, and its trapdoor.
+///
+/// The CRS is u = [(p1, p1^a1), (p1^t1, p1^(a1 t1 - 1))] and v = [(p2, p2^a2), (p2^t2, p2^(a2 t2 - 1))].
+/// It is indistinguishable from a binding CRS under SXDH. Commitments are perfectly hiding, and the
+/// trapdoor allows simulating zero-knowledge proofs (see `proof::zk`).
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use transferable_ecash::crs;
+///
+/// let rng = &mut test_rng();
+/// let (crs, td) = crs::generate_hiding_crs_with_trapdoor::<ark_bls12_381::Bls12_381, _>(rng);
+/// assert!(td.matches_hiding(&crs));
+/// assert!(!td.matches(&crs));
+/// ```
+pub fn generate_hiding_crs_with_trapdoor<E: Pairing, R: RngCore>(
+    rng: &mut R,
+) -> (CRS<E>, CrsTrapdoor<E>) {
+    generate(rng, true)
+}
+
+fn generate<E: Pairing, R: RngCore>(rng: &mut R, hiding: bool) -> (CRS<E>, CrsTrapdoor<E>) {
+    let p1 = E::G1Affine::rand(rng);
+    let p2 = E::G2Affine::rand(rng);
+
+    let td = CrsTrapdoor {
+        a1: E::ScalarField::rand(rng),
+        t1: E::ScalarField::rand(rng),
+        a2: E::ScalarField::rand(rng),
+        t2: E::ScalarField::rand(rng),
+    };
+
+    let q1: E::G1Affine = p1.mul(td.a1).into();
+    let q2: E::G2Affine = p2.mul(td.a2).into();
+    // binding: u2 = u1^t1, v2 = v1^t2
+    // hiding: u2 = u1^t1 - (0, p1), v2 = v1^t2 - (0, p2)
+    let (e1, e2) = if hiding {
+        (p1.into_group(), p2.into_group())
+    } else {
+        (E::G1::zero(), E::G2::zero())
+    };
+    let u = vec![
+        Com1::<E>(p1, q1),
+        Com1::<E>(p1.mul(td.t1).into(), (q1.mul(td.t1) - e1).into()),
+    ];
+    let v = vec![
+        Com2::<E>(p2, q2),
+        Com2::<E>(p2.mul(td.t2).into(), (q2.mul(td.t2) - e2).into()),
+    ];
+
+    let crs = CRS::<E> {
+        u,
+        v,
+        g1_gen: p1,
+        g2_gen: p2,
+        gt_gen: E::pairing(p1, p2),
+    };
+    (crs, td)
+}
+
+impl<E: Pairing> CrsTrapdoor<E> {
+    /// Checks that the trapdoor belongs to the given CRS.
+    pub fn matches(&self, crs: &CRS<E>) -> bool {
+        crs.u.len() == 2
+            && crs.v.len() == 2
+            && crs.u[0].1 == crs.u[0].0.mul(self.a1).into()
+            && crs.u[1].0 == crs.u[0].0.mul(self.t1).into()
+            && crs.u[1].1 == crs.u[0].1.mul(self.t1).into()
+            && crs.v[0].1 == crs.v[0].0.mul(self.a2).into()
+            && crs.v[1].0 == crs.v[0].0.mul(self.t2).into()
+            && crs.v[1].1 == crs.v[0].1.mul(self.t2).into()
+    }
+
+    /// Checks that the trapdoor belongs to the given hiding CRS.
+    pub fn matches_hiding(&self, crs: &CRS<E>) -> bool {
+        crs.u.len() == 2
+            && crs.v.len() == 2
+            && crs.g1_gen == crs.u[0].0
+            && crs.g2_gen == crs.v[0].0
+            && crs.u[0].1 == crs.u[0].0.mul(self.a1).into()
+            && crs.u[1].0 == crs.u[0].0.mul(self.t1).into()
+            && crs.u[1].1 == (crs.u[0].1.mul(self.t1) - crs.g1_gen).into()
+            && crs.v[0].1 == crs.v[0].0.mul(self.a2).into()
+            && crs.v[1].0 == crs.v[0].0.mul(self.t2).into()
+            && crs.v[1].1 == (crs.v[0].1.mul(self.t2) - crs.g2_gen).into()
+    }
+}
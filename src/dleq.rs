@@ -0,0 +1,93 @@
+//! This module implements the non-interactive Chaum-Pedersen proof of equality of
+//! discrete logarithms in G1, made non-interactive by the Fiat-Shamir transform.
+
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use sha2::{Digest, Sha256};
+use std::ops::Mul;
+
+/// A proof of knowledge of x such that y = g^x and z = h^x.
+#[derive(Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DleqProof<E: Pairing> {
+    pub(crate) c: E::ScalarField,
+    pub(crate) s: E::ScalarField,
+}
+
+impl<E: Pairing> DleqProof<E> {
+    /// Proves that y = g^x and z = h^x. The `domain` separates proofs of different protocols.
+    pub(crate) fn prove<R: RngCore>(
+        rng: &mut R,
+        domain: &[u8],
+        (g, y): (E::G1Affine, E::G1Affine),
+        (h, z): (E::G1Affine, E::G1Affine),
+        x: E::ScalarField,
+    ) -> Self {
+        let k = E::ScalarField::rand(rng);
+        // a1 = g^k, a2 = h^k
+        let a1 = g.mul(k).into();
+        let a2 = h.mul(k).into();
+        let c = challenge::<E>(domain, &[g, y, h, z, a1, a2]);
+        // s = k - c x
+        let s = k - c * x;
+        Self { c, s }
+    }
+
+    /// Verifies the proof that log_g(y) = log_h(z).
+    pub fn verify(
+        &self,
+        domain: &[u8],
+        (g, y): (E::G1Affine, E::G1Affine),
+        (h, z): (E::G1Affine, E::G1Affine),
+    ) -> bool {
+        // a1 = g^s y^c, a2 = h^s z^c
+        let a1 = (g.mul(self.s) + y.mul(self.c)).into();
+        let a2 = (h.mul(self.s) + z.mul(self.c)).into();
+        self.c == challenge::<E>(domain, &[g, y, h, z, a1, a2])
+    }
+}
+
+/// Hashes the domain and the group elements into a scalar (Fiat-Shamir challenge).
+pub(crate) fn challenge<E: Pairing>(domain: &[u8], points: &[E::G1Affine]) -> E::ScalarField {
+    let mut hasher = Sha256::new();
+    hasher.update((domain.len() as u64).to_le_bytes());
+    hasher.update(domain);
+    for p in points {
+        let mut bytes = Vec::new();
+        p.serialize_compressed(&mut bytes)
+            .expect("serialization to vec should not fail");
+        hasher.update(bytes);
+    }
+    E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+    use std::ops::Mul;
+
+    use super::DleqProof;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_dleq() {
+        let rng = &mut test_rng();
+        let (g, h) = (G1::rand(rng), G1::rand(rng));
+        let x = Fr::rand(rng);
+        let (y, z) = (g.mul(x).into(), h.mul(x).into());
+
+        let pf = DleqProof::<E>::prove(rng, b"test", (g, y), (h, z), x);
+        assert!(pf.verify(b"test", (g, y), (h, z)));
+        // wrong domain
+        assert!(!pf.verify(b"other", (g, y), (h, z)));
+        // different discrete logarithms
+        let z_ = h.mul(x + Fr::from(1u64)).into();
+        assert!(!pf.verify(b"test", (g, y), (h, z_)));
+    }
+}
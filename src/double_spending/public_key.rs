@@ -33,6 +33,11 @@ impl<E: Pairing> PublicKey<E> {
             || lhs == rhs_0 + E::pairing(params.h2, proof.tx)
     }
 
+    /// Checks that `identity` is the G1 counterpart g1^sk of the public key g^sk, i.e. e(identity, g) == e(g1, pk).
+    pub fn is_identity(&self, params: &Params<E>, identity: E::G1Affine) -> bool {
+        E::pairing(identity, params.g) == E::pairing(params.g1, self.pk)
+    }
+
     /// On input a public key, a serial number and a message, checks their consistency.
     pub fn verify_first_serial_number(
         &self,
@@ -1,20 +1,34 @@
 use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use std::ops::Mul;
 
 use crate::params::Params;
 
 use super::{
     message::Message,
+    public_key::PublicKey,
     serial_number::{SerialNumber, SerialNumberProof},
     tag::{Tag, TagProof},
 };
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SecretKey<E: Pairing> {
     pub(crate) sk: E::ScalarField,
 }
 
 impl<E: Pairing> SecretKey<E> {
+    /// The public key g^sk of the user.
+    pub fn public_key(&self, params: &Params<E>) -> PublicKey<E> {
+        PublicKey {
+            pk: params.g.mul(self.sk).into(),
+        }
+    }
+
+    /// The identity g1^sk of the user, i.e. the G1 counterpart of the public key g^sk.
+    pub fn identity(&self, params: &Params<E>) -> E::G1Affine {
+        params.g1.mul(self.sk).into()
+    }
+
     /// The serial-number generation function, on input a secret key and
     /// a nonce, outputs a serial-number component and a message which is
     /// signed by the bank using a signature scheme.
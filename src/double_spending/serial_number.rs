@@ -1,11 +1,13 @@
 use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SerialNumber<E: Pairing> {
     pub(crate) m: E::G1Affine,
     pub(crate) n: E::G1Affine,
 }
 
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SerialNumberProof<E: Pairing> {
     pub(crate) sn_pf: E::G2Affine,
 }
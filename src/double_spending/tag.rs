@@ -1,10 +1,13 @@
 use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct Tag<E: Pairing> {
     pub(crate) a: E::G1Affine,
     pub(crate) b: E::G1Affine,
 }
 
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct TagProof<E: Pairing> {
     pub(crate) t_pf: E::G2Affine,
 }
@@ -1,6 +1,33 @@
-use ark_ec::pairing::Pairing;
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::ops::{Add, Mul, Neg, Sub};
 
-#[derive(Clone, PartialEq, Eq)]
+/// A pair of ElGamal ciphertexts (c0, c1) and (c0, c2) of (m1, m2) with shared randomness v, i.e.
+/// c0 = g^v, c1 = m1 y1^v and c2 = m2 y2^v.
+///
+/// Ciphertexts under the same key are homomorphic: `c + c'` encrypts (m1 m1', m2 m2') with randomness
+/// v + v', `c - c'` encrypts (m1 / m1', m2 / m2'), and `c * k` encrypts (m1^k, m2^k) with randomness kv.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::encrypt_e;
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+/// type Fr = <E as Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let (dk, ek) = encrypt_e::key_gen::<E, _>(rng);
+/// let (m1, m2, n1, n2) = (G1::rand(rng), G1::rand(rng), G1::rand(rng), G1::rand(rng));
+/// let k = Fr::rand(rng);
+///
+/// let c = ek.encrypt(rng, m1, m2) + ek.encrypt(rng, n1, n2) * k;
+/// assert_eq!(dk.decrypt(&c), ((n1 * k + m1).into(), (n2 * k + m2).into()));
+/// ```
+#[derive(Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct Ciphertext<E: Pairing> {
     pub c0: E::G1Affine,
     pub c1: E::G1Affine,
@@ -45,3 +72,131 @@ impl<'a, E: Pairing> From<&'a Ciphertext<E>>
         )
     }
 }
+
+impl<'a, E: Pairing> Add<&'a Ciphertext<E>> for &'a Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn add(self, rhs: &'a Ciphertext<E>) -> Ciphertext<E> {
+        Ciphertext {
+            c0: (self.c0 + rhs.c0).into(),
+            c1: (self.c1 + rhs.c1).into(),
+            c2: (self.c2 + rhs.c2).into(),
+        }
+    }
+}
+
+impl<E: Pairing> Add for Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn add(self, rhs: Ciphertext<E>) -> Ciphertext<E> {
+        &self + &rhs
+    }
+}
+
+impl<'a, E: Pairing> Neg for &'a Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn neg(self) -> Ciphertext<E> {
+        Ciphertext {
+            c0: self.c0.into_group().neg().into(),
+            c1: self.c1.into_group().neg().into(),
+            c2: self.c2.into_group().neg().into(),
+        }
+    }
+}
+
+impl<E: Pairing> Neg for Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn neg(self) -> Ciphertext<E> {
+        -&self
+    }
+}
+
+impl<'a, E: Pairing> Sub<&'a Ciphertext<E>> for &'a Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn sub(self, rhs: &'a Ciphertext<E>) -> Ciphertext<E> {
+        self + &(-rhs)
+    }
+}
+
+impl<E: Pairing> Sub for Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn sub(self, rhs: Ciphertext<E>) -> Ciphertext<E> {
+        &self - &rhs
+    }
+}
+
+impl<'a, E: Pairing> Mul<E::ScalarField> for &'a Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn mul(self, k: E::ScalarField) -> Ciphertext<E> {
+        Ciphertext {
+            c0: self.c0.mul(k).into(),
+            c1: self.c1.mul(k).into(),
+            c2: self.c2.mul(k).into(),
+        }
+    }
+}
+
+impl<E: Pairing> Mul<E::ScalarField> for Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn mul(self, k: E::ScalarField) -> Ciphertext<E> {
+        &self * k
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{pairing::Pairing, AffineRepr};
+    use ark_std::{test_rng, UniformRand};
+
+    use crate::encrypt_e::key_gen;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_homomorphic_operations() {
+        let rng = &mut test_rng();
+        let (dk, ek) = key_gen::<E, _>(rng);
+        let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+        let (n1, n2) = (G1::rand(rng), G1::rand(rng));
+        let (v, w, k) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+
+        let c = ek.encrypt_with(m1, m2, v);
+        let d = ek.encrypt_with(n1, n2, w);
+
+        // the shared randomness is added and multiplied along
+        let sum: (G1, G1) = ((m1 + n1).into(), (m2 + n2).into());
+        assert!(ek.verify(sum.0, sum.1, &(&c + &d), v + w));
+        let diff: (G1, G1) = ((m1 - n1).into(), (m2 - n2).into());
+        assert!(ek.verify(diff.0, diff.1, &(&c - &d), v - w));
+        let prod: (G1, G1) = ((m1 * k).into(), (m2 * k).into());
+        assert!(ek.verify(prod.0, prod.1, &(&c * k), v * k));
+        assert_eq!(dk.decrypt(&(c.clone() + d.clone() - d)), (m1, m2));
+
+        // aggregate encrypted identifiers
+        let ids: Vec<(G1, G1)> = (0..5).map(|_| (G1::rand(rng), G1::rand(rng))).collect();
+        let total = ids
+            .iter()
+            .map(|(id1, id2)| ek.encrypt(rng, *id1, *id2))
+            .reduce(|acc, c| acc + c)
+            .unwrap();
+        let expected = ids.iter().fold((G1::zero(), G1::zero()), |acc, id| {
+            ((acc.0 + id.0).into(), (acc.1 + id.1).into())
+        });
+        assert_eq!(dk.decrypt(&total), expected);
+
+        // blinding with an encryption of zero keeps the message and changes the ciphertext
+        let z = ek.encrypt_zero(rng);
+        assert_eq!(dk.decrypt(&z), (G1::zero(), G1::zero()));
+        let blinded = &c + &z;
+        assert!(blinded != c);
+        assert_eq!(dk.decrypt(&blinded), (m1, m2));
+    }
+}
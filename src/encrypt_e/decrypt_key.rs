@@ -1,6 +1,19 @@
 use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::rand::RngCore;
+use std::ops::{Mul, Neg};
 
-use super::ciphertext::Ciphertext;
+use crate::dleq::DleqProof;
+
+use super::{
+    ciphertext::Ciphertext,
+    decryption_proof::{DecryptionProof, DOMAIN},
+    encrypt_key::EncryptKey,
+};
 
 pub struct DecryptKey<E: Pairing> {
     // decryption keys with dk1 and dk2. They must use the same generator.
@@ -8,6 +21,10 @@ pub struct DecryptKey<E: Pairing> {
         bls_elgamal::DecryptKey<E::G1>,
         bls_elgamal::DecryptKey<E::G1>,
     ),
+
+    // the generator and the secret scalars (dk1, dk2) behind `inner`.
+    pub(crate) g: E::G1Affine,
+    pub(crate) dk: (E::ScalarField, E::ScalarField),
 }
 
 impl<E: Pairing> DecryptKey<E> {
@@ -36,4 +53,102 @@ impl<E: Pairing> DecryptKey<E> {
         let m2 = self.inner.1.decrypt(c2);
         (m1, m2)
     }
+
+    /// Decrypt a ciphertext into message (m1, m2), with a proof that the decryption is
+    /// correct under the encryption key.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::encrypt_e::{self, decryption_proof::verify_decryption};
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = encrypt_e::key_gen::<E, _>(rng);
+    /// let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+    /// let c = ek.encrypt(rng, m1, m2);
+    /// let (m, pf) = dk.decrypt_with_proof(rng, &c);
+    /// assert_eq!((m1, m2), m);
+    /// assert!(verify_decryption(&ek, &c, m, &pf));
+    /// ```
+    pub fn decrypt_with_proof<R: RngCore>(
+        &self,
+        rng: &mut R,
+        c: &Ciphertext<E>,
+    ) -> ((E::G1Affine, E::G1Affine), DecryptionProof<E>) {
+        let (m1, m2) = self.decrypt(c);
+        let ek = self.encrypt_key();
+        // log_g(y_i) = log_c0(c_i / m_i)
+        let d1: E::G1Affine = (c.c1 + m1.into_group().neg()).into();
+        let d2: E::G1Affine = (c.c2 + m2.into_group().neg()).into();
+        let pf1 = DleqProof::prove(rng, DOMAIN, (self.g, ek.y.0), (c.c0, d1), self.dk.0);
+        let pf2 = DleqProof::prove(rng, DOMAIN, (self.g, ek.y.1), (c.c0, d2), self.dk.1);
+        ((m1, m2), DecryptionProof { pf1, pf2 })
+    }
+}
+
+impl<E: Pairing> DecryptKey<E> {
+    pub(crate) fn new(g: E::G1Affine, dk1: E::ScalarField, dk2: E::ScalarField) -> Self {
+        Self {
+            inner: (
+                bls_elgamal::DecryptKey::new(g, dk1),
+                bls_elgamal::DecryptKey::new(g, dk2),
+            ),
+            g,
+            dk: (dk1, dk2),
+        }
+    }
+
+    pub(crate) fn encrypt_key(&self) -> EncryptKey<E> {
+        EncryptKey {
+            inner: (
+                self.inner.0.encrypt_key().clone(),
+                self.inner.1.encrypt_key().clone(),
+            ),
+            g: self.g,
+            y: (self.g.mul(self.dk.0).into(), self.g.mul(self.dk.1).into()),
+        }
+    }
+}
+
+// Only (g, dk1, dk2) is serialized. The ElGamal keys are rebuilt on deserialization.
+impl<E: Pairing> CanonicalSerialize for DecryptKey<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.g.serialize_with_mode(&mut writer, compress)?;
+        self.dk.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.g.serialized_size(compress) + self.dk.serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for DecryptKey<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.g.check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for DecryptKey<E> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let g = E::G1Affine::deserialize_with_mode(&mut reader, compress, validate)?;
+        let (dk1, dk2) = <(E::ScalarField, E::ScalarField)>::deserialize_with_mode(
+            &mut reader,
+            compress,
+            validate,
+        )?;
+        Ok(Self::new(g, dk1, dk2))
+    }
 }
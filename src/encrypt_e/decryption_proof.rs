@@ -0,0 +1,61 @@
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::ops::Neg;
+
+use crate::dleq::DleqProof;
+
+use super::{ciphertext::Ciphertext, encrypt_key::EncryptKey};
+
+pub(crate) const DOMAIN: &[u8] = b"transferable-ecash/encrypt_e/decryption";
+
+/// A proof that (m1, m2) is the decryption of a ciphertext, generated by `DecryptKey::decrypt_with_proof`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DecryptionProof<E: Pairing> {
+    pub(crate) pf1: DleqProof<E>,
+    pub(crate) pf2: DleqProof<E>,
+}
+
+/// Verifies that (m1, m2) is the correct decryption of the ciphertext `c` under the encryption key `ek`.
+pub fn verify_decryption<E: Pairing>(
+    ek: &EncryptKey<E>,
+    c: &Ciphertext<E>,
+    (m1, m2): (E::G1Affine, E::G1Affine),
+    proof: &DecryptionProof<E>,
+) -> bool {
+    // log_g(y_i) = log_c0(c_i / m_i)
+    let d1: E::G1Affine = (c.c1 + m1.into_group().neg()).into();
+    let d2: E::G1Affine = (c.c2 + m2.into_group().neg()).into();
+    proof.pf1.verify(DOMAIN, (ek.g, ek.y.0), (c.c0, d1))
+        && proof.pf2.verify(DOMAIN, (ek.g, ek.y.1), (c.c0, d2))
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+
+    use super::verify_decryption;
+    use crate::encrypt_e::key_gen;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_dishonest_decryption() {
+        let rng = &mut test_rng();
+        let (dk, ek) = key_gen::<E, _>(rng);
+        let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+        let c = ek.encrypt(rng, m1, m2);
+        let (m, pf) = dk.decrypt_with_proof(rng, &c);
+        assert!(verify_decryption(&ek, &c, m, &pf));
+
+        // claim another message
+        assert!(!verify_decryption(&ek, &c, (m1, G1::rand(rng)), &pf));
+        assert!(!verify_decryption(&ek, &c, (G1::rand(rng), m2), &pf));
+
+        // proof under another key
+        let (_, ek2) = key_gen::<E, _>(rng);
+        assert!(!verify_decryption(&ek2, &c, m, &pf));
+    }
+}
@@ -1,7 +1,9 @@
-use ark_ec::pairing::Pairing;
+use ark_ec::{pairing::Pairing, AffineRepr};
 use ark_std::rand::RngCore;
 use ark_std::UniformRand;
 
+use crate::params::Params;
+
 use super::ciphertext::Ciphertext;
 
 /// The encryption key for the encryption scheme E - ElGamal encryption.
@@ -15,6 +17,10 @@ pub struct EncryptKey<E: Pairing> {
         // D2
         bls_elgamal::EncryptKey<E::G1>,
     ),
+
+    // the generator g and the public keys (y1, y2) = (g^dk1, g^dk2) behind `inner`.
+    pub(crate) g: E::G1Affine,
+    pub(crate) y: (E::G1Affine, E::G1Affine),
 }
 
 impl<E: Pairing> EncryptKey<E> {
@@ -79,6 +85,29 @@ impl<E: Pairing> EncryptKey<E> {
         (c1, c2).into()
     }
 
+    /// Encrypt the message (1, 1), i.e. (0, 0) in additive notation. Adding it to a ciphertext
+    /// blinds the ciphertext without changing its message.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::encrypt_e;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = encrypt_e::key_gen::<E, _>(rng);
+    /// let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+    /// let c = ek.encrypt(rng, m1, m2) + ek.encrypt_zero(rng);
+    /// assert_eq!(dk.decrypt(&c), (m1, m2));
+    /// ```
+    pub fn encrypt_zero<R: RngCore>(&self, rng: &mut R) -> Ciphertext<E> {
+        self.encrypt(rng, E::G1Affine::zero(), E::G1Affine::zero())
+    }
+
     /// Randomize a ciphertext.
     ///
     /// # Example
@@ -137,6 +166,16 @@ impl<E: Pairing> EncryptKey<E> {
         self.encrypt_with(m1, m2, v) == *c
     }
 
+    /// Returns the generator g of the ElGamal keys.
+    pub fn generator(&self) -> E::G1Affine {
+        self.g
+    }
+
+    /// Checks that the key was built on the generator g1 of `params`, e.g. by `encrypt_e::key_gen_with_params`.
+    pub fn check_params(&self, params: &Params<E>) -> bool {
+        self.g == params.g1
+    }
+
     pub fn adapt_proof(&self) {
         todo!()
     }
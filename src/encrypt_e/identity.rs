@@ -0,0 +1,185 @@
+//! This module implements the verifiable encryption of a user's identity under the scheme E.
+//!
+//! The identity of a user with the double-spending key pair (sk, pk = g^sk) in G2 is its G1
+//! counterpart P = g1^sk, which is consistent with pk iff e(P, g) = e(g1, pk). The ciphertext
+//! c = (g1^v, P y1^v, y2^v) encrypts (P, 1), and the proof shows knowledge of v such that
+//!
+//! - c0 = g1^v,
+//! - c2 = y2^v,
+//! - e(c1, g) / e(g1, pk) = e(y1, g)^v,
+//!
+//! so it can be checked against pk without revealing P.
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use sha2::{Digest, Sha256};
+use std::ops::{Mul, Neg};
+
+use crate::double_spending::public_key::PublicKey;
+use crate::params::Params;
+
+use super::{ciphertext::Ciphertext, encrypt_key::EncryptKey};
+
+const DOMAIN: &[u8] = b"transferable-ecash/encrypt_e/identity";
+
+/// A proof that a ciphertext encrypts the identity of a double-spending public key.
+#[derive(Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IdentityProof<E: Pairing> {
+    pub(crate) c: E::ScalarField,
+    pub(crate) s: E::ScalarField,
+}
+
+/// Encrypts the identity P = g1^sk of the public key `pk` under `ek`, with a proof of consistency with `pk`.
+///
+/// It fails if `ek` is not built on the generator g1 of `params` (see `encrypt_e::key_gen_with_params`),
+/// or if `identity` is not the identity of `pk`.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use transferable_ecash::{double_spending, encrypt_e, params::Params};
+///
+/// type E = ark_bls12_381::Bls12_381;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (dk, ek) = encrypt_e::key_gen_with_params(rng, &params);
+/// let (sk, pk) = double_spending::key_gen(rng, &params);
+///
+/// let identity = sk.identity(&params);
+/// let (c, pf) = encrypt_e::identity::encrypt_identity(&ek, &params, &pk, identity, rng).unwrap();
+/// assert!(encrypt_e::identity::verify_identity(&ek, &params, &pk, &c, &pf));
+/// assert!(pk.is_identity(&params, dk.decrypt(&c).0));
+/// ```
+pub fn encrypt_identity<E: Pairing, R: RngCore>(
+    ek: &EncryptKey<E>,
+    params: &Params<E>,
+    pk: &PublicKey<E>,
+    identity: E::G1Affine,
+    rng: &mut R,
+) -> Result<(Ciphertext<E>, IdentityProof<E>), ()> {
+    let v = E::ScalarField::rand(rng);
+    encrypt_identity_with(ek, params, pk, identity, v, rng)
+}
+
+// Encrypts the identity with the randomness `v`, e.g. to prove further statements on v (see `coin::Hop`).
+pub(crate) fn encrypt_identity_with<E: Pairing, R: RngCore>(
+    ek: &EncryptKey<E>,
+    params: &Params<E>,
+    pk: &PublicKey<E>,
+    identity: E::G1Affine,
+    v: E::ScalarField,
+    rng: &mut R,
+) -> Result<(Ciphertext<E>, IdentityProof<E>), ()> {
+    if !ek.check_params(params) || !pk.is_identity(params, identity) {
+        return Err(());
+    }
+
+    let c = ek.encrypt_with(identity, E::G1Affine::zero(), v);
+
+    let k = E::ScalarField::rand(rng);
+    // a0 = g1^k, a2 = y2^k, a_t = e(y1, g)^k
+    let a0 = params.g1.mul(k).into();
+    let a2 = ek.y.1.mul(k).into();
+    let a_t = E::pairing(ek.y.0.mul(k), params.g);
+    let ch = challenge(ek, params, pk, &c, (a0, a2, a_t));
+    // s = k - ch v
+    let s = k - ch * v;
+
+    Ok((c, IdentityProof { c: ch, s }))
+}
+
+/// Verifies that the ciphertext `c` encrypts the identity of the public key `pk` under `ek`.
+pub fn verify_identity<E: Pairing>(
+    ek: &EncryptKey<E>,
+    params: &Params<E>,
+    pk: &PublicKey<E>,
+    c: &Ciphertext<E>,
+    pf: &IdentityProof<E>,
+) -> bool {
+    if !ek.check_params(params) {
+        return false;
+    }
+
+    // t = e(c1, g) / e(g1, pk) = e(y1, g)^v
+    let t = E::pairing(c.c1, params.g) + E::pairing(params.g1.into_group().neg(), pk.pk);
+    // a0 = g1^s c0^ch, a2 = y2^s c2^ch, a_t = e(y1, g)^s t^ch
+    let a0 = (params.g1.mul(pf.s) + c.c0.mul(pf.c)).into();
+    let a2 = (ek.y.1.mul(pf.s) + c.c2.mul(pf.c)).into();
+    let a_t = E::pairing(ek.y.0.mul(pf.s), params.g) + t.mul(pf.c);
+    pf.c == challenge(ek, params, pk, c, (a0, a2, a_t))
+}
+
+fn challenge<E: Pairing>(
+    ek: &EncryptKey<E>,
+    params: &Params<E>,
+    pk: &PublicKey<E>,
+    c: &Ciphertext<E>,
+    (a0, a2, a_t): (E::G1Affine, E::G1Affine, PairingOutput<E>),
+) -> E::ScalarField {
+    let mut bytes = Vec::new();
+    (
+        [params.g1, ek.y.0, ek.y.1, c.c0, c.c1, c.c2, a0, a2],
+        [params.g, pk.pk],
+        a_t,
+    )
+        .serialize_compressed(&mut bytes)
+        .expect("serialization to vec should not fail");
+
+    let mut hasher = Sha256::new();
+    hasher.update((DOMAIN.len() as u64).to_le_bytes());
+    hasher.update(DOMAIN);
+    hasher.update(bytes);
+    E::ScalarField::from_le_bytes_mod_order(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+
+    use super::{encrypt_identity, verify_identity};
+    use crate::double_spending;
+    use crate::encrypt_e::{key_gen, key_gen_with_params};
+    use crate::params::Params;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_encrypt_identity() {
+        let rng = &mut test_rng();
+        let params = Params::<E>::rand(rng);
+        let (dk, ek) = key_gen_with_params(rng, &params);
+        let (sk, pk) = double_spending::key_gen(rng, &params);
+        let (sk2, pk2) = double_spending::key_gen(rng, &params);
+
+        let identity = sk.identity(&params);
+        let (c, pf) = encrypt_identity(&ek, &params, &pk, identity, rng).unwrap();
+        assert!(verify_identity(&ek, &params, &pk, &c, &pf));
+        assert_eq!(dk.decrypt(&c).0, identity);
+
+        // the proof does not hold for another user, a rerandomized ciphertext or another key
+        assert!(!verify_identity(&ek, &params, &pk2, &c, &pf));
+        let c_r = ek.rerandomize(rng, &c);
+        assert!(!verify_identity(&ek, &params, &pk, &c_r, &pf));
+        let (_, ek2) = key_gen_with_params(rng, &params);
+        assert!(!verify_identity(&ek2, &params, &pk, &c, &pf));
+
+        // another user's identity, a random point, or a key not built on params
+        assert!(encrypt_identity(&ek, &params, &pk, sk2.identity(&params), rng).is_err());
+        assert!(encrypt_identity(&ek, &params, &pk2, G1::rand(rng), rng).is_err());
+        let (_, ek_r) = key_gen::<E, _>(rng);
+        assert!(encrypt_identity(&ek_r, &params, &pk, identity, rng).is_err());
+
+        // a ciphertext of another identity with an honest proof for it
+        let (c2, pf2) = encrypt_identity(&ek, &params, &pk2, sk2.identity(&params), rng).unwrap();
+        assert!(verify_identity(&ek, &params, &pk2, &c2, &pf2));
+        assert!(!verify_identity(&ek, &params, &pk, &c2, &pf2));
+    }
+}
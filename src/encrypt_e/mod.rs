@@ -2,7 +2,10 @@
 
 pub mod ciphertext;
 pub mod decrypt_key;
+pub mod decryption_proof;
 pub mod encrypt_key;
+pub mod identity;
+pub mod threshold;
 
 use ark_ec::pairing::Pairing;
 use ark_std::rand::RngCore;
@@ -11,6 +14,8 @@ use ark_std::UniformRand;
 use decrypt_key::DecryptKey;
 use encrypt_key::EncryptKey;
 
+use crate::params::Params;
+
 /// Generates key pair for the encryption scheme E - ElGamal vector encryption.
 ///
 /// # Example
@@ -24,16 +29,74 @@ use encrypt_key::EncryptKey;
 /// ```
 pub fn key_gen<E: Pairing, R: RngCore>(rng: &mut R) -> (DecryptKey<E>, EncryptKey<E>) {
     let g = E::G1Affine::rand(rng);
+    key_gen_with_generator(rng, g)
+}
+
+/// Generates key pair for the encryption scheme E on the generator g1 of the shared `Params`,
+/// so that the encrypted key material and the equality proofs use the same bases as `double_spending`.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use transferable_ecash::{encrypt_e, params::Params};
+///
+/// type E = ark_bls12_381::Bls12_381;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (dk, ek) = encrypt_e::key_gen_with_params(rng, &params);
+/// assert!(ek.check_params(&params));
+/// ```
+pub fn key_gen_with_params<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    params: &Params<E>,
+) -> (DecryptKey<E>, EncryptKey<E>) {
+    key_gen_with_generator(rng, params.g1)
+}
+
+fn key_gen_with_generator<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    g: E::G1Affine,
+) -> (DecryptKey<E>, EncryptKey<E>) {
     let dk1 = E::ScalarField::rand(rng);
     let dk2 = E::ScalarField::rand(rng);
 
-    let dk1 = bls_elgamal::DecryptKey::new(g, dk1);
-    let dk2 = bls_elgamal::DecryptKey::new(g, dk2);
+    let dk = DecryptKey::new(g, dk1, dk2);
+    let ek = dk.encrypt_key();
+    (dk, ek)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+    use std::ops::Mul;
+
+    use super::{key_gen, key_gen_with_params};
+    use crate::params::Params;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_key_gen_with_params() {
+        let rng = &mut test_rng();
+        let params = Params::<E>::rand(rng);
+        let (dk, ek) = key_gen_with_params(rng, &params);
+        assert!(ek.check_params(&params));
+        assert_eq!(ek.generator(), params.g1);
+        // y_i = g1^dk_i
+        assert_eq!(ek.y.0, params.g1.mul(dk.dk.0).into());
+        assert_eq!(ek.y.1, params.g1.mul(dk.dk.1).into());
+
+        let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+        let c = ek.encrypt(rng, m1, m2);
+        assert_eq!(dk.decrypt(&c), (m1, m2));
 
-    let ek1 = dk1.encrypt_key().clone();
-    let ek2 = dk2.encrypt_key().clone();
-    (
-        DecryptKey { inner: (dk1, dk2) },
-        EncryptKey { inner: (ek1, ek2) },
-    )
+        // keys on another generator
+        let (_, ek2) = key_gen::<E, _>(rng);
+        assert!(!ek2.check_params(&params));
+        assert!(!ek.check_params(&Params::<E>::rand(rng)));
+    }
 }
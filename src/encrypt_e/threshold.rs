@@ -0,0 +1,255 @@
+//! This module implements t-of-n threshold decryption for the encryption scheme E.
+//!
+//! The scalars dk1 and dk2 of the tracing key are Shamir-shared among n holders. Each holder
+//! produces a partial decryption together with Chaum-Pedersen proofs of correctness, and any
+//! t valid partial decryptions recover the message (m1, m2).
+
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Zero;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use std::ops::{Mul, Neg};
+
+use crate::dleq::DleqProof;
+use crate::shamir;
+
+use super::{ciphertext::Ciphertext, decrypt_key::DecryptKey, encrypt_key::EncryptKey};
+
+const DOMAIN: &[u8] = b"transferable-ecash/encrypt_e/threshold";
+
+/// A share of the decryption key (dk1, dk2) held by one party.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyShare<E: Pairing> {
+    // the evaluation point of the share, starting from 1.
+    pub(crate) index: u64,
+    pub(crate) g: E::G1Affine,
+    pub(crate) s: (E::ScalarField, E::ScalarField),
+}
+
+/// The public verification key of a key share, i.e. (g^s1, g^s2).
+#[derive(Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ShareVerifyKey<E: Pairing> {
+    pub(crate) index: u64,
+    pub(crate) g: E::G1Affine,
+    pub(crate) vk: (E::G1Affine, E::G1Affine),
+}
+
+/// A partial decryption (c0^s1, c0^s2) with proofs that it is consistent with the verification key.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PartialDecryption<E: Pairing> {
+    pub(crate) index: u64,
+    pub(crate) d: (E::G1Affine, E::G1Affine),
+    pub(crate) proofs: (DleqProof<E>, DleqProof<E>),
+}
+
+/// Generates a t-of-n threshold key pair for the encryption scheme E.
+///
+/// It outputs n key shares, their verification keys and the encryption key.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::encrypt_e::threshold;
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+///
+/// let rng = &mut test_rng();
+/// let (shares, vks, ek) = threshold::key_gen::<E, _>(rng, 2, 3).unwrap();
+/// let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+/// let c = ek.encrypt(rng, m1, m2);
+///
+/// let pds = vec![
+///     shares[0].partial_decrypt(rng, &c),
+///     shares[2].partial_decrypt(rng, &c),
+/// ];
+/// let (m1_, m2_) = threshold::combine(2, &vks, &c, &pds).unwrap();
+/// assert_eq!((m1, m2), (m1_, m2_));
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn key_gen<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    t: usize,
+    n: usize,
+) -> Result<(Vec<KeyShare<E>>, Vec<ShareVerifyKey<E>>, EncryptKey<E>), ()> {
+    if t == 0 || t > n {
+        return Err(());
+    }
+
+    let g = E::G1Affine::rand(rng);
+    let dk1 = E::ScalarField::rand(rng);
+    let dk2 = E::ScalarField::rand(rng);
+
+    // the dealer's key is dropped after sharing.
+    let ek = DecryptKey::<E>::new(g, dk1, dk2).encrypt_key();
+
+    let s1 = shamir::split(rng, dk1, t, n);
+    let s2 = shamir::split(rng, dk2, t, n);
+
+    let shares: Vec<KeyShare<E>> = (1..=n as u64)
+        .zip(s1.into_iter().zip(s2))
+        .map(|(index, s)| KeyShare { index, g, s })
+        .collect();
+    let vks = shares.iter().map(|share| share.verify_key()).collect();
+
+    Ok((shares, vks, ek))
+}
+
+impl<E: Pairing> KeyShare<E> {
+    /// Returns the public verification key of this share.
+    pub fn verify_key(&self) -> ShareVerifyKey<E> {
+        ShareVerifyKey {
+            index: self.index,
+            g: self.g,
+            vk: (self.g.mul(self.s.0).into(), self.g.mul(self.s.1).into()),
+        }
+    }
+
+    /// Computes the partial decryption (c0^s1, c0^s2) with proofs of correctness.
+    pub fn partial_decrypt<R: RngCore>(
+        &self,
+        rng: &mut R,
+        c: &Ciphertext<E>,
+    ) -> PartialDecryption<E> {
+        let vk = self.verify_key();
+        let d: (E::G1Affine, E::G1Affine) = (c.c0.mul(self.s.0).into(), c.c0.mul(self.s.1).into());
+        // log_g(vk_1) = log_c0(d_1), log_g(vk_2) = log_c0(d_2)
+        let proofs = (
+            DleqProof::prove(rng, DOMAIN, (self.g, vk.vk.0), (c.c0, d.0), self.s.0),
+            DleqProof::prove(rng, DOMAIN, (self.g, vk.vk.1), (c.c0, d.1), self.s.1),
+        );
+        PartialDecryption {
+            index: self.index,
+            d,
+            proofs,
+        }
+    }
+}
+
+impl<E: Pairing> ShareVerifyKey<E> {
+    /// Checks that the partial decryption is correctly computed from the key share of this verification key.
+    pub fn verify(&self, c: &Ciphertext<E>, pd: &PartialDecryption<E>) -> bool {
+        pd.index == self.index
+            && pd
+                .proofs
+                .0
+                .verify(DOMAIN, (self.g, self.vk.0), (c.c0, pd.d.0))
+            && pd
+                .proofs
+                .1
+                .verify(DOMAIN, (self.g, self.vk.1), (c.c0, pd.d.1))
+    }
+}
+
+/// Combines partial decryptions into the message (m1, m2).
+///
+/// Partial decryptions that do not verify against `vks` are ignored. It fails if there are
+/// less than `t` valid partial decryptions from distinct holders.
+pub fn combine<E: Pairing>(
+    t: usize,
+    vks: &[ShareVerifyKey<E>],
+    c: &Ciphertext<E>,
+    pds: &[PartialDecryption<E>],
+) -> Result<(E::G1Affine, E::G1Affine), ()> {
+    let mut valid: Vec<&PartialDecryption<E>> = Vec::new();
+    for pd in pds {
+        if valid.len() == t {
+            break;
+        }
+        if valid.iter().any(|v| v.index == pd.index) {
+            continue;
+        }
+        if vks.iter().any(|vk| vk.verify(c, pd)) {
+            valid.push(pd);
+        }
+    }
+    if t == 0 || valid.len() < t {
+        return Err(());
+    }
+
+    // c0^dk = Π (c0^s_i)^lambda_i
+    let indices: Vec<u64> = valid.iter().map(|pd| pd.index).collect();
+    let (d1, d2) = valid
+        .iter()
+        .map(|pd| {
+            let lambda = shamir::lagrange_at_zero::<E::ScalarField>(pd.index, &indices);
+            (pd.d.0.mul(lambda), pd.d.1.mul(lambda))
+        })
+        .fold((E::G1::zero(), E::G1::zero()), |acc, d| {
+            (acc.0 + d.0, acc.1 + d.1)
+        });
+
+    // m = c / c0^dk
+    let m1 = (c.c1.into_group() + d1.neg()).into_affine();
+    let m2 = (c.c2.into_group() + d2.neg()).into_affine();
+    Ok((m1, m2))
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+
+    use super::{combine, key_gen};
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_any_t_shares() {
+        let rng = &mut test_rng();
+        let (shares, vks, ek) = key_gen::<E, _>(rng, 3, 5).unwrap();
+        let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+        let c = ek.encrypt(rng, m1, m2);
+
+        for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1]] {
+            let pds: Vec<_> = subset
+                .iter()
+                .map(|i| shares[*i].partial_decrypt(rng, &c))
+                .collect();
+            assert_eq!(combine(3, &vks, &c, &pds).unwrap(), (m1, m2));
+        }
+
+        // not enough shares
+        let pds: Vec<_> = shares[..2]
+            .iter()
+            .map(|share| share.partial_decrypt(rng, &c))
+            .collect();
+        assert!(combine(3, &vks, &c, &pds).is_err());
+
+        // duplicated shares do not count twice
+        let pds = vec![pds[0].clone(), pds[0].clone(), pds[1].clone()];
+        assert!(combine(3, &vks, &c, &pds).is_err());
+    }
+
+    #[test]
+    fn test_invalid_partial_decryption() {
+        let rng = &mut test_rng();
+        let (shares, vks, ek) = key_gen::<E, _>(rng, 2, 3).unwrap();
+        let (m1, m2) = (G1::rand(rng), G1::rand(rng));
+        let c = ek.encrypt(rng, m1, m2);
+
+        let mut bad = shares[0].partial_decrypt(rng, &c);
+        bad.d.0 = G1::rand(rng);
+        assert!(!vks[0].verify(&c, &bad));
+
+        // the invalid share is skipped, and the remaining valid shares are enough.
+        let pds = vec![
+            bad,
+            shares[1].partial_decrypt(rng, &c),
+            shares[2].partial_decrypt(rng, &c),
+        ];
+        assert_eq!(combine(2, &vks, &c, &pds).unwrap(), (m1, m2));
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let rng = &mut test_rng();
+        assert!(key_gen::<E, _>(rng, 0, 3).is_err());
+        assert!(key_gen::<E, _>(rng, 4, 3).is_err());
+    }
+}
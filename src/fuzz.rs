@@ -0,0 +1,164 @@
+//! Fuzzes the deserialization of ciphertexts and proofs, which arrive from untrusted parties, into
+//! the functions checking them. The inputs are valid encodings with flipped bytes, overwritten
+//! length prefixes or truncations, and arbitrary bytes. Every input must be either rejected or
+//! accepted as the original plaintext, without panicking.
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{test_rng, UniformRand, Zero};
+use groth_sahai::{AbstractCrs, CRS};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestRunner};
+use std::ops::Mul;
+
+use crate::proof::{
+    shared::{self, SharedProof},
+    zk::{self, ZkProof},
+    Equation, PairingProduct,
+    Term::{Const, Var},
+};
+use crate::rcca::{self, bytes_ciphertext::BytesCiphertext, compact_ciphertext::CompactCiphertext};
+
+type E = ark_bls12_381::Bls12_381;
+type G1 = <E as Pairing>::G1Affine;
+type G2 = <E as Pairing>::G2Affine;
+type Fr = <E as Pairing>::ScalarField;
+
+// the deserialized types, in the order of the encodings
+const COMPACT: usize = 0;
+const BYTES: usize = 1;
+const ZK: usize = 2;
+const SHARED: usize = 3;
+const TARGETS: usize = 4;
+
+#[derive(Clone, Debug)]
+enum Mutation {
+    /// XORs the bytes at the positions (modulo the length) with the masks, then truncates.
+    Flip {
+        flips: Vec<(usize, u8)>,
+        truncate: Option<usize>,
+    },
+    /// Overwrites 8 bytes at the position (modulo the length), e.g. the length prefix of a vector.
+    Overwrite { at: usize, value: u64 },
+    /// Replaces the encoding.
+    Replace(Vec<u8>),
+}
+
+impl Mutation {
+    fn apply(&self, valid: &[u8]) -> Vec<u8> {
+        let mut bytes = valid.to_vec();
+        match self {
+            Mutation::Flip { flips, truncate } => {
+                for (i, mask) in flips {
+                    let i = i % bytes.len();
+                    bytes[i] ^= mask;
+                }
+                if let Some(t) = truncate {
+                    bytes.truncate(t % (bytes.len() + 1));
+                }
+            }
+            Mutation::Overwrite { at, value } => {
+                let at = at % (bytes.len() - 7);
+                bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+            }
+            Mutation::Replace(replaced) => bytes = replaced.clone(),
+        }
+        bytes
+    }
+}
+
+fn inputs() -> impl Strategy<Value = (usize, Mutation)> {
+    let mutation = prop_oneof![
+        (
+            vec((any::<usize>(), 1..=u8::MAX), 1..4),
+            any::<Option<usize>>()
+        )
+            .prop_map(|(flips, truncate)| Mutation::Flip { flips, truncate }),
+        (any::<usize>(), any::<u64>()).prop_map(|(at, value)| Mutation::Overwrite { at, value }),
+        vec(any::<u8>(), 0..1024).prop_map(Mutation::Replace),
+    ];
+    (0..TARGETS, mutation)
+}
+
+fn to_bytes<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    t.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_fuzz_deserialize() {
+    let rng = &mut test_rng();
+
+    // the rcca ciphertexts
+    let (dk, ek) = rcca::key_gen::<E, _>(rng, 2);
+    let m = (0..2).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+    let data = b"receipt #42".to_vec();
+    let c_compact = ek.encrypt_compact(rng, &m).unwrap();
+    let c_bytes = ek.encrypt_bytes(rng, &data).unwrap();
+
+    // e(X, B) = e(P, Q), where X = P^s and B = Q^(1/s)
+    let crs = CRS::<E>::generate_crs(rng);
+    let (p, q, s) = (G1::rand(rng), G2::rand(rng), Fr::rand(rng));
+    let (x, b): (G1, G2) = (p.mul(s).into(), q.mul(s.inverse().unwrap()).into());
+    let zk_target = [(p, q)];
+    let zk_pf = zk::prove(rng, &crs, &[x], &[b], &zk_target).unwrap();
+
+    // e(X_0, g2) = e(g1, Y_0)
+    let (g1, g2) = (crs.g1_gen, crs.g2_gen);
+    let equs = [Equation::<E, PairingProduct>::new()
+        .pair(Var(0), Const(g2))
+        .pair(Const(-g1), Var(0))
+        .target(PairingOutput::zero())];
+    let shared_pf =
+        shared::prove(rng, &crs, &equs, &[g1.mul(s).into()], &[g2.mul(s).into()]).unwrap();
+
+    let mut encodings = vec![Vec::new(); TARGETS];
+    encodings[COMPACT] = to_bytes(&c_compact);
+    encodings[BYTES] = to_bytes(&c_bytes);
+    encodings[ZK] = to_bytes(&zk_pf);
+    encodings[SHARED] = to_bytes(&shared_pf);
+
+    let mut runner = TestRunner::new(Config {
+        cases: 256,
+        failure_persistence: None,
+        ..Config::default()
+    });
+    runner
+        .run(&inputs(), |(target, mutation)| {
+            let bytes = mutation.apply(&encodings[target]);
+            match target {
+                COMPACT => {
+                    if let Ok(c) = CompactCiphertext::<E>::deserialize_compressed(&bytes[..]) {
+                        let checked = c.check_proofs(&ek);
+                        let decrypted = dk.decrypt_compact(&c);
+                        prop_assert_eq!(checked.is_ok(), decrypted.is_ok());
+                        if let Ok(m_d) = decrypted {
+                            prop_assert_eq!(&m_d, &m);
+                        }
+                    }
+                }
+                BYTES => {
+                    if let Ok(c) = BytesCiphertext::<E>::deserialize_compressed(&bytes[..]) {
+                        if let Ok(data_d) = dk.decrypt_bytes(&c) {
+                            prop_assert_eq!(&data_d, &data);
+                        }
+                    }
+                }
+                ZK => {
+                    if let Ok(pf) = ZkProof::<E>::deserialize_compressed(&bytes[..]) {
+                        zk::verify(&crs, &pf, &[b], &zk_target);
+                    }
+                }
+                _ => {
+                    if let Ok(pf) = SharedProof::<E>::deserialize_compressed(&bytes[..]) {
+                        shared::verify(&crs, &equs, &pf);
+                    }
+                }
+            }
+            Ok(())
+        })
+        .unwrap();
+}
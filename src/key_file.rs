@@ -0,0 +1,370 @@
+//! This module implements a password-encrypted key file format for storing secrets,
+//! such as the user's `double_spending::SecretKey`, the bank's `lhsps::tagged::SigningKey`,
+//! `encrypt_e::DecryptKey` and `crs::CrsTrapdoor`.
+//!
+//! The file layout is:
+//!
+//! | field        | size     | description                                          |
+//! |--------------|----------|------------------------------------------------------|
+//! | magic        | 8        | `b"TECASHKF"`                                        |
+//! | version      | 2        | format version, little-endian                        |
+//! | curve id     | 32       | SHA-256 of the compressed generators of G1 and G2    |
+//! | m_cost       | 4        | Argon2id memory cost in KiB, little-endian           |
+//! | t_cost       | 4        | Argon2id number of iterations, little-endian         |
+//! | p_cost       | 4        | Argon2id degree of parallelism, little-endian        |
+//! | salt         | 16       | Argon2id salt                                        |
+//! | nonce        | 24       | XChaCha20-Poly1305 nonce                             |
+//! | ciphertext   | variable | AEAD encryption of the canonical (compressed) serialization |
+//!
+//! The header (all fields before the ciphertext) is authenticated as associated data.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use zeroize::Zeroizing;
+
+/// The magic bytes at the beginning of every key file.
+pub const MAGIC: [u8; 8] = *b"TECASHKF";
+/// The current version of the key file format.
+pub const VERSION: u16 = 1;
+
+/// The maximum Argon2id memory cost in KiB (256 MiB) accepted from a key file.
+pub const MAX_M_COST: u32 = 256 * 1024;
+/// The maximum Argon2id number of iterations accepted from a key file.
+pub const MAX_T_COST: u32 = 16;
+/// The maximum Argon2id degree of parallelism accepted from a key file.
+pub const MAX_P_COST: u32 = 16;
+
+const CURVE_ID_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 2 + CURVE_ID_LEN + 4 * 3 + SALT_LEN + NONCE_LEN;
+
+#[derive(Debug)]
+pub enum KeyFileError {
+    Io(std::io::Error),
+    /// The file does not start with `MAGIC`.
+    InvalidMagic,
+    /// The file was written by an unknown version of the format.
+    UnsupportedVersion(u16),
+    /// The file was written for a different pairing curve.
+    CurveMismatch,
+    /// The key derivation parameters are invalid.
+    Kdf,
+    /// The key derivation parameters exceed `MAX_M_COST`, `MAX_T_COST` or `MAX_P_COST`.
+    KdfLimits,
+    /// Wrong password, or the file has been tampered with.
+    Decryption,
+    Serialization(ark_serialize::SerializationError),
+}
+
+impl From<std::io::Error> for KeyFileError {
+    fn from(e: std::io::Error) -> Self {
+        KeyFileError::Io(e)
+    }
+}
+
+impl From<ark_serialize::SerializationError> for KeyFileError {
+    fn from(e: ark_serialize::SerializationError) -> Self {
+        KeyFileError::Serialization(e)
+    }
+}
+
+/// Parameters of the memory-hard key derivation function (Argon2id).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// memory cost in KiB
+    pub m_cost: u32,
+    /// number of iterations
+    pub t_cost: u32,
+    /// degree of parallelism
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    /// Whether the parameters are within the caps accepted when loading a key file.
+    pub fn within_limits(&self) -> bool {
+        self.m_cost <= MAX_M_COST && self.t_cost <= MAX_T_COST && self.p_cost <= MAX_P_COST
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: argon2::Params::DEFAULT_M_COST,
+            t_cost: argon2::Params::DEFAULT_T_COST,
+            p_cost: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// The identifier of the pairing curve, computed as SHA-256 of the compressed generators of G1 and G2.
+pub fn curve_id<E: Pairing>() -> [u8; CURVE_ID_LEN] {
+    let mut bytes = Vec::new();
+    E::G1Affine::generator()
+        .serialize_compressed(&mut bytes)
+        .expect("serialization to vec should not fail");
+    E::G2Affine::generator()
+        .serialize_compressed(&mut bytes)
+        .expect("serialization to vec should not fail");
+    Sha256::digest(&bytes).into()
+}
+
+/// Encrypts `value` with a key derived from `password` and writes it to `writer` in the key file format,
+/// using the default KDF parameters.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use transferable_ecash::{double_spending, key_file, params::Params};
+///
+/// type E = ark_bls12_381::Bls12_381;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (sk, _) = double_spending::key_gen(rng, &params);
+///
+/// let mut file = Vec::new();
+/// key_file::save_encrypted::<E, _, _, _>(&mut file, &sk, b"password", rng).unwrap();
+/// let sk_: double_spending::secret_key::SecretKey<E> =
+///     key_file::load_encrypted::<E, _, _>(file.as_slice(), b"password").unwrap();
+/// assert!(sk == sk_);
+/// ```
+pub fn save_encrypted<E: Pairing, T: CanonicalSerialize, W: Write, R: RngCore + CryptoRng>(
+    writer: W,
+    value: &T,
+    password: &[u8],
+    rng: &mut R,
+) -> Result<(), KeyFileError> {
+    save_encrypted_with::<E, _, _, _>(writer, value, password, KdfParams::default(), rng)
+}
+
+/// Same as `save_encrypted`, but with the given KDF parameters. It fails if the parameters
+/// exceed the caps, as the file could not be loaded.
+pub fn save_encrypted_with<E: Pairing, T: CanonicalSerialize, W: Write, R: RngCore + CryptoRng>(
+    mut writer: W,
+    value: &T,
+    password: &[u8],
+    kdf: KdfParams,
+    rng: &mut R,
+) -> Result<(), KeyFileError> {
+    if !kdf.within_limits() {
+        return Err(KeyFileError::KdfLimits);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&curve_id::<E>());
+    header.extend_from_slice(&kdf.m_cost.to_le_bytes());
+    header.extend_from_slice(&kdf.t_cost.to_le_bytes());
+    header.extend_from_slice(&kdf.p_cost.to_le_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let mut plaintext = Zeroizing::new(Vec::new());
+    value.serialize_compressed(&mut *plaintext)?;
+
+    let key = derive_key(password, &salt, kdf)?;
+    let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| KeyFileError::Decryption)?;
+
+    writer.write_all(&header)?;
+    writer.write_all(&ciphertext)?;
+    Ok(())
+}
+
+/// Reads a key file from `reader` and decrypts it with a key derived from `password`.
+///
+/// It fails if the magic, version or curve identifier do not match, if the KDF parameters exceed
+/// the caps (before any key derivation), or if the password is wrong.
+pub fn load_encrypted<E: Pairing, T: CanonicalDeserialize, R: Read>(
+    mut reader: R,
+    password: &[u8],
+) -> Result<T, KeyFileError> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+
+    let (magic, rest) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(KeyFileError::InvalidMagic);
+    }
+    let (version, rest) = rest.split_at(2);
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version != VERSION {
+        return Err(KeyFileError::UnsupportedVersion(version));
+    }
+    let (id, rest) = rest.split_at(CURVE_ID_LEN);
+    if id != curve_id::<E>() {
+        return Err(KeyFileError::CurveMismatch);
+    }
+    let (costs, rest) = rest.split_at(4 * 3);
+    let cost = |i: usize| u32::from_le_bytes(costs[4 * i..4 * i + 4].try_into().unwrap());
+    let kdf = KdfParams {
+        m_cost: cost(0),
+        t_cost: cost(1),
+        p_cost: cost(2),
+    };
+    if !kdf.within_limits() {
+        return Err(KeyFileError::KdfLimits);
+    }
+    let (salt, nonce) = rest.split_at(SALT_LEN);
+
+    let mut ciphertext = Vec::new();
+    reader.read_to_end(&mut ciphertext)?;
+
+    let key = derive_key(password, salt, kdf)?;
+    let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| KeyFileError::Decryption)?;
+
+    Ok(T::deserialize_compressed(plaintext.as_slice())?)
+}
+
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    kdf: KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LEN]>, KeyFileError> {
+    let params = argon2::Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(KEY_LEN))
+        .map_err(|_| KeyFileError::Kdf)?;
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    argon2
+        .hash_password_into(password, salt, &mut *key)
+        .map_err(|_| KeyFileError::Kdf)?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_std::test_rng;
+    use groth_sahai::CRS;
+
+    use super::*;
+    use crate::crs::{generate_crs_with_trapdoor, CrsTrapdoor};
+    use crate::encrypt_e;
+    use crate::lhsps::tagged;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    // small parameters to keep tests fast
+    const KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn test_bank_secrets() {
+        let rng = &mut test_rng();
+
+        let (sk, vk) = tagged::setup::<E, _>(rng, 3);
+        let mut file = Vec::new();
+        save_encrypted_with::<E, _, _, _>(&mut file, &sk, b"pw", KDF, rng).unwrap();
+        let sk: tagged::signing_key::SigningKey<E> =
+            load_encrypted::<E, _, _>(file.as_slice(), b"pw").unwrap();
+        let m = vec![G1::generator(); 3];
+        assert!(vk.verify(b"file", &m, &sk.sign(rng, b"file", &m).unwrap()));
+
+        let (dk, ek) = encrypt_e::key_gen::<E, _>(rng);
+        let mut file = Vec::new();
+        save_encrypted_with::<E, _, _, _>(&mut file, &dk, b"pw", KDF, rng).unwrap();
+        let dk: encrypt_e::decrypt_key::DecryptKey<E> =
+            load_encrypted::<E, _, _>(file.as_slice(), b"pw").unwrap();
+        let c = ek.encrypt(rng, G1::generator(), G1::zero());
+        assert_eq!(dk.decrypt(&c), (G1::generator(), G1::zero()));
+
+        let (crs, td): (CRS<E>, _) = generate_crs_with_trapdoor(rng);
+        let mut file = Vec::new();
+        save_encrypted_with::<E, _, _, _>(&mut file, &td, b"pw", KDF, rng).unwrap();
+        let td: CrsTrapdoor<E> = load_encrypted::<E, _, _>(file.as_slice(), b"pw").unwrap();
+        assert!(td.matches(&crs));
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let rng = &mut test_rng();
+        let (sk, _) = tagged::setup::<E, _>(rng, 3);
+        let mut file = Vec::new();
+        save_encrypted_with::<E, _, _, _>(&mut file, &sk, b"pw", KDF, rng).unwrap();
+
+        // wrong password
+        let r = load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(file.as_slice(), b"p");
+        assert!(matches!(r, Err(KeyFileError::Decryption)));
+
+        // tampered header
+        let mut tampered = file.clone();
+        tampered[HEADER_LEN - 1] ^= 1;
+        let r =
+            load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(tampered.as_slice(), b"pw");
+        assert!(matches!(r, Err(KeyFileError::Decryption)));
+
+        // invalid magic
+        let mut tampered = file.clone();
+        tampered[0] ^= 1;
+        let r =
+            load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(tampered.as_slice(), b"pw");
+        assert!(matches!(r, Err(KeyFileError::InvalidMagic)));
+
+        // unknown version
+        let mut tampered = file.clone();
+        tampered[MAGIC.len()] = 0xff;
+        let r =
+            load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(tampered.as_slice(), b"pw");
+        assert!(matches!(r, Err(KeyFileError::UnsupportedVersion(_))));
+
+        // another curve
+        let mut tampered = file.clone();
+        tampered[MAGIC.len() + 2] ^= 1;
+        let r =
+            load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(tampered.as_slice(), b"pw");
+        assert!(matches!(r, Err(KeyFileError::CurveMismatch)));
+
+        // KDF costs above the caps are rejected before deriving the key
+        let m_cost = MAGIC.len() + 2 + CURVE_ID_LEN;
+        for (i, cap) in [MAX_M_COST, MAX_T_COST, MAX_P_COST].into_iter().enumerate() {
+            let mut tampered = file.clone();
+            tampered[m_cost + 4 * i..m_cost + 4 * i + 4].copy_from_slice(&(cap + 1).to_le_bytes());
+            let r = load_encrypted::<E, tagged::signing_key::SigningKey<E>, _>(
+                tampered.as_slice(),
+                b"pw",
+            );
+            assert!(matches!(r, Err(KeyFileError::KdfLimits)));
+        }
+        let kdf = KdfParams {
+            m_cost: MAX_M_COST + 1,
+            ..KDF
+        };
+        let r = save_encrypted_with::<E, _, _, _>(Vec::new(), &sk, b"pw", kdf, rng);
+        assert!(matches!(r, Err(KeyFileError::KdfLimits)));
+    }
+}
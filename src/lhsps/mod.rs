@@ -2,14 +2,22 @@
 //! signature from Appendix B.2 of `Transferable E-cash: A Cleaner Model and the First Practical Instantiation`.
 
 pub mod signature;
+pub mod signed_vector;
 pub mod signing_key;
+pub mod tagged;
 pub mod verifying_key;
 
 use ark_ec::pairing::Pairing;
 use ark_std::rand::RngCore;
 use ark_std::UniformRand;
+use groth_sahai::prover::CProof;
+use groth_sahai::CRS;
 use std::ops::Mul;
 
+use crate::crs::CrsTrapdoor;
+use crate::proof::zk::{self, ZkProof};
+
+use signed_vector::{combine_messages, SignedVector};
 use signing_key::SigningKey;
 use verifying_key::VerifyKey;
 
@@ -43,3 +51,275 @@ pub fn setup<E: Pairing, R: RngCore>(rng: &mut R, n: usize) -> (SigningKey<E>, V
 
     (SigningKey { xy }, VerifyKey { gz, gr, pk })
 }
+
+/// The common interface of the linearly homomorphic structure-preserving signatures in this module.
+///
+/// Signatures are bound to a `Tag`. Only signatures under the same tag can be combined by `sign_derive`.
+/// The one-time scheme has no tag (i.e. `()`), while the tag-based scheme takes a file identifier.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::lhsps::{self, tagged, LinearlyHomomorphicSignature, OneTime};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+/// type Fr = <E as Pairing>::ScalarField;
+///
+/// fn sign_and_derive<S: LinearlyHomomorphicSignature<E>>(
+///     sk: S::SigningKey,
+///     vk: &S::VerifyKey,
+///     tag: &S::Tag,
+/// ) -> bool {
+///     let rng = &mut test_rng();
+///     let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+///     let sig = S::sign_basis(sk, rng, tag, &[m.clone()]).unwrap().remove(0);
+///     let sig_d = S::sign_derive(vk, tag, &[(Fr::from(1u64), sig)]).unwrap();
+///     S::verify(vk, tag, &m, &sig_d)
+/// }
+///
+/// let rng = &mut test_rng();
+/// let (sk, vk) = lhsps::setup::<E, _>(rng, 3);
+/// assert!(sign_and_derive::<OneTime>(sk, &vk, &()));
+/// let (sk, vk) = tagged::setup::<E, _>(rng, 3);
+/// assert!(sign_and_derive::<tagged::Tagged>(sk, &vk, b"file".as_slice()));
+/// ```
+pub trait LinearlyHomomorphicSignature<E: Pairing> {
+    type SigningKey;
+    type VerifyKey: Clone;
+    type Signature: Clone;
+    type Tag: ?Sized + 'static;
+
+    /// The tag used by keys which sign a single subspace, such as the keys in `rcca::EncryptKey`.
+    const DEFAULT_TAG: &'static Self::Tag;
+
+    /// Generates key pair for messages of dimension n.
+    fn setup<R: RngCore>(rng: &mut R, n: usize) -> (Self::SigningKey, Self::VerifyKey);
+
+    /// Derives a signature on Π mi^wi from the signatures pairs (wi, sigi) under the tag.
+    fn sign_derive(
+        vk: &Self::VerifyKey,
+        tag: &Self::Tag,
+        sig_with_w: &[(E::ScalarField, Self::Signature)],
+    ) -> Result<Self::Signature, ()>;
+
+    /// Verifies a signature on a message under the tag.
+    fn verify(
+        vk: &Self::VerifyKey,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+        sig: &Self::Signature,
+    ) -> bool;
+
+    /// Creates GS proof of a valid signature on a message under the tag.
+    fn generate_proof<R: RngCore>(
+        vk: &Self::VerifyKey,
+        rng: &mut R,
+        crs: &CRS<E>,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+        sig: &Self::Signature,
+    ) -> Result<CProof<E>, ()>;
+
+    /// Checks GS proof generated by `generate_proof`.
+    fn check_proof(
+        vk: &Self::VerifyKey,
+        crs: &CRS<E>,
+        cpf: &CProof<E>,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+    ) -> bool;
+
+    /// The bases B_k of the signature equation Π e(s_k, B_k) = Π e(m_i, pk_i) under the tag,
+    /// where s_k are the elements of the signature (see `signature_elements`).
+    fn equation_bases(vk: &Self::VerifyKey, tag: &Self::Tag) -> Vec<E::G2Affine>;
+
+    /// The elements s_k of the signature, in the order of `equation_bases`.
+    fn signature_elements(sig: &Self::Signature) -> Vec<E::G1Affine>;
+
+    /// The elements pk_i of the verification key paired with the message.
+    fn message_bases(vk: &Self::VerifyKey) -> &[E::G2Affine];
+
+    /// Creates a zero-knowledge GS proof of a valid signature on a message under the tag.
+    ///
+    /// Unlike `generate_proof`, the proof can be simulated with the trapdoor of a hiding CRS
+    /// (see `proof::zk::simulate`).
+    fn generate_proof_zk<R: RngCore>(
+        vk: &Self::VerifyKey,
+        rng: &mut R,
+        crs: &CRS<E>,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+        sig: &Self::Signature,
+    ) -> Result<ZkProof<E>, ()> {
+        let target = message_pairs::<E>(m, Self::message_bases(vk)).ok_or(())?;
+        zk::prove(
+            rng,
+            crs,
+            &Self::signature_elements(sig),
+            &Self::equation_bases(vk, tag),
+            &target,
+        )
+    }
+
+    /// Checks the proof generated by `generate_proof_zk`.
+    fn check_proof_zk(
+        vk: &Self::VerifyKey,
+        crs: &CRS<E>,
+        pf: &ZkProof<E>,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+    ) -> bool {
+        match message_pairs::<E>(m, Self::message_bases(vk)) {
+            Some(target) => zk::verify(crs, pf, &Self::equation_bases(vk, tag), &target),
+            None => false,
+        }
+    }
+
+    /// Simulates the proof of `generate_proof_zk` on message `m` without a signature,
+    /// using the trapdoor of a hiding CRS.
+    fn simulate_proof_zk<R: RngCore>(
+        vk: &Self::VerifyKey,
+        rng: &mut R,
+        crs: &CRS<E>,
+        td: &CrsTrapdoor<E>,
+        tag: &Self::Tag,
+        m: &[E::G1Affine],
+    ) -> Result<ZkProof<E>, ()> {
+        let target = message_pairs::<E>(m, Self::message_bases(vk)).ok_or(())?;
+        zk::simulate(rng, crs, td, &Self::equation_bases(vk, tag), &target)
+    }
+
+    /// Derives the signed vector Π mi^wi from the pairs (wi, (mi, sigi)) under the tag,
+    /// computing the message and its signature together.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use ark_ec::pairing::Pairing;
+    /// use transferable_ecash::lhsps::{self, signed_vector::SignedVector};
+    /// use transferable_ecash::lhsps::{LinearlyHomomorphicSignature, OneTime};
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    /// type Fr = <E as Pairing>::ScalarField;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
+    /// let m1: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let m2: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sigs = sk.sign_basis(&[m1.clone(), m2.clone()]).unwrap();
+    /// let sv1 = SignedVector::new(m1, sigs[0]);
+    /// let sv2 = SignedVector::new(m2, sigs[1]);
+    /// let (w1, w2) = (Fr::rand(rng), Fr::rand(rng));
+    /// let sv = OneTime::derive(&pk, &(), &[(w1, &sv1), (w2, &sv2)]).unwrap();
+    /// assert!(pk.verify(sv.message(), sv.signature()));
+    /// ```
+    fn derive(
+        vk: &Self::VerifyKey,
+        tag: &Self::Tag,
+        sv_with_w: &[(E::ScalarField, &SignedVector<E, Self::Signature>)],
+    ) -> Result<SignedVector<E, Self::Signature>, ()> {
+        let m_with_w: Vec<_> = sv_with_w.iter().map(|(w, sv)| (*w, sv.message())).collect();
+        let sig_with_w: Vec<_> = sv_with_w
+            .iter()
+            .map(|(w, sv)| (*w, sv.sig.clone()))
+            .collect();
+        let m = combine_messages::<E>(&m_with_w)?;
+        let sig = Self::sign_derive(vk, tag, &sig_with_w)?;
+        Ok(SignedVector { m, sig })
+    }
+
+    /// Signs the whole basis of a subspace under the tag in one call, and consumes the key.
+    ///
+    /// It is the only way to sign through the trait, so that a one-time key never signs outside
+    /// the span of the basis.
+    fn sign_basis<R: RngCore>(
+        sk: Self::SigningKey,
+        rng: &mut R,
+        tag: &Self::Tag,
+        basis: &[Vec<E::G1Affine>],
+    ) -> Result<Vec<Self::Signature>, ()>;
+}
+
+// (m_i, pk_i) for the target Π e(m_i, pk_i). None if the dimensions do not match.
+fn message_pairs<E: Pairing>(
+    m: &[E::G1Affine],
+    pk: &[E::G2Affine],
+) -> Option<Vec<(E::G1Affine, E::G2Affine)>> {
+    (m.len() == pk.len()).then(|| m.iter().copied().zip(pk.iter().copied()).collect())
+}
+
+/// The one-time scheme of this module, implementing `LinearlyHomomorphicSignature`.
+#[derive(Clone, Copy, Debug)]
+pub struct OneTime;
+
+impl<E: Pairing> LinearlyHomomorphicSignature<E> for OneTime {
+    type SigningKey = SigningKey<E>;
+    type VerifyKey = VerifyKey<E>;
+    type Signature = signature::Signature<E>;
+    type Tag = ();
+
+    const DEFAULT_TAG: &'static () = &();
+
+    fn setup<R: RngCore>(rng: &mut R, n: usize) -> (SigningKey<E>, VerifyKey<E>) {
+        setup(rng, n)
+    }
+
+    fn sign_derive(
+        vk: &VerifyKey<E>,
+        _tag: &(),
+        sig_with_w: &[(E::ScalarField, Self::Signature)],
+    ) -> Result<Self::Signature, ()> {
+        vk.sign_derive(sig_with_w)
+    }
+
+    fn verify(vk: &VerifyKey<E>, _tag: &(), m: &[E::G1Affine], sig: &Self::Signature) -> bool {
+        vk.verify(m, sig)
+    }
+
+    fn generate_proof<R: RngCore>(
+        vk: &VerifyKey<E>,
+        rng: &mut R,
+        crs: &CRS<E>,
+        _tag: &(),
+        m: &[E::G1Affine],
+        sig: &Self::Signature,
+    ) -> Result<CProof<E>, ()> {
+        vk.generate_proof(rng, crs, m, sig)
+    }
+
+    fn check_proof(
+        vk: &VerifyKey<E>,
+        crs: &CRS<E>,
+        cpf: &CProof<E>,
+        _tag: &(),
+        m: &[E::G1Affine],
+    ) -> bool {
+        vk.check_proof(crs, cpf, m)
+    }
+
+    fn equation_bases(vk: &VerifyKey<E>, _tag: &()) -> Vec<E::G2Affine> {
+        vec![vk.gz, vk.gr]
+    }
+
+    fn signature_elements(sig: &Self::Signature) -> Vec<E::G1Affine> {
+        vec![sig.z, sig.r]
+    }
+
+    fn message_bases(vk: &VerifyKey<E>) -> &[E::G2Affine] {
+        &vk.pk
+    }
+
+    fn sign_basis<R: RngCore>(
+        sk: SigningKey<E>,
+        _rng: &mut R,
+        _tag: &(),
+        basis: &[Vec<E::G1Affine>],
+    ) -> Result<Vec<Self::Signature>, ()> {
+        sk.sign_basis(basis).map_err(|_| ())
+    }
+}
@@ -1,6 +1,7 @@
 use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, CanonicalSerialize, CanonicalDeserialize)]
 pub struct Signature<E: Pairing> {
     pub(crate) z: E::G1Affine,
     pub(crate) r: E::G1Affine,
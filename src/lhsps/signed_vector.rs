@@ -0,0 +1,53 @@
+use ark_ec::pairing::Pairing;
+use ark_std::Zero;
+use std::ops::Mul;
+
+use super::signature::Signature;
+
+/// A message vector together with its signature, so that the two are derived as one unit.
+///
+/// `Sig` is the signature type of the scheme, i.e. `LinearlyHomomorphicSignature::Signature`.
+#[derive(Clone)]
+pub struct SignedVector<E: Pairing, Sig = Signature<E>> {
+    pub(crate) m: Vec<E::G1Affine>,
+    pub(crate) sig: Sig,
+}
+
+impl<E: Pairing, Sig> SignedVector<E, Sig> {
+    pub fn new(m: Vec<E::G1Affine>, sig: Sig) -> Self {
+        Self { m, sig }
+    }
+
+    pub fn message(&self) -> &[E::G1Affine] {
+        &self.m
+    }
+
+    pub fn signature(&self) -> &Sig {
+        &self.sig
+    }
+
+    pub fn into_parts(self) -> (Vec<E::G1Affine>, Sig) {
+        (self.m, self.sig)
+    }
+}
+
+// m = Π mi^wi, for non-empty messages of the same dimension.
+pub(crate) fn combine_messages<E: Pairing>(
+    m_with_w: &[(E::ScalarField, &[E::G1Affine])],
+) -> Result<Vec<E::G1Affine>, ()> {
+    let n = match m_with_w.first() {
+        Some((_, m)) => m.len(),
+        None => return Err(()),
+    };
+    if n == 0 || m_with_w.iter().any(|(_, m)| m.len() != n) {
+        return Err(());
+    }
+    Ok((0..n)
+        .map(|i| {
+            m_with_w
+                .iter()
+                .fold(E::G1::zero(), |acc, (w, m)| acc + m[i].mul(w))
+                .into()
+        })
+        .collect())
+}
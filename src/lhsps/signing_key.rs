@@ -1,9 +1,27 @@
 use ark_ec::pairing::Pairing;
 use ark_ec::AffineRepr;
+use ark_std::Zero;
 use std::ops::Mul;
 
 use super::signature::Signature;
 
+/// Errors of signing with a `SigningKey` restricted to a subspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignError {
+    /// The basis of the subspace is empty.
+    EmptyBasis,
+    /// The dimension of a vector does not match the signing key.
+    DimensionMismatch,
+    /// The basis contains the vector (1, ..., 1), which cannot be signed.
+    TrivialVector,
+    /// The number of coefficients does not match the basis.
+    CoefficientsMismatch,
+    /// The vector is not the given linear combination of the basis, i.e. it is not in the declared subspace.
+    OutsideSubspace,
+}
+
+/// The one-time signing key. It is not serializable, so that `sign_basis` and `restrict`, which
+/// consume the key, cannot be bypassed by restoring a copy of it.
 pub struct SigningKey<E: Pairing> {
     pub(crate) xy: Vec<(E::ScalarField, E::ScalarField)>,
 }
@@ -12,6 +30,28 @@ impl<E: Pairing> SigningKey<E> {
     /// Signs a message using the one-time linearly homomorphic structure-preserving signature.
     ///
     /// A deterministic algorithm that takes the signing key sk and the message m, and outputs a signature.
+    /// It does not restrict the message, so it is only used inside the crate. Users sign through
+    /// `sign_basis` or a key restricted by `restrict`.
+    pub(crate) fn sign(&self, m: &[E::G1Affine]) -> Result<Signature<E>, ()> {
+        if self.xy.len() != m.len() {
+            return Err(());
+        }
+        // z = Π m^xi, r = Π m^yi
+        let (z, r) = m
+            .iter()
+            .zip(&self.xy)
+            .map(|(m, (x, y))| (m.mul(x), m.mul(y)))
+            .fold((E::G1Affine::zero(), E::G1Affine::zero()), |acc, m| {
+                ((acc.0 + m.0).into(), (acc.1 + m.1).into())
+            });
+
+        Ok(Signature { z, r })
+    }
+
+    /// Signs the whole basis of a subspace in one call, and consumes the key.
+    ///
+    /// As the scheme is one-time, the key must not sign anything outside the span of
+    /// this basis afterwards. Signatures of vectors in the span are derived by `VerifyKey::sign_derive`.
     ///
     /// # Example
     ///
@@ -25,23 +65,189 @@ impl<E: Pairing> SigningKey<E> {
     ///
     /// let rng = &mut test_rng();
     /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
-    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
-    /// let sig = sk.sign(&m).unwrap();
+    /// let basis: Vec<Vec<G1>> = (0..2)
+    ///     .map(|_| (0..5).map(|_| G1::rand(rng)).collect())
+    ///     .collect();
+    /// let sigs = sk.sign_basis(&basis).unwrap();
+    /// assert!(pk.verify(&basis[0], &sigs[0]));
+    /// assert!(pk.verify(&basis[1], &sigs[1]));
+    /// ```
+    pub fn sign_basis(self, basis: &[Vec<E::G1Affine>]) -> Result<Vec<Signature<E>>, SignError> {
+        self.check_basis(basis)?;
+        Ok(basis
+            .iter()
+            .map(|b| self.sign(b).expect("dimension is checked"))
+            .collect())
+    }
+
+    /// Restricts the key to the subspace spanned by `basis`, and consumes the key.
+    ///
+    /// The returned key only signs vectors given together with their coefficients with respect to
+    /// the basis. It is because deciding whether a vector of group elements is in the span of others,
+    /// without the coefficients, is as hard as computing discrete logarithms.
+    pub fn restrict(self, basis: &[Vec<E::G1Affine>]) -> Result<SubspaceSigningKey<E>, SignError> {
+        self.check_basis(basis)?;
+        Ok(SubspaceSigningKey {
+            sk: self,
+            basis: basis.to_vec(),
+        })
+    }
+
+    fn check_basis(&self, basis: &[Vec<E::G1Affine>]) -> Result<(), SignError> {
+        if basis.is_empty() {
+            return Err(SignError::EmptyBasis);
+        }
+        if basis.iter().any(|b| b.len() != self.xy.len()) {
+            return Err(SignError::DimensionMismatch);
+        }
+        if basis.iter().any(|b| b.iter().all(|bi| bi.is_zero())) {
+            return Err(SignError::TrivialVector);
+        }
+        Ok(())
+    }
+}
+
+/// A one-time signing key which only signs vectors in a declared subspace. It is created by `SigningKey::restrict`.
+pub struct SubspaceSigningKey<E: Pairing> {
+    sk: SigningKey<E>,
+    basis: Vec<Vec<E::G1Affine>>,
+}
+
+impl<E: Pairing> SubspaceSigningKey<E> {
+    /// Returns the basis of the declared subspace.
+    pub fn basis(&self) -> &[Vec<E::G1Affine>] {
+        &self.basis
+    }
+
+    /// Signs the message `m`, which must equal to Π basis_j^w_j for the coefficients `w`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use ark_ec::pairing::Pairing;
+    /// use transferable_ecash::lhsps::{self, signing_key::SignError};
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    /// type Fr = <E as Pairing>::ScalarField;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = lhsps::setup::<E, _>(rng, 3);
+    /// let basis: Vec<Vec<G1>> = vec![(0..3).map(|_| G1::rand(rng)).collect()];
+    /// let sk = sk.restrict(&basis).unwrap();
+    ///
+    /// let w = [Fr::rand(rng)];
+    /// let (m, sig) = sk.sign_combination(&w).unwrap();
     /// assert!(pk.verify(&m, &sig));
+    /// assert!(sk.sign(&m, &w).is_ok());
+    ///
+    /// // vector outside the subspace
+    /// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+    /// assert_eq!(sk.sign(&m, &w).err(), Some(SignError::OutsideSubspace));
     /// ```
-    pub fn sign(&self, m: &[E::G1Affine]) -> Result<Signature<E>, ()> {
-        if self.xy.len() != m.len() {
-            return Err(());
+    pub fn sign(&self, m: &[E::G1Affine], w: &[E::ScalarField]) -> Result<Signature<E>, SignError> {
+        if m.len() != self.sk.xy.len() {
+            return Err(SignError::DimensionMismatch);
         }
-        // z = Π m^xi, r = Π m^yi
-        let (z, r) = m
-            .iter()
-            .zip(&self.xy)
-            .map(|(m, (x, y))| (m.mul(x), m.mul(y)))
-            .fold((E::G1Affine::zero(), E::G1Affine::zero()), |acc, m| {
-                ((acc.0 + m.0).into(), (acc.1 + m.1).into())
-            });
+        if self.combine(w)? != m {
+            return Err(SignError::OutsideSubspace);
+        }
+        self.sk.sign(m).map_err(|_| SignError::DimensionMismatch)
+    }
 
-        Ok(Signature { z, r })
+    /// Computes the message Π basis_j^w_j and signs it.
+    pub fn sign_combination(
+        &self,
+        w: &[E::ScalarField],
+    ) -> Result<(Vec<E::G1Affine>, Signature<E>), SignError> {
+        let m = self.combine(w)?;
+        if m.iter().all(|mi| mi.is_zero()) {
+            return Err(SignError::TrivialVector);
+        }
+        let sig = self.sk.sign(&m).map_err(|_| SignError::DimensionMismatch)?;
+        Ok((m, sig))
+    }
+
+    // m = Π basis_j^w_j
+    fn combine(&self, w: &[E::ScalarField]) -> Result<Vec<E::G1Affine>, SignError> {
+        if w.len() != self.basis.len() {
+            return Err(SignError::CoefficientsMismatch);
+        }
+        Ok((0..self.sk.xy.len())
+            .map(|i| {
+                self.basis
+                    .iter()
+                    .zip(w)
+                    .fold(E::G1::zero(), |acc, (b, wj)| acc + b[i].mul(wj))
+                    .into()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{pairing::Pairing, AffineRepr};
+    use ark_std::{test_rng, UniformRand, Zero};
+
+    use super::SignError;
+    use crate::lhsps::setup;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_restrict() {
+        let rng = &mut test_rng();
+        let basis: Vec<Vec<G1>> = (0..2)
+            .map(|_| (0..3).map(|_| G1::rand(rng)).collect())
+            .collect();
+
+        let (sk, _) = setup::<E, _>(rng, 3);
+        assert_eq!(sk.restrict(&[]).err(), Some(SignError::EmptyBasis));
+        let (sk, _) = setup::<E, _>(rng, 3);
+        let short = vec![basis[0][..2].to_vec()];
+        assert_eq!(
+            sk.restrict(&short).err(),
+            Some(SignError::DimensionMismatch)
+        );
+        let (sk, _) = setup::<E, _>(rng, 3);
+        let trivial = vec![basis[0].clone(), vec![G1::zero(); 3]];
+        assert_eq!(sk.restrict(&trivial).err(), Some(SignError::TrivialVector));
+        let (sk, _) = setup::<E, _>(rng, 3);
+        assert_eq!(
+            sk.sign_basis(&trivial).err(),
+            Some(SignError::TrivialVector)
+        );
+
+        let (sk, pk) = setup::<E, _>(rng, 3);
+        let sk = sk.restrict(&basis).unwrap();
+        let w = [Fr::rand(rng), Fr::rand(rng)];
+        let (m, sig) = sk.sign_combination(&w).unwrap();
+        assert!(pk.verify(&m, &sig));
+        assert!(pk.verify(&m, &sk.sign(&m, &w).unwrap()));
+
+        // the message must be the declared combination of the basis
+        let other = [w[0], w[1] + Fr::from(1u64)];
+        assert_eq!(sk.sign(&m, &other).err(), Some(SignError::OutsideSubspace));
+        let random: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+        assert_eq!(sk.sign(&random, &w).err(), Some(SignError::OutsideSubspace));
+        assert_eq!(
+            sk.sign(&m[..2], &w).err(),
+            Some(SignError::DimensionMismatch)
+        );
+        assert_eq!(
+            sk.sign(&m, &w[..1]).err(),
+            Some(SignError::CoefficientsMismatch)
+        );
+
+        // the combination with zero coefficients is the trivial vector
+        let zero = [Fr::zero(), Fr::zero()];
+        assert_eq!(
+            sk.sign_combination(&zero).err(),
+            Some(SignError::TrivialVector)
+        );
     }
 }
@@ -0,0 +1,143 @@
+//! This module implements a multi-use (tag-based) linearly homomorphic structure-preserving
+//! signature, following the tag-based construction of Libert, Peters, Joye and Yung
+//! (`Linearly Homomorphic Structure-Preserving Signatures and Their Applications`) in the
+//! asymmetric setting of the one-time scheme.
+//!
+//! Signatures are bound to a file identifier (tag). Signatures under the same tag can be
+//! combined, so a single key can sign many independent subspaces, one per tag.
+
+pub mod signature;
+pub mod signing_key;
+pub mod verifying_key;
+
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use sha2::{Digest, Sha256};
+use std::ops::Mul;
+
+use groth_sahai::prover::CProof;
+use groth_sahai::CRS;
+
+use signing_key::SigningKey;
+use verifying_key::VerifyKey;
+
+use super::LinearlyHomomorphicSignature;
+
+/// Generates key pair for the tag-based linearly homomorphic structure-preserving signature.
+///
+/// A probabilistic algorithm taking the group parameter and an integer n denoting the dimension
+/// of the message to be signed. It outputs the public verification key pk and the signing key sk.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::lhsps::tagged;
+///
+/// let rng = &mut test_rng();
+/// let (sk, pk) = tagged::setup::<ark_bls12_381::Bls12_381, _>(rng, 5);
+/// ```
+pub fn setup<E: Pairing, R: RngCore>(rng: &mut R, n: usize) -> (SigningKey<E>, VerifyKey<E>) {
+    let xy: Vec<(E::ScalarField, E::ScalarField)> = (0..n)
+        .map(|_| (E::ScalarField::rand(rng), E::ScalarField::rand(rng)))
+        .collect();
+    let ab = [
+        (E::ScalarField::rand(rng), E::ScalarField::rand(rng)),
+        (E::ScalarField::rand(rng), E::ScalarField::rand(rng)),
+    ];
+
+    let gz = E::G2Affine::rand(rng);
+    let gr = E::G2Affine::rand(rng);
+
+    // pk = gz^xi + gr^yi
+    let pk = xy
+        .iter()
+        .map(|(x, y)| (gz.mul(*x) + gr.mul(*y)).into())
+        .collect();
+    // v0 = gz^a0 + gr^b0, v1 = gz^a1 + gr^b1
+    let v = ab.map(|(a, b)| (gz.mul(a) + gr.mul(b)).into());
+
+    (SigningKey { xy, ab }, VerifyKey { gz, gr, v, pk })
+}
+
+/// The tag-based scheme of this module, implementing `LinearlyHomomorphicSignature`.
+#[derive(Clone, Copy, Debug)]
+pub struct Tagged;
+
+impl<E: Pairing> LinearlyHomomorphicSignature<E> for Tagged {
+    type SigningKey = SigningKey<E>;
+    type VerifyKey = VerifyKey<E>;
+    type Signature = signature::Signature<E>;
+    type Tag = [u8];
+
+    const DEFAULT_TAG: &'static [u8] = b"";
+
+    fn setup<R: RngCore>(rng: &mut R, n: usize) -> (SigningKey<E>, VerifyKey<E>) {
+        setup(rng, n)
+    }
+
+    fn sign_basis<R: RngCore>(
+        sk: SigningKey<E>,
+        rng: &mut R,
+        tag: &[u8],
+        basis: &[Vec<E::G1Affine>],
+    ) -> Result<Vec<Self::Signature>, ()> {
+        basis.iter().map(|b| sk.sign(rng, tag, b)).collect()
+    }
+
+    fn sign_derive(
+        vk: &VerifyKey<E>,
+        tag: &[u8],
+        sig_with_w: &[(E::ScalarField, Self::Signature)],
+    ) -> Result<Self::Signature, ()> {
+        vk.sign_derive(tag, sig_with_w)
+    }
+
+    fn verify(vk: &VerifyKey<E>, tag: &[u8], m: &[E::G1Affine], sig: &Self::Signature) -> bool {
+        vk.verify(tag, m, sig)
+    }
+
+    fn generate_proof<R: RngCore>(
+        vk: &VerifyKey<E>,
+        rng: &mut R,
+        crs: &CRS<E>,
+        tag: &[u8],
+        m: &[E::G1Affine],
+        sig: &Self::Signature,
+    ) -> Result<CProof<E>, ()> {
+        vk.generate_proof(rng, crs, tag, m, sig)
+    }
+
+    fn check_proof(
+        vk: &VerifyKey<E>,
+        crs: &CRS<E>,
+        cpf: &CProof<E>,
+        tag: &[u8],
+        m: &[E::G1Affine],
+    ) -> bool {
+        vk.check_proof(crs, cpf, tag, m)
+    }
+
+    fn equation_bases(vk: &VerifyKey<E>, tag: &[u8]) -> Vec<E::G2Affine> {
+        vec![vk.gz, vk.gr, vk.hash(tag)]
+    }
+
+    fn signature_elements(sig: &Self::Signature) -> Vec<E::G1Affine> {
+        vec![sig.z, sig.r, sig.u]
+    }
+
+    fn message_bases(vk: &VerifyKey<E>) -> &[E::G2Affine] {
+        &vk.pk
+    }
+}
+
+// h(tag) maps the file identifier to a scalar.
+pub(crate) fn hash_tag<E: Pairing>(tag: &[u8]) -> E::ScalarField {
+    let digest = Sha256::new()
+        .chain_update(b"transferable-ecash/lhsps/tagged")
+        .chain_update(tag)
+        .finalize();
+    E::ScalarField::from_le_bytes_mod_order(&digest)
+}
@@ -0,0 +1,70 @@
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use std::ops::{Mul, Neg};
+
+use super::{hash_tag, signature::Signature};
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SigningKey<E: Pairing> {
+    pub(crate) xy: Vec<(E::ScalarField, E::ScalarField)>,
+    // discrete logarithms of v0 = gz^a0 gr^b0 and v1 = gz^a1 gr^b1
+    pub(crate) ab: [(E::ScalarField, E::ScalarField); 2],
+}
+
+impl<E: Pairing> SigningKey<E> {
+    /// Signs a message under the file identifier `tag`.
+    ///
+    /// A randomized algorithm that takes the signing key sk, a tag and the message m, and outputs a signature.
+    /// Only signatures under the same tag can be combined by `sign_derive`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use ark_ec::pairing::Pairing;
+    /// use transferable_ecash::lhsps::tagged;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = tagged::setup::<E, _>(rng, 5);
+    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sig = sk.sign(rng, b"file-1", &m).unwrap();
+    /// assert!(pk.verify(b"file-1", &m, &sig));
+    /// assert!(!pk.verify(b"file-2", &m, &sig));
+    /// ```
+    pub fn sign<R: RngCore>(
+        &self,
+        rng: &mut R,
+        tag: &[u8],
+        m: &[E::G1Affine],
+    ) -> Result<Signature<E>, ()> {
+        if self.xy.len() != m.len() {
+            return Err(());
+        }
+        // H(tag) = v0 v1^h(tag) = gz^a gr^b, where a = a0 + h(tag) a1, b = b0 + h(tag) b1
+        let h = hash_tag::<E>(tag);
+        let a = self.ab[0].0 + h * self.ab[1].0;
+        let b = self.ab[0].1 + h * self.ab[1].1;
+
+        // u = g1^theta, z = Π m^xi u^-a, r = Π m^yi u^-b
+        let u = E::G1Affine::generator().mul(E::ScalarField::rand(rng));
+        let (z, r) = m
+            .iter()
+            .zip(&self.xy)
+            .map(|(m, (x, y))| (m.mul(x), m.mul(y)))
+            .fold((u.mul(a).neg(), u.mul(b).neg()), |acc, m| {
+                (acc.0 + m.0, acc.1 + m.1)
+            });
+
+        Ok(Signature {
+            z: z.into(),
+            r: r.into(),
+            u: u.into(),
+        })
+    }
+}
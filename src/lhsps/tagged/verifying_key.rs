@@ -0,0 +1,278 @@
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_std::rand::RngCore;
+use ark_std::Zero;
+use groth_sahai::prover::CProof;
+use groth_sahai::CRS;
+use std::ops::Mul;
+
+use crate::proof::{check_proof_xb_t, create_proof_xb_t};
+
+use super::{hash_tag, signature::Signature};
+
+#[derive(Clone)]
+pub struct VerifyKey<E: Pairing> {
+    pub(crate) gz: E::G2Affine,
+    pub(crate) gr: E::G2Affine,
+    // the hash of tag is H(tag) = v0 v1^h(tag)
+    pub(crate) v: [E::G2Affine; 2],
+
+    pub(crate) pk: Vec<E::G2Affine>,
+}
+
+impl<E: Pairing> VerifyKey<E> {
+    /// Derives a new signature from signatures under the same tag.
+    ///
+    /// A deterministic algorithm taking the verification key pk, a tag and signature pairs (wi, sigi) for i in 1..l.
+    /// It outputs a signature on the message Π mi^wi under the tag.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use ark_ec::pairing::Pairing;
+    /// use transferable_ecash::lhsps::tagged;
+    /// use std::ops::Mul;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    /// type Fr = <E as Pairing>::ScalarField;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = tagged::setup::<E, _>(rng, 5);
+    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sig = sk.sign(rng, b"file", &m).unwrap();
+    /// let w = Fr::rand(rng);
+    /// let sig_d = pk.sign_derive(b"file", &[(w, sig)]).unwrap();
+    /// let m_d = m.iter().map(|mi| mi.mul(w).into()).collect::<Vec<_>>();
+    /// assert!(pk.verify(b"file", &m_d, &sig_d));
+    /// ```
+    pub fn sign_derive(
+        &self,
+        _tag: &[u8],
+        sig_with_w: &[(E::ScalarField, Signature<E>)],
+    ) -> Result<Signature<E>, ()> {
+        if sig_with_w.is_empty() {
+            return Err(());
+        }
+        // cannot derive signature on message with Mi' = Mi^0 = 1. (verify must fail)
+        if sig_with_w.iter().any(|(w, _)| w.is_zero()) {
+            return Err(());
+        }
+
+        // z = Π z^w, r = Π r^w, u = Π u^w
+        let (z, r, u) = sig_with_w
+            .iter()
+            .map(|(w, sigi)| (sigi.z.mul(*w), sigi.r.mul(*w), sigi.u.mul(*w)))
+            .fold((E::G1::zero(), E::G1::zero(), E::G1::zero()), |acc, p| {
+                (acc.0 + p.0, acc.1 + p.1, acc.2 + p.2)
+            });
+
+        Ok(Signature {
+            z: z.into(),
+            r: r.into(),
+            u: u.into(),
+        })
+    }
+
+    /// Verifies a signature under the tag.
+    ///
+    /// A deterministic algorithm taking the verification key pk, a tag, the message vector m and a signature.
+    /// It outputs true if the signature is valid, false otherwise.
+    pub fn verify(&self, tag: &[u8], m: &[E::G1Affine], sig: &Signature<E>) -> bool {
+        if m.len() != self.pk.len() {
+            return false;
+        }
+
+        // check (M1, ...,Mn) != (1, ... ,1)
+        if m.iter().all(|mi| mi.is_zero()) {
+            return false;
+        }
+        // with u = 1, the signature does not depend on the tag
+        if sig.u.is_zero() {
+            return false;
+        }
+
+        // e(z, gz)e(r, gr)e(u, H(tag)) == Π e(m, pk)
+        let lhs = E::pairing(sig.z, self.gz)
+            + E::pairing(sig.r, self.gr)
+            + E::pairing(sig.u, self.hash(tag));
+        lhs == self.target(m)
+    }
+
+    /// Create GS proof from a signature that satisfies pairing product equation: e(z, gz)e(r, gr)e(u, H(tag)) == Π e(m, pk).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use groth_sahai::{AbstractCrs, CRS};
+    /// use transferable_ecash::lhsps::tagged;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = tagged::setup::<E, _>(rng, 5);
+    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sig = sk.sign(rng, b"file", &m).unwrap();
+    ///
+    /// let crs = CRS::<E>::generate_crs(rng);
+    /// let pf = pk.generate_proof(rng, &crs, b"file", &m, &sig)
+    ///     .expect("proof should be valid");
+    /// assert!(pk.check_proof(&crs, &pf, b"file", &m));
+    /// ```
+    pub fn generate_proof<R: RngCore>(
+        &self,
+        rng: &mut R,
+        crs: &CRS<E>,
+        tag: &[u8],
+        m: &[E::G1Affine],
+        sig: &Signature<E>,
+    ) -> Result<CProof<E>, ()> {
+        create_proof_xb_t(
+            rng,
+            crs,
+            &[sig.z, sig.r, sig.u],
+            &[self.gz, self.gr, self.hash(tag)],
+            self.target(m),
+        )
+    }
+
+    /// Check GS proof generated by `generate_proof`.
+    pub fn check_proof(
+        &self,
+        crs: &CRS<E>,
+        cpf: &CProof<E>,
+        tag: &[u8],
+        m: &[E::G1Affine],
+    ) -> bool {
+        if m.len() != self.pk.len() {
+            return false;
+        }
+        check_proof_xb_t(
+            crs,
+            cpf,
+            &[self.gz, self.gr, self.hash(tag)],
+            self.target(m),
+        )
+    }
+
+    // H(tag) = v0 v1^h(tag)
+    pub(crate) fn hash(&self, tag: &[u8]) -> E::G2Affine {
+        (self.v[0] + self.v[1].mul(hash_tag::<E>(tag))).into()
+    }
+
+    // Π e(m, pk)
+    fn target(&self, m: &[E::G1Affine]) -> PairingOutput<E> {
+        m.iter()
+            .zip(&self.pk)
+            .map(|(m, pk)| E::pairing(*m, *pk))
+            .fold(PairingOutput::zero(), |acc, m| acc + m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{pairing::Pairing, AffineRepr};
+    use ark_std::UniformRand;
+    use std::ops::Mul;
+
+    use crate::crs::generate_hiding_crs_with_trapdoor;
+    use crate::lhsps::tagged::{setup, signature::Signature, Tagged};
+    use crate::lhsps::LinearlyHomomorphicSignature;
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_many_subspaces() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = setup::<E, _>(rng, 3);
+
+        // one key signs several independent subspaces
+        for tag in [b"file-1", b"file-2", b"file-3"] {
+            let m1 = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+            let m2 = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+            let sig1 = sk.sign(rng, tag, &m1).unwrap();
+            let sig2 = sk.sign(rng, tag, &m2).unwrap();
+
+            let (w1, w2) = (Fr::rand(rng), Fr::rand(rng));
+            let sig_d = pk.sign_derive(tag, &[(w1, sig1), (w2, sig2)]).unwrap();
+            let m_d = m1
+                .iter()
+                .zip(&m2)
+                .map(|(m1i, m2i)| (m1i.mul(w1) + m2i.mul(w2)).into())
+                .collect::<Vec<G1>>();
+            assert!(pk.verify(tag, &m_d, &sig_d));
+        }
+    }
+
+    #[test]
+    fn test_combine_across_tags() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = setup::<E, _>(rng, 3);
+
+        let m1 = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let m2 = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let sig1 = sk.sign(rng, b"file-1", &m1).unwrap();
+        let sig2 = sk.sign(rng, b"file-2", &m2).unwrap();
+
+        // signatures under different tags do not combine
+        let sig_d = pk
+            .sign_derive(b"file-1", &[(Fr::from(1u64), sig1), (Fr::from(1u64), sig2)])
+            .unwrap();
+        let m_d = m1
+            .iter()
+            .zip(&m2)
+            .map(|(m1i, m2i)| (*m1i + *m2i).into())
+            .collect::<Vec<G1>>();
+        assert!(!pk.verify(b"file-1", &m_d, &sig_d));
+        assert!(!pk.verify(b"file-2", &m_d, &sig_d));
+    }
+
+    #[test]
+    fn test_trivial_u() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = setup::<E, _>(rng, 3);
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+
+        // z = Π m^xi, r = Π m^yi and u = 1 satisfy the equation under every tag
+        let (z, r) = m
+            .iter()
+            .zip(&sk.xy)
+            .fold((G1::zero(), G1::zero()), |acc, (mi, (x, y))| {
+                ((acc.0 + mi.mul(x)).into(), (acc.1 + mi.mul(y)).into())
+            });
+        let sig = Signature {
+            z,
+            r,
+            u: G1::zero(),
+        };
+        assert!(!pk.verify(b"file-1", &m, &sig));
+        assert!(!pk.verify(b"", &m, &sig));
+    }
+
+    #[test]
+    fn test_proof_zk() {
+        let rng = &mut ark_std::test_rng();
+        let (crs, td) = generate_hiding_crs_with_trapdoor::<E, _>(rng);
+        let (sk, pk) = setup::<E, _>(rng, 3);
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let sig = sk.sign(rng, b"file-1", &m).unwrap();
+
+        let pf = Tagged::generate_proof_zk(&pk, rng, &crs, b"file-1", &m, &sig).unwrap();
+        assert!(Tagged::check_proof_zk(&pk, &crs, &pf, b"file-1", &m));
+        // the proof is bound to the tag
+        assert!(!Tagged::check_proof_zk(&pk, &crs, &pf, b"file-2", &m));
+        assert!(Tagged::generate_proof_zk(&pk, rng, &crs, b"file-2", &m, &sig).is_err());
+
+        // the simulator proves any message under any tag
+        let m2 = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let pf = Tagged::simulate_proof_zk(&pk, rng, &crs, &td, b"file-2", &m2).unwrap();
+        assert!(Tagged::check_proof_zk(&pk, &crs, &pf, b"file-2", &m2));
+        assert!(!Tagged::check_proof_zk(&pk, &crs, &pf, b"file-2", &m2[..2]));
+    }
+}
@@ -1,15 +1,23 @@
 use ark_ec::pairing::{Pairing, PairingOutput};
 use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
 use ark_std::rand::RngCore;
 use ark_std::Zero;
 use groth_sahai::prover::CProof;
 use groth_sahai::CRS;
+use sha2::{Digest, Sha256};
 use std::ops::Mul;
 
+use crate::crs::CrsTrapdoor;
+use crate::proof::zk::{self, ZkProof};
 use crate::proof::{check_proof_xbxb_t, create_proof_xbxb_t};
 
 use super::signature::Signature;
 
+/// The statistical security parameter of `VerifyKey::verify_batch`. Its soundness error is 2^-BATCH_SECURITY_BITS.
+pub const BATCH_SECURITY_BITS: usize = 128;
+
 #[derive(Clone)]
 pub struct VerifyKey<E: Pairing> {
     pub(crate) gz: E::G2Affine,
@@ -38,8 +46,8 @@ impl<E: Pairing> VerifyKey<E> {
     ///
     /// let rng = &mut test_rng();
     /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
-    /// let mut m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
-    /// let sig = sk.sign(&m).unwrap();
+    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sig = sk.sign_basis(&[m.clone()]).unwrap()[0];
     /// let w = Fr::rand(rng);
     /// let sig_with_w = vec![(w, sig)];
     /// let sig_d = pk.sign_derive(&sig_with_w).unwrap();
@@ -87,7 +95,7 @@ impl<E: Pairing> VerifyKey<E> {
     /// let rng = &mut test_rng();
     /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
     /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
-    /// let sig = sk.sign(&m).unwrap();
+    /// let sig = sk.sign_basis(&[m.clone()]).unwrap()[0];
     /// assert!(pk.verify(&m, &sig));
     /// ```
     pub fn verify(&self, m: &[E::G1Affine], sig: &Signature<E>) -> bool {
@@ -110,6 +118,64 @@ impl<E: Pairing> VerifyKey<E> {
         lhs == rhs
     }
 
+    /// Verifies many signatures under the same verification key at once.
+    ///
+    /// It takes a random linear combination of the equations e(z, gz)e(r, gr) == Π e(m, pk),
+    /// i.e. e(Π z_j^rho_j, gz)e(Π r_j^rho_j, gr) == Π_i e(Π_j m_ji^rho_j, pk_i), so that it computes
+    /// n+2 pairings in total. The coefficients rho_j are derived by hashing the whole batch, and the
+    /// soundness error is 2^-BATCH_SECURITY_BITS. An empty batch is valid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use ark_ec::pairing::Pairing;
+    /// use transferable_ecash::lhsps;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
+    /// let basis: Vec<Vec<G1>> = (0..3)
+    ///     .map(|_| (0..5).map(|_| G1::rand(rng)).collect())
+    ///     .collect();
+    /// let sigs = sk.sign_basis(&basis).unwrap();
+    /// let batch: Vec<_> = basis.into_iter().zip(sigs).collect();
+    /// assert!(pk.verify_batch(&batch));
+    /// ```
+    pub fn verify_batch(&self, batch: &[(Vec<E::G1Affine>, Signature<E>)]) -> bool {
+        if batch
+            .iter()
+            .any(|(m, _)| m.len() != self.pk.len() || m.iter().all(|mi| mi.is_zero()))
+        {
+            return false;
+        }
+
+        let rho = batch_coefficients::<E>(batch);
+
+        // z = Π z_j^rho_j, r = Π r_j^rho_j, m_i = Π m_ji^rho_j
+        let mut z = E::G1::zero();
+        let mut r = E::G1::zero();
+        let mut m = vec![E::G1::zero(); self.pk.len()];
+        for ((mj, sig), rho_j) in batch.iter().zip(&rho) {
+            z += sig.z.mul(rho_j);
+            r += sig.r.mul(rho_j);
+            m.iter_mut()
+                .zip(mj)
+                .for_each(|(mi, mji)| *mi += mji.mul(rho_j));
+        }
+
+        // e(z, gz)e(r, gr) == Π e(m, pk)
+        let lhs = E::pairing(r, self.gr) + E::pairing(z, self.gz);
+        let rhs = m
+            .into_iter()
+            .zip(&self.pk)
+            .map(|(m, pk)| E::pairing(m, *pk))
+            .fold(PairingOutput::zero(), |acc, m| acc + m);
+        lhs == rhs
+    }
+
     /// Create GS proof from a signature that satisfies pairing product equation: e(z, gz)e(r, gr) == Π e(m, pk).
     ///
     /// # Example
@@ -126,7 +192,7 @@ impl<E: Pairing> VerifyKey<E> {
     /// let rng = &mut test_rng();
     /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
     /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
-    /// let sig = sk.sign(&m).unwrap();
+    /// let sig = sk.sign_basis(&[m.clone()]).unwrap()[0];
     /// assert!(pk.verify(&m, &sig));
     ///
     /// let crs = CRS::<E>::generate_crs(rng);
@@ -163,7 +229,7 @@ impl<E: Pairing> VerifyKey<E> {
     /// let rng = &mut test_rng();
     /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
     /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
-    /// let sig = sk.sign(&m).unwrap();
+    /// let sig = sk.sign_basis(&[m.clone()]).unwrap()[0];
     /// assert!(pk.verify(&m, &sig));
     ///
     /// let crs = CRS::<E>::generate_crs(rng);
@@ -172,6 +238,9 @@ impl<E: Pairing> VerifyKey<E> {
     /// assert!(pk.check_proof(&crs, &pf, &m));
     /// ```
     pub fn check_proof(&self, crs: &CRS<E>, cpf: &CProof<E>, m: &[E::G1Affine]) -> bool {
+        if m.len() != self.pk.len() {
+            return false;
+        }
         let target = m
             .iter()
             .zip(&self.pk)
@@ -179,6 +248,100 @@ impl<E: Pairing> VerifyKey<E> {
             .fold(PairingOutput::zero(), |acc, m| acc + m);
         check_proof_xbxb_t(crs, cpf, self.gz, self.gr, target)
     }
+
+    /// Generate a zero-knowledge GS proof of e(z, gz) e(r, gr) = Π e(m_i, pk_i).
+    ///
+    /// Unlike `generate_proof`, which is only witness-indistinguishable, the proof can be simulated
+    /// with the trapdoor of a hiding CRS (see `simulate_proof_zk`).
+    ///
+    /// # Example
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use groth_sahai::{AbstractCrs, CRS};
+    /// use transferable_ecash::lhsps;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (sk, pk) = lhsps::setup::<E, _>(rng, 5);
+    /// let m: Vec<G1> = (0..5).map(|_| G1::rand(rng)).collect();
+    /// let sig = sk.sign_basis(&[m.clone()]).unwrap()[0];
+    ///
+    /// let crs = CRS::<E>::generate_crs(rng);
+    /// let pf = pk.generate_proof_zk(rng, &crs, &m, &sig).unwrap();
+    /// assert!(pk.check_proof_zk(&crs, &pf, &m));
+    /// ```
+    pub fn generate_proof_zk<R: RngCore>(
+        &self,
+        rng: &mut R,
+        crs: &CRS<E>,
+        m: &[E::G1Affine],
+        sig: &Signature<E>,
+    ) -> Result<ZkProof<E>, ()> {
+        if m.len() != self.pk.len() {
+            return Err(());
+        }
+        zk::prove(
+            rng,
+            crs,
+            &[sig.z, sig.r],
+            &[self.gz, self.gr],
+            &self.target_pairs(m),
+        )
+    }
+
+    /// Check the zero-knowledge GS proof generated by `generate_proof_zk`.
+    pub fn check_proof_zk(&self, crs: &CRS<E>, pf: &ZkProof<E>, m: &[E::G1Affine]) -> bool {
+        m.len() == self.pk.len() && zk::verify(crs, pf, &[self.gz, self.gr], &self.target_pairs(m))
+    }
+
+    /// Simulate the proof of `generate_proof_zk` on message `m` without a signature,
+    /// using the trapdoor of a hiding CRS.
+    pub fn simulate_proof_zk<R: RngCore>(
+        &self,
+        rng: &mut R,
+        crs: &CRS<E>,
+        td: &CrsTrapdoor<E>,
+        m: &[E::G1Affine],
+    ) -> Result<ZkProof<E>, ()> {
+        if m.len() != self.pk.len() {
+            return Err(());
+        }
+        zk::simulate(rng, crs, td, &[self.gz, self.gr], &self.target_pairs(m))
+    }
+
+    // (m_i, pk_i) for the target Π e(m_i, pk_i)
+    fn target_pairs(&self, m: &[E::G1Affine]) -> Vec<(E::G1Affine, E::G2Affine)> {
+        m.iter().copied().zip(self.pk.iter().copied()).collect()
+    }
+}
+
+// rho_j = H(batch, j) truncated to BATCH_SECURITY_BITS bits.
+fn batch_coefficients<E: Pairing>(
+    batch: &[(Vec<E::G1Affine>, Signature<E>)],
+) -> Vec<E::ScalarField> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"transferable-ecash/lhsps/verify_batch");
+    for (m, sig) in batch {
+        let mut bytes = Vec::new();
+        (m.as_slice(), sig.z, sig.r)
+            .serialize_compressed(&mut bytes)
+            .expect("serialization to vec should not fail");
+        hasher.update(bytes);
+    }
+    let seed = hasher.finalize();
+
+    (0..batch.len() as u64)
+        .map(|j| {
+            let digest = Sha256::new()
+                .chain_update(seed)
+                .chain_update(j.to_le_bytes())
+                .finalize();
+            E::ScalarField::from_le_bytes_mod_order(&digest[..BATCH_SECURITY_BITS / 8])
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -186,6 +349,7 @@ mod tests {
     use crate::lhsps::setup;
     use ark_bls12_381::Bls12_381;
     use ark_ec::pairing::Pairing;
+    use ark_ec::AffineRepr;
     use ark_std::UniformRand;
     use groth_sahai::{AbstractCrs, CRS};
     use std::ops::Mul;
@@ -253,4 +417,65 @@ mod tests {
             .expect("proof should be valid");
         assert!(pk.check_proof(&crs, &pf, &m1_m2_d));
     }
+
+    #[test]
+    fn test_verify_batch() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = setup::<E, _>(rng, 5);
+
+        let mut batch = (0..8)
+            .map(|_| {
+                let m = (0..5).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+                let sig = sk.sign(&m).unwrap();
+                (m, sig)
+            })
+            .collect::<Vec<_>>();
+        assert!(pk.verify_batch(&batch));
+        assert!(pk.verify_batch(&[]));
+
+        // one forged signature
+        let mut forged = batch.clone();
+        forged[3].1.z = G1::rand(rng);
+        assert!(!pk.verify_batch(&forged));
+
+        // signatures of two messages are swapped
+        let mut forged = batch.clone();
+        let sig = forged[0].1;
+        forged[0].1 = forged[1].1;
+        forged[1].1 = sig;
+        assert!(!pk.verify_batch(&forged));
+
+        // forged signatures which cancel out each other in a plain (not randomized) sum
+        let mut forged = batch.clone();
+        let delta = G1::rand(rng);
+        forged[0].1.z = (forged[0].1.z + delta).into();
+        forged[1].1.z = (forged[1].1.z + delta.mul(-Fr::from(1u64))).into();
+        assert!(!pk.verify_batch(&forged));
+
+        // a trivial message
+        batch[2].0 = vec![G1::zero(); 5];
+        assert!(!pk.verify_batch(&batch));
+    }
+
+    #[test]
+    fn test_zk_proof_simulation() {
+        let rng = &mut ark_std::test_rng();
+        let (crs, td) = crate::crs::generate_hiding_crs_with_trapdoor::<E, _>(rng);
+
+        let (sk, pk) = setup::<E, _>(rng, 5);
+        let m = (0..5).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let sig = sk.sign(&m).unwrap();
+        let pf = pk.generate_proof_zk(rng, &crs, &m, &sig).unwrap();
+        assert!(pk.check_proof_zk(&crs, &pf, &m));
+
+        // a message without signature
+        let m2 = (0..5).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        assert!(pk.generate_proof_zk(rng, &crs, &m2, &sig).is_err());
+        assert!(!pk.check_proof_zk(&crs, &pf, &m2));
+
+        // the simulator proves any message with the trapdoor of the hiding CRS
+        let pf = pk.simulate_proof_zk(rng, &crs, &td, &m2).unwrap();
+        assert!(pk.check_proof_zk(&crs, &pf, &m2));
+        assert!(pk.simulate_proof_zk(rng, &crs, &td, &m2[..4]).is_err());
+    }
 }
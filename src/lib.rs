@@ -1,8 +1,17 @@
 #![doc = include_str!("../README.md")]
 
+pub mod coin;
+pub mod crs;
+pub mod dleq;
 pub mod double_spending;
 pub mod encrypt_e;
+#[cfg(test)]
+mod fuzz;
+pub mod key_file;
 pub mod lhsps;
 pub mod params;
-pub(crate) mod proof;
+pub mod proof;
 pub mod rcca;
+pub(crate) mod shamir;
+pub mod tracing;
+pub mod wallet;
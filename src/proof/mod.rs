@@ -0,0 +1,861 @@
+//! This module provides functions related to GS proof.
+//!
+//! `Equation` is a typed builder of Groth-Sahai equations. It produces the statement (e.g. `PPE`),
+//! proves it and verifies it, so that callers do not lay out the constant vectors and the gamma
+//! matrix by hand. The `create_proof_*`/`check_proof_*` helpers are for internal use.
+//!
+//! The submodule `zk` provides zero-knowledge proofs for equations with non-trivial targets, and
+//! the submodule `shared` provides proofs of several equations over the same committed variables.
+
+pub mod shared;
+pub mod zk;
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{rand::RngCore, One, UniformRand, Zero};
+use groth_sahai::data_structures::{Com1, Com2};
+use groth_sahai::statement::{QuadEqu, MSMEG1, MSMEG2, PPE};
+use groth_sahai::verifier::Verifiable;
+use groth_sahai::EquType;
+use groth_sahai::{
+    prover::{CProof, Provable},
+    Matrix, CRS,
+};
+use std::marker::PhantomData;
+use std::ops::Mul;
+
+/// A term of an equation, which is either a public constant or the i-th variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Term<T> {
+    Const(T),
+    Var(usize),
+}
+
+/// The kind of a Groth-Sahai equation, i.e. the types of its X-side, Y-side and target.
+///
+/// An equation is Σ A_j * Y_j + Σ X_i * B_i + ΣΣ gamma_ij X_i * Y_j = t, where `*` is
+/// the pairing, or the scalar multiplication, or the field multiplication.
+pub trait EquationKind<E: Pairing> {
+    type X: Copy;
+    type Y: Copy;
+    type Target: Copy + PartialEq;
+    type Statement: Provable<E, Self::X, Self::Y, Self::Target> + Verifiable<E>;
+
+    const EQU_TYPE: EquType;
+    /// The number of elements of pi (in B2) and theta (in B1) in a proof of this kind.
+    const PROOF_LEN: (usize, usize);
+
+    fn zero_x() -> Self::X;
+    fn zero_y() -> Self::Y;
+    fn zero_target() -> Self::Target;
+    fn add_x(a: Self::X, b: Self::X) -> Self::X;
+    fn add_y(a: Self::Y, b: Self::Y) -> Self::Y;
+    /// t - x * y
+    fn sub_product(t: Self::Target, x: Self::X, y: Self::Y) -> Self::Target;
+    fn statement(
+        a_consts: Vec<Self::X>,
+        b_consts: Vec<Self::Y>,
+        gamma: Matrix<E::ScalarField>,
+        target: Self::Target,
+    ) -> Self::Statement;
+}
+
+/// Pairing product equation: Π e(A_j, Y_j) Π e(X_i, B_i) ΠΠ e(X_i, Y_j)^gamma_ij = t.
+#[derive(Clone, Copy, Debug)]
+pub struct PairingProduct;
+
+/// Multi-scalar multiplication equation in G1: Σ A_j y_j + Σ X_i b_i + ΣΣ gamma_ij X_i y_j = t.
+#[derive(Clone, Copy, Debug)]
+pub struct MultiScalarG1;
+
+/// Multi-scalar multiplication equation in G2: Σ a_j Y_j + Σ x_i B_i + ΣΣ gamma_ij x_i Y_j = t.
+#[derive(Clone, Copy, Debug)]
+pub struct MultiScalarG2;
+
+/// Quadratic equation in the scalar field: Σ a_j y_j + Σ x_i b_i + ΣΣ gamma_ij x_i y_j = t.
+#[derive(Clone, Copy, Debug)]
+pub struct Quadratic;
+
+impl<E: Pairing> EquationKind<E> for PairingProduct {
+    type X = E::G1Affine;
+    type Y = E::G2Affine;
+    type Target = PairingOutput<E>;
+    type Statement = PPE<E>;
+
+    const EQU_TYPE: EquType = EquType::PairingProduct;
+    const PROOF_LEN: (usize, usize) = (2, 2);
+
+    fn zero_x() -> E::G1Affine {
+        E::G1Affine::zero()
+    }
+    fn zero_y() -> E::G2Affine {
+        E::G2Affine::zero()
+    }
+    fn zero_target() -> PairingOutput<E> {
+        PairingOutput::zero()
+    }
+    fn add_x(a: E::G1Affine, b: E::G1Affine) -> E::G1Affine {
+        (a + b).into()
+    }
+    fn add_y(a: E::G2Affine, b: E::G2Affine) -> E::G2Affine {
+        (a + b).into()
+    }
+    fn sub_product(t: PairingOutput<E>, x: E::G1Affine, y: E::G2Affine) -> PairingOutput<E> {
+        t - E::pairing(x, y)
+    }
+    fn statement(
+        a_consts: Vec<E::G1Affine>,
+        b_consts: Vec<E::G2Affine>,
+        gamma: Matrix<E::ScalarField>,
+        target: PairingOutput<E>,
+    ) -> PPE<E> {
+        PPE {
+            a_consts,
+            b_consts,
+            gamma,
+            target,
+        }
+    }
+}
+
+impl<E: Pairing> EquationKind<E> for MultiScalarG1 {
+    type X = E::G1Affine;
+    type Y = E::ScalarField;
+    type Target = E::G1Affine;
+    type Statement = MSMEG1<E>;
+
+    const EQU_TYPE: EquType = EquType::MultiScalarG1;
+    const PROOF_LEN: (usize, usize) = (2, 1);
+
+    fn zero_x() -> E::G1Affine {
+        E::G1Affine::zero()
+    }
+    fn zero_y() -> E::ScalarField {
+        E::ScalarField::zero()
+    }
+    fn zero_target() -> E::G1Affine {
+        E::G1Affine::zero()
+    }
+    fn add_x(a: E::G1Affine, b: E::G1Affine) -> E::G1Affine {
+        (a + b).into()
+    }
+    fn add_y(a: E::ScalarField, b: E::ScalarField) -> E::ScalarField {
+        a + b
+    }
+    fn sub_product(t: E::G1Affine, x: E::G1Affine, y: E::ScalarField) -> E::G1Affine {
+        (t.into_group() - x.mul(y)).into()
+    }
+    fn statement(
+        a_consts: Vec<E::G1Affine>,
+        b_consts: Vec<E::ScalarField>,
+        gamma: Matrix<E::ScalarField>,
+        target: E::G1Affine,
+    ) -> MSMEG1<E> {
+        MSMEG1 {
+            a_consts,
+            b_consts,
+            gamma,
+            target,
+        }
+    }
+}
+
+impl<E: Pairing> EquationKind<E> for MultiScalarG2 {
+    type X = E::ScalarField;
+    type Y = E::G2Affine;
+    type Target = E::G2Affine;
+    type Statement = MSMEG2<E>;
+
+    const EQU_TYPE: EquType = EquType::MultiScalarG2;
+    const PROOF_LEN: (usize, usize) = (1, 2);
+
+    fn zero_x() -> E::ScalarField {
+        E::ScalarField::zero()
+    }
+    fn zero_y() -> E::G2Affine {
+        E::G2Affine::zero()
+    }
+    fn zero_target() -> E::G2Affine {
+        E::G2Affine::zero()
+    }
+    fn add_x(a: E::ScalarField, b: E::ScalarField) -> E::ScalarField {
+        a + b
+    }
+    fn add_y(a: E::G2Affine, b: E::G2Affine) -> E::G2Affine {
+        (a + b).into()
+    }
+    fn sub_product(t: E::G2Affine, x: E::ScalarField, y: E::G2Affine) -> E::G2Affine {
+        (t.into_group() - y.mul(x)).into()
+    }
+    fn statement(
+        a_consts: Vec<E::ScalarField>,
+        b_consts: Vec<E::G2Affine>,
+        gamma: Matrix<E::ScalarField>,
+        target: E::G2Affine,
+    ) -> MSMEG2<E> {
+        MSMEG2 {
+            a_consts,
+            b_consts,
+            gamma,
+            target,
+        }
+    }
+}
+
+impl<E: Pairing> EquationKind<E> for Quadratic {
+    type X = E::ScalarField;
+    type Y = E::ScalarField;
+    type Target = E::ScalarField;
+    type Statement = QuadEqu<E>;
+
+    const EQU_TYPE: EquType = EquType::Quadratic;
+    const PROOF_LEN: (usize, usize) = (1, 1);
+
+    fn zero_x() -> E::ScalarField {
+        E::ScalarField::zero()
+    }
+    fn zero_y() -> E::ScalarField {
+        E::ScalarField::zero()
+    }
+    fn zero_target() -> E::ScalarField {
+        E::ScalarField::zero()
+    }
+    fn add_x(a: E::ScalarField, b: E::ScalarField) -> E::ScalarField {
+        a + b
+    }
+    fn add_y(a: E::ScalarField, b: E::ScalarField) -> E::ScalarField {
+        a + b
+    }
+    fn sub_product(t: E::ScalarField, x: E::ScalarField, y: E::ScalarField) -> E::ScalarField {
+        t - x * y
+    }
+    fn statement(
+        a_consts: Vec<E::ScalarField>,
+        b_consts: Vec<E::ScalarField>,
+        gamma: Matrix<E::ScalarField>,
+        target: E::ScalarField,
+    ) -> QuadEqu<E> {
+        QuadEqu {
+            a_consts,
+            b_consts,
+            gamma,
+            target,
+        }
+    }
+}
+
+/// A typed builder of Groth-Sahai equations.
+///
+/// Each `pair` adds a term to the left hand side of the equation, and `target` sets its right hand side.
+/// - `pair(Const(a), Var(j))` adds e(a, Y_j),
+/// - `pair(Var(i), Const(b))` adds e(X_i, b),
+/// - `pair(Var(i), Var(j))` adds e(X_i, Y_j),
+/// - `pair(Const(a), Const(b))` moves e(a, b) to the target.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::{Pairing, PairingOutput};
+/// use ark_std::{test_rng, UniformRand, Zero};
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use std::ops::{Mul, Neg};
+/// use transferable_ecash::proof::{Equation, PairingProduct, Term::{Const, Var}};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+/// type G2 = <E as Pairing>::G2Affine;
+/// type Fr = <E as Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let crs = CRS::<E>::generate_crs(rng);
+///
+/// // e(a, y) e(x, b) = 1, where y = b^-s and x = a^s
+/// let (a, b, s) = (G1::rand(rng), G2::rand(rng), Fr::rand(rng));
+/// let (x, y): (G1, G2) = (a.mul(s).into(), b.mul(s.neg()).into());
+/// let equ = Equation::<E, PairingProduct>::new()
+///     .pair(Const(a), Var(0))
+///     .pair(Var(0), Const(b))
+///     .target(PairingOutput::zero());
+/// let pf = equ.prove(rng, &crs, &[x], &[y]).unwrap();
+/// assert!(equ.verify(&crs, &pf));
+/// ```
+pub struct Equation<E: Pairing, K: EquationKind<E>> {
+    terms: Vec<(Term<K::X>, Term<K::Y>)>,
+    target: K::Target,
+    _e: PhantomData<E>,
+}
+
+impl<E: Pairing, K: EquationKind<E>> Default for Equation<E, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Pairing, K: EquationKind<E>> Equation<E, K> {
+    /// Creates an empty equation with the zero target.
+    pub fn new() -> Self {
+        Self {
+            terms: Vec::new(),
+            target: K::zero_target(),
+            _e: PhantomData,
+        }
+    }
+
+    /// Adds the term x * y to the equation.
+    pub fn pair(mut self, x: Term<K::X>, y: Term<K::Y>) -> Self {
+        self.terms.push((x, y));
+        self
+    }
+
+    /// Sets the target of the equation.
+    pub fn target(mut self, t: K::Target) -> Self {
+        self.target = t;
+        self
+    }
+
+    /// The number of X variables (i.e. the largest index of X variable plus 1).
+    pub fn num_x(&self) -> usize {
+        self.terms
+            .iter()
+            .filter_map(|(x, _)| match x {
+                Term::Var(i) => Some(i + 1),
+                Term::Const(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The number of Y variables (i.e. the largest index of Y variable plus 1).
+    pub fn num_y(&self) -> usize {
+        self.terms
+            .iter()
+            .filter_map(|(_, y)| match y {
+                Term::Var(j) => Some(j + 1),
+                Term::Const(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Produces the Groth-Sahai statement of the equation.
+    ///
+    /// The layout always has a zero X variable at index 0, and has a zero Y variable if there
+    /// is no Y variable.
+    pub fn statement(&self) -> K::Statement {
+        let (m, n) = self.dims();
+        let y_offset = n - self.num_y();
+
+        let mut a_consts = vec![K::zero_x(); n];
+        let mut b_consts = vec![K::zero_y(); m];
+        let mut gamma: Matrix<E::ScalarField> = vec![vec![E::ScalarField::zero(); n]; m];
+        let mut target = self.target;
+
+        for (x, y) in self.terms.iter() {
+            match (x, y) {
+                (Term::Const(a), Term::Var(j)) => {
+                    a_consts[j + y_offset] = K::add_x(a_consts[j + y_offset], *a)
+                }
+                (Term::Var(i), Term::Const(b)) => b_consts[i + 1] = K::add_y(b_consts[i + 1], *b),
+                (Term::Var(i), Term::Var(j)) => gamma[i + 1][j + y_offset] += E::ScalarField::one(),
+                (Term::Const(a), Term::Const(b)) => target = K::sub_product(target, *a, *b),
+            }
+        }
+
+        K::statement(a_consts, b_consts, gamma, target)
+    }
+
+    /// Commits to the variables and proves the equation. It fails if the values do not satisfy the equation.
+    pub fn prove<R: RngCore>(
+        &self,
+        rng: &mut R,
+        crs: &CRS<E>,
+        xvals: &[K::X],
+        yvals: &[K::Y],
+    ) -> Result<CProof<E>, ()> {
+        if xvals.len() != self.num_x() || yvals.len() != self.num_y() {
+            return Err(());
+        }
+        let (_, n) = self.dims();
+
+        let mut xvars = vec![K::zero_x()];
+        xvars.extend_from_slice(xvals);
+        let mut yvars = vec![K::zero_y(); n - yvals.len()];
+        yvars.extend_from_slice(yvals);
+
+        let equ = self.statement();
+        let proof: CProof<E> = equ.commit_and_prove(&xvars, &yvars, crs, rng);
+        equ.verify(&proof, crs).then_some(proof).ok_or(())
+    }
+
+    /// Verifies the proof of the equation, where the proof contains commitments to the variables.
+    ///
+    /// The structure of the proof is checked by `ProofShape` before it reaches groth-sahai.
+    pub fn verify(&self, crs: &CRS<E>, cp: &CProof<E>) -> bool {
+        self.shape().check(cp) && self.statement().verify(cp, crs)
+    }
+
+    /// The expected structure of a proof of this equation.
+    pub fn shape(&self) -> ProofShape {
+        let (m, n) = self.dims();
+        ProofShape {
+            equ_type: K::EQU_TYPE,
+            num_x: m,
+            num_y: n,
+            pi_len: K::PROOF_LEN.0,
+            theta_len: K::PROOF_LEN.1,
+        }
+    }
+
+    // (m, n) = (number of X variables, number of Y variables) in the statement.
+    fn dims(&self) -> (usize, usize) {
+        (self.num_x() + 1, self.num_y().max(1))
+    }
+}
+
+/// The size in bytes of the group elements of a proof (commitments, pi and theta) in compressed form.
+pub fn proof_size<E: Pairing>(cp: &CProof<E>) -> usize {
+    let g1_size = E::G1Affine::zero().compressed_size();
+    let g2_size = E::G2Affine::zero().compressed_size();
+
+    // each element of B1 (resp. B2) consists of two elements of G1 (resp. G2).
+    let b1_len = cp.xcoms.coms.len() + cp.equ_proofs.iter().map(|p| p.theta.len()).sum::<usize>();
+    let b2_len = cp.ycoms.coms.len() + cp.equ_proofs.iter().map(|p| p.pi.len()).sum::<usize>();
+    2 * (b1_len * g1_size + b2_len * g2_size)
+}
+
+/// The structure of a `CProof` of a single equation.
+///
+/// A `CProof` received from another party can be malformed (e.g. truncated or padded), and such
+/// a proof may make groth-sahai panic during verification. `check` must pass before the proof is verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofShape {
+    pub equ_type: EquType,
+    /// the number of commitments to X variables (including the padding).
+    pub num_x: usize,
+    /// the number of commitments to Y variables (including the padding).
+    pub num_y: usize,
+    pub pi_len: usize,
+    pub theta_len: usize,
+}
+
+impl ProofShape {
+    /// Checks that the proof has exactly one equation proof of the expected type,
+    /// and that all of its vectors have the expected lengths.
+    pub fn check<E: Pairing>(&self, cp: &CProof<E>) -> bool {
+        if cp.equ_proofs.len() != 1
+            || cp.xcoms.coms.len() != self.num_x
+            || cp.ycoms.coms.len() != self.num_y
+        {
+            return false;
+        }
+
+        let equ_proof = &cp.equ_proofs[0];
+        equ_proof.equ_type == self.equ_type
+            && equ_proof.pi.len() == self.pi_len
+            && equ_proof.theta.len() == self.theta_len
+    }
+}
+
+/// Create GS proof for pairing product equation: e(A, Y) + e(X, B) = 0.
+/// This function is used by encryption function in EncryptKey.
+pub(crate) fn create_proof_ayxb<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    a: E::G1Affine,
+    y: E::G2Affine,
+    x: E::G1Affine,
+    b: E::G2Affine,
+) -> Result<CProof<E>, ()> {
+    equation_ayxb(a, b).prove(rng, crs, &[x], &[y])
+}
+
+/// Check GS proof for pairing product equation: e(A, Y) + e(X, B) = 0,
+/// where the proof contains commitments to X and Y (i.e. have the knowledge of X and Y).
+pub(crate) fn check_proof_ayxb<E: Pairing>(
+    crs: &CRS<E>,
+    cp: &CProof<E>,
+    a: E::G1Affine,
+    b: E::G2Affine,
+) -> bool {
+    equation_ayxb(a, b).verify(crs, cp)
+}
+
+// e(A, Y) + e(X, B) = 0
+fn equation_ayxb<E: Pairing>(a: E::G1Affine, b: E::G2Affine) -> Equation<E, PairingProduct> {
+    Equation::new()
+        .pair(Term::Const(a), Term::Var(0))
+        .pair(Term::Var(0), Term::Const(b))
+}
+
+/// Create GS proof for pairing product equation: e(X1, B1) + e(X2, B2) = T.
+/// This function is used by encryption function in EncryptKey.
+pub(crate) fn create_proof_xbxb_t<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    x1: E::G1Affine,
+    b1: E::G2Affine,
+    x2: E::G1Affine,
+    b2: E::G2Affine,
+    target: PairingOutput<E>,
+) -> Result<CProof<E>, ()> {
+    create_proof_xb_t(rng, crs, &[x1, x2], &[b1, b2], target)
+}
+
+/// Check GS proof for pairing product equation: e(X1, B1) + e(X2, B2) = T.
+/// where the proof contains commitments to X1 and X2 (i.e. have the knowledge of X1 and X2).
+pub(crate) fn check_proof_xbxb_t<E: Pairing>(
+    crs: &CRS<E>,
+    cp: &CProof<E>,
+    b1: E::G2Affine,
+    b2: E::G2Affine,
+    t: PairingOutput<E>,
+) -> bool {
+    check_proof_xb_t(crs, cp, &[b1, b2], t)
+}
+
+/// Create GS proof for pairing product equation: Π e(X_i, B_i) = T.
+/// This function generalizes `create_proof_xbxb_t` to any number of variables.
+pub(crate) fn create_proof_xb_t<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    x: &[E::G1Affine],
+    b: &[E::G2Affine],
+    target: PairingOutput<E>,
+) -> Result<CProof<E>, ()> {
+    if x.is_empty() || x.len() != b.len() {
+        return Err(());
+    }
+    equation_xb_t(b, target).prove(rng, crs, x, &[])
+}
+
+/// Check GS proof for pairing product equation: Π e(X_i, B_i) = T.
+/// where the proof contains commitments to X_i (i.e. have the knowledge of X_i).
+pub(crate) fn check_proof_xb_t<E: Pairing>(
+    crs: &CRS<E>,
+    cp: &CProof<E>,
+    b: &[E::G2Affine],
+    t: PairingOutput<E>,
+) -> bool {
+    if b.is_empty() {
+        return false;
+    }
+    equation_xb_t(b, t).verify(crs, cp)
+}
+
+// Π e(X_i, B_i) = T
+fn equation_xb_t<E: Pairing>(
+    b: &[E::G2Affine],
+    target: PairingOutput<E>,
+) -> Equation<E, PairingProduct> {
+    b.iter()
+        .enumerate()
+        .fold(Equation::new(), |equ, (i, b_i)| {
+            equ.pair(Term::Var(i), Term::Const(*b_i))
+        })
+        .target(target)
+}
+
+/// Re-randomizes a proof of a pairing product equation in place.
+///
+/// The commitments are refreshed as c_i' = c_i + Σ_k r_ik u_k and d_j' = d_j + Σ_l s_jl v_l, and
+/// the proof elements are adjusted for the new commitments:
+/// - pi' = pi + R^T ι2(B) + R^T Γ d + (R^T Γ S + T) v,
+/// - theta' = theta + S^T ι1(A) + S^T Γ^T c - T^T u,
+///
+/// where R, S and T are uniformly random. The output is distributed like a fresh proof of the same
+/// equation with the same variables. It fails if the proof is malformed.
+pub fn rerandomize_proof<E: Pairing, R: RngCore>(
+    crs: &CRS<E>,
+    equ: &Equation<E, PairingProduct>,
+    cp: &mut CProof<E>,
+    rng: &mut R,
+) -> Result<(), ()> {
+    if !equ.shape().check(cp) || crs.u.len() != 2 || crs.v.len() != 2 {
+        return Err(());
+    }
+    let PPE {
+        a_consts,
+        b_consts,
+        gamma,
+        ..
+    } = equ.statement();
+    let (m, n) = equ.dims();
+
+    let rand_matrix = |rng: &mut R, rows: usize| -> Matrix<E::ScalarField> {
+        (0..rows)
+            .map(|_| (0..2).map(|_| E::ScalarField::rand(rng)).collect())
+            .collect()
+    };
+    let r = rand_matrix(rng, m);
+    let s = rand_matrix(rng, n);
+    let t = rand_matrix(rng, 2);
+
+    let c = cp.xcoms.coms.clone();
+    let d = cp.ycoms.coms.clone();
+    let equ_proof = &mut cp.equ_proofs[0];
+
+    for k in 0..2 {
+        // pi_k' = pi_k + Σ_i r_ik ι2(B_i) + ΣΣ r_ik Γ_ij d_j + Σ_l ((R^T Γ S)_kl + t_kl) v_l
+        let mut terms = vec![(E::ScalarField::one(), equ_proof.pi[k])];
+        for i in 0..m {
+            terms.push((r[i][k], Com2(E::G2Affine::zero(), b_consts[i])));
+            for j in 0..n {
+                terms.push((r[i][k] * gamma[i][j], d[j]));
+            }
+        }
+        for l in 0..2 {
+            let rgs = (0..m)
+                .flat_map(|i| (0..n).map(move |j| (i, j)))
+                .map(|(i, j)| r[i][k] * gamma[i][j] * s[j][l])
+                .sum::<E::ScalarField>();
+            terms.push((rgs + t[k][l], crs.v[l]));
+        }
+        equ_proof.pi[k] = linear_combination2(&terms);
+    }
+
+    for l in 0..2 {
+        // theta_l' = theta_l + Σ_j s_jl ι1(A_j) + ΣΣ s_jl Γ_ij c_i - Σ_k t_kl u_k
+        let mut terms = vec![(E::ScalarField::one(), equ_proof.theta[l])];
+        for j in 0..n {
+            terms.push((s[j][l], Com1(E::G1Affine::zero(), a_consts[j])));
+            for i in 0..m {
+                terms.push((s[j][l] * gamma[i][j], c[i]));
+            }
+        }
+        for k in 0..2 {
+            terms.push((-t[k][l], crs.u[k]));
+        }
+        equ_proof.theta[l] = linear_combination1(&terms);
+    }
+
+    // c_i' = c_i + Σ_k r_ik u_k, d_j' = d_j + Σ_l s_jl v_l
+    for (c_i, r_i) in cp.xcoms.coms.iter_mut().zip(&r) {
+        *c_i = linear_combination1(&[
+            (E::ScalarField::one(), *c_i),
+            (r_i[0], crs.u[0]),
+            (r_i[1], crs.u[1]),
+        ]);
+    }
+    for (d_j, s_j) in cp.ycoms.coms.iter_mut().zip(&s) {
+        *d_j = linear_combination2(&[
+            (E::ScalarField::one(), *d_j),
+            (s_j[0], crs.v[0]),
+            (s_j[1], crs.v[1]),
+        ]);
+    }
+
+    Ok(())
+}
+
+// Σ s_i c_i in B1
+pub(crate) fn linear_combination1<E: Pairing>(terms: &[(E::ScalarField, Com1<E>)]) -> Com1<E> {
+    let (a, b) = terms
+        .iter()
+        .fold((E::G1::zero(), E::G1::zero()), |(a, b), (s, c)| {
+            (a + c.0.mul(s), b + c.1.mul(s))
+        });
+    Com1(a.into(), b.into())
+}
+
+// Σ s_i d_i in B2
+pub(crate) fn linear_combination2<E: Pairing>(terms: &[(E::ScalarField, Com2<E>)]) -> Com2<E> {
+    let (a, b) = terms
+        .iter()
+        .fold((E::G2::zero(), E::G2::zero()), |(a, b), (s, d)| {
+            (a + d.0.mul(s), b + d.1.mul(s))
+        });
+    Com2(a.into(), b.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_ec::AffineRepr;
+    use ark_std::{test_rng, UniformRand, Zero};
+    use groth_sahai::{prover::CProof, AbstractCrs, CRS};
+    use std::ops::Mul;
+
+    use super::{Equation, MultiScalarG1, MultiScalarG2, PairingProduct, Quadratic, Term::*};
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type G2 = <E as Pairing>::G2Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_pairing_product() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+
+        // e(x1, y1) e(a, y1) e(x2, b) = e(c, d), where x1 = c - a, y1 = d and x2 = 0
+        let (a, b, c, d) = (G1::rand(rng), G2::rand(rng), G1::rand(rng), G2::rand(rng));
+        let x1: G1 = (c.into_group() - a).into();
+        let x2 = G1::zero();
+        let equ = Equation::<E, PairingProduct>::new()
+            .pair(Var(0), Var(0))
+            .pair(Const(a), Var(0))
+            .pair(Var(1), Const(b))
+            .target(E::pairing(c, d));
+        let pf = equ.prove(rng, &crs, &[x1, x2], &[d]).unwrap();
+        assert!(equ.verify(&crs, &pf));
+
+        // invalid witness
+        assert!(equ.prove(rng, &crs, &[x1, x2], &[G2::rand(rng)]).is_err());
+        // wrong number of variables
+        assert!(equ.prove(rng, &crs, &[x1], &[d]).is_err());
+        // a different equation
+        let equ2 = Equation::<E, PairingProduct>::new()
+            .pair(Var(0), Var(0))
+            .pair(Const(a), Var(0))
+            .pair(Var(1), Const(b))
+            .target(E::pairing(a, d));
+        assert!(!equ2.verify(&crs, &pf));
+    }
+
+    #[test]
+    fn test_constants_move_to_target() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+
+        // e(x, b) e(a, b) = e(c, b), where x = c - a
+        let (a, b, c) = (G1::rand(rng), G2::rand(rng), G1::rand(rng));
+        let x: G1 = (c.into_group() - a).into();
+        let equ = Equation::<E, PairingProduct>::new()
+            .pair(Var(0), Const(b))
+            .pair(Const(a), Const(b))
+            .target(E::pairing(c, b));
+        let pf = equ.prove(rng, &crs, &[x], &[]).unwrap();
+        assert!(equ.verify(&crs, &pf));
+    }
+
+    #[test]
+    fn test_multi_scalar_and_quadratic() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+
+        // MSM in G1: a y + x b = t
+        let (a, x) = (G1::rand(rng), G1::rand(rng));
+        let (y, b) = (Fr::rand(rng), Fr::rand(rng));
+        let t: G1 = (a.mul(y) + x.mul(b)).into();
+        let equ = Equation::<E, MultiScalarG1>::new()
+            .pair(Const(a), Var(0))
+            .pair(Var(0), Const(b))
+            .target(t);
+        let pf = equ.prove(rng, &crs, &[x], &[y]).unwrap();
+        assert!(equ.verify(&crs, &pf));
+        // proof of another kind of equation
+        assert!(!Equation::<E, PairingProduct>::new()
+            .pair(Var(0), Const(G2::rand(rng)))
+            .verify(&crs, &pf));
+
+        // MSM in G2: x B = t
+        let (x, bb) = (Fr::rand(rng), G2::rand(rng));
+        let equ = Equation::<E, MultiScalarG2>::new()
+            .pair(Var(0), Const(bb))
+            .target(bb.mul(x).into());
+        let pf = equ.prove(rng, &crs, &[x], &[]).unwrap();
+        assert!(equ.verify(&crs, &pf));
+
+        // quadratic: x y + a y = t
+        let (x, y, a) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+        let equ = Equation::<E, Quadratic>::new()
+            .pair(Var(0), Var(0))
+            .pair(Const(a), Var(0))
+            .target(x * y + a * y);
+        let pf = equ.prove(rng, &crs, &[x], &[y]).unwrap();
+        assert!(equ.verify(&crs, &pf));
+    }
+
+    #[test]
+    fn test_malformed_proofs() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+
+        // e(A, Y) + e(X, B) = 0
+        let (a, b, s) = (G1::rand(rng), G2::rand(rng), Fr::rand(rng));
+        let (x, y): (G1, G2) = (a.mul(s).into(), b.mul(-s).into());
+        let pf = super::create_proof_ayxb(rng, &crs, a, y, x, b).unwrap();
+        assert!(super::check_proof_ayxb(&crs, &pf, a, b));
+
+        let malformed: Vec<fn(&mut CProof<E>)> = vec![
+            // truncated
+            |pf| pf.equ_proofs.clear(),
+            |pf| {
+                pf.xcoms.coms.pop();
+            },
+            |pf| {
+                pf.ycoms.coms.pop();
+            },
+            |pf| {
+                pf.equ_proofs[0].pi.pop();
+            },
+            |pf| {
+                pf.equ_proofs[0].theta.pop();
+            },
+            // padded
+            |pf| pf.equ_proofs.push(pf.equ_proofs[0].clone()),
+            |pf| pf.xcoms.coms.push(pf.xcoms.coms[0]),
+            |pf| pf.ycoms.coms.push(pf.ycoms.coms[0]),
+            |pf| pf.equ_proofs[0].pi.push(pf.equ_proofs[0].pi[0]),
+            |pf| pf.equ_proofs[0].theta.push(pf.equ_proofs[0].theta[0]),
+        ];
+        let shape = super::equation_ayxb::<E>(a, b).shape();
+        for malform in malformed {
+            let mut bad = pf.clone();
+            malform(&mut bad);
+            assert!(!shape.check(&bad));
+            assert!(!super::check_proof_ayxb(&crs, &bad, a, b));
+        }
+
+        // a proof of Π e(X_i, B_i) = T of different dimension
+        let t = E::pairing(a, b);
+        let pf = super::create_proof_xb_t(rng, &crs, &[a, G1::zero()], &[b, b], t).unwrap();
+        assert!(super::check_proof_xb_t(&crs, &pf, &[b, b], t));
+        assert!(!super::check_proof_xb_t(&crs, &pf, &[b, b, b], t));
+        assert!(!super::check_proof_xb_t(&crs, &pf, &[b], t));
+    }
+
+    #[test]
+    fn test_rerandomize_proof() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+
+        // e(x1, y) e(a, y) e(x2, b) = e(c, y), where x1 = c - a and x2 = 0
+        let (a, b, c, y) = (G1::rand(rng), G2::rand(rng), G1::rand(rng), G2::rand(rng));
+        let x1: G1 = (c.into_group() - a).into();
+        let equ = Equation::<E, PairingProduct>::new()
+            .pair(Var(0), Var(0))
+            .pair(Const(a), Var(0))
+            .pair(Var(1), Const(b))
+            .target(E::pairing(c, y));
+        let pf = equ.prove(rng, &crs, &[x1, G1::zero()], &[y]).unwrap();
+
+        let mut pf2 = pf.clone();
+        super::rerandomize_proof(&crs, &equ, &mut pf2, rng).unwrap();
+        assert!(equ.verify(&crs, &pf));
+        assert!(equ.verify(&crs, &pf2));
+
+        // no group element is shared between the two proofs
+        let b1 = |pf: &CProof<E>| {
+            let mut v = pf.xcoms.coms.clone();
+            v.extend(&pf.equ_proofs[0].theta);
+            v.iter().flat_map(|c| [c.0, c.1]).collect::<Vec<_>>()
+        };
+        let b2 = |pf: &CProof<E>| {
+            let mut v = pf.ycoms.coms.clone();
+            v.extend(&pf.equ_proofs[0].pi);
+            v.iter().flat_map(|d| [d.0, d.1]).collect::<Vec<_>>()
+        };
+        assert!(b1(&pf).iter().all(|p| !b1(&pf2).contains(p)));
+        assert!(b2(&pf).iter().all(|p| !b2(&pf2).contains(p)));
+
+        // it can be re-randomized again
+        super::rerandomize_proof(&crs, &equ, &mut pf2, rng).unwrap();
+        assert!(equ.verify(&crs, &pf2));
+
+        // malformed proof
+        pf2.equ_proofs[0].pi.pop();
+        assert!(super::rerandomize_proof(&crs, &equ, &mut pf2, rng).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! This module provides GS proofs of several pairing product equations over shared commitments.
+//!
+//! `Equation::prove` commits to the variables of each equation separately, so the proofs of two
+//! equations do not show that they are satisfied by the same values. `prove` commits to the
+//! variables once and proves every equation against the same commitments, i.e. the variable X_i
+//! (resp. Y_j) has the same value in all the equations. The proofs are witness-indistinguishable.
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::{rand::RngCore, One, Zero};
+use groth_sahai::data_structures::{Com1, Com2};
+use groth_sahai::statement::PPE;
+use groth_sahai::{Matrix, CRS};
+use std::ops::Mul;
+
+use super::zk::{
+    commit1, commit2, elements_size, from_parts, prove_equation, rand_matrix, to_parts,
+    verify_equation, EquationProof, ProofParts,
+};
+use super::{Equation, PairingProduct, Term};
+
+/// A proof of pairing product equations over the shared commitments to X = [X_0, ..., X_m-1]
+/// and Y = [Y_0, ..., Y_n-1].
+#[derive(Clone)]
+pub struct SharedProof<E: Pairing> {
+    pub(crate) xcoms: Vec<Com1<E>>,
+    pub(crate) ycoms: Vec<Com2<E>>,
+    // one proof for each equation
+    pub(crate) proofs: Vec<EquationProof<E>>,
+}
+
+impl<E: Pairing> SharedProof<E> {
+    /// The size in bytes of the group elements of the proof (commitments, pi and theta) in compressed form.
+    pub fn size_in_bytes(&self) -> usize {
+        elements_size::<E>(self.xcoms.len(), self.ycoms.len(), self.proofs.len())
+    }
+}
+
+/// Commits to the variables and proves all the equations. `xvals` (resp. `yvals`) contains the values
+/// of all the X (resp. Y) variables used by the equations.
+///
+/// It fails if the values do not satisfy every equation.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::{Pairing, PairingOutput};
+/// use ark_std::{test_rng, UniformRand, Zero};
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use std::ops::Mul;
+/// use transferable_ecash::proof::{shared, Equation, PairingProduct, Term::{Const, Var}};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+/// type G2 = <E as Pairing>::G2Affine;
+/// type Fr = <E as Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let crs = CRS::<E>::generate_crs(rng);
+///
+/// // e(X, b1) = e(a1, Y) and e(X, b2) = e(a2, Y), where X = a1^s = a2^t and Y = b1^s = b2^t
+/// let (a1, b1, s, t) = (G1::rand(rng), G2::rand(rng), Fr::rand(rng), Fr::rand(rng));
+/// let (a2, b2): (G1, G2) = (a1.mul(s / t).into(), b1.mul(s / t).into());
+/// let (x, y): (G1, G2) = (a1.mul(s).into(), b1.mul(s).into());
+/// let equs = [(a1, b1), (a2, b2)].map(|(a, b)| {
+///     Equation::<E, PairingProduct>::new()
+///         .pair(Var(0), Const(b))
+///         .pair(Const(-a), Var(0))
+///         .target(PairingOutput::zero())
+/// });
+/// let pf = shared::prove(rng, &crs, &equs, &[x], &[y]).unwrap();
+/// assert!(shared::verify(&crs, &equs, &pf));
+/// ```
+pub fn prove<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    equs: &[Equation<E, PairingProduct>],
+    xvals: &[E::G1Affine],
+    yvals: &[E::G2Affine],
+) -> Result<SharedProof<E>, ()> {
+    let (m, n) = dims(equs);
+    if equs.is_empty()
+        || xvals.len() != m
+        || yvals.len() != n
+        || crs.u.len() != 2
+        || crs.v.len() != 2
+    {
+        return Err(());
+    }
+    let statements = statements(equs, m, n);
+    if !statements.iter().all(|equ| is_satisfied(equ, xvals, yvals)) {
+        return Err(());
+    }
+
+    let r = rand_matrix::<E, _>(rng, m);
+    let s = rand_matrix::<E, _>(rng, n);
+    let xcoms = commit1(crs, xvals, &r);
+    let ycoms = commit2(crs, yvals, &s);
+
+    let proofs = statements
+        .iter()
+        .map(|equ| prove_equation(rng, crs, equ, xvals, &r, yvals, &s))
+        .collect();
+
+    Ok(SharedProof {
+        xcoms,
+        ycoms,
+        proofs,
+    })
+}
+
+/// Verifies the proof generated by `prove` against the same equations.
+pub fn verify<E: Pairing>(
+    crs: &CRS<E>,
+    equs: &[Equation<E, PairingProduct>],
+    proof: &SharedProof<E>,
+) -> bool {
+    let (m, n) = dims(equs);
+    if equs.is_empty()
+        || crs.u.len() != 2
+        || crs.v.len() != 2
+        || proof.xcoms.len() != m
+        || proof.ycoms.len() != n
+        || proof.proofs.len() != equs.len()
+    {
+        return false;
+    }
+
+    statements(equs, m, n)
+        .iter()
+        .zip(&proof.proofs)
+        .all(|(equ, pf)| verify_equation(crs, equ, &proof.xcoms, &proof.ycoms, pf))
+}
+
+// (m, n) = (number of X variables, number of Y variables) over all the equations.
+fn dims<E: Pairing>(equs: &[Equation<E, PairingProduct>]) -> (usize, usize) {
+    let m = equs.iter().map(|equ| equ.num_x()).max().unwrap_or(0);
+    let n = equs.iter().map(|equ| equ.num_y()).max().unwrap_or(0);
+    (m, n)
+}
+
+// The statements over X = [X_0, ..., X_m-1] and Y = [Y_0, ..., Y_n-1]. Unlike `Equation::statement`,
+// there is no padding, so the variable i of every equation is at the same index.
+fn statements<E: Pairing>(equs: &[Equation<E, PairingProduct>], m: usize, n: usize) -> Vec<PPE<E>> {
+    equs.iter()
+        .map(|equ| {
+            let mut a_consts = vec![E::G1Affine::zero(); n];
+            let mut b_consts = vec![E::G2Affine::zero(); m];
+            let mut gamma: Matrix<E::ScalarField> = vec![vec![E::ScalarField::zero(); n]; m];
+            let mut target = equ.target;
+
+            for (x, y) in equ.terms.iter() {
+                match (x, y) {
+                    (Term::Const(a), Term::Var(j)) => a_consts[*j] = (a_consts[*j] + *a).into(),
+                    (Term::Var(i), Term::Const(b)) => b_consts[*i] = (b_consts[*i] + *b).into(),
+                    (Term::Var(i), Term::Var(j)) => gamma[*i][*j] += E::ScalarField::one(),
+                    (Term::Const(a), Term::Const(b)) => target -= E::pairing(*a, *b),
+                }
+            }
+
+            PPE {
+                a_consts,
+                b_consts,
+                gamma,
+                target,
+            }
+        })
+        .collect()
+}
+
+// Π e(A_j, Y_j) Π e(X_i, B_i) ΠΠ e(X_i, Y_j)^gamma_ij == t
+fn is_satisfied<E: Pairing>(equ: &PPE<E>, x: &[E::G1Affine], y: &[E::G2Affine]) -> bool {
+    let mut lhs: PairingOutput<E> =
+        E::multi_pairing(equ.a_consts.iter().copied(), y.iter().copied())
+            + E::multi_pairing(x.iter().copied(), equ.b_consts.iter().copied());
+    for (i, row) in equ.gamma.iter().enumerate() {
+        for (j, gamma_ij) in row.iter().enumerate() {
+            if !gamma_ij.is_zero() {
+                let x_gamma: E::G1Affine = x[i].mul(*gamma_ij).into();
+                lhs += E::pairing(x_gamma, y[j]);
+            }
+        }
+    }
+    lhs == equ.target
+}
+
+impl<E: Pairing> CanonicalSerialize for SharedProof<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for SharedProof<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for SharedProof<E> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let parts = ProofParts::<E>::deserialize_with_mode(reader, compress, validate)?;
+        let (xcoms, ycoms, proofs) = from_parts(parts);
+        Ok(Self {
+            xcoms,
+            ycoms,
+            proofs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::{Pairing, PairingOutput};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::{test_rng, UniformRand, Zero};
+    use groth_sahai::{AbstractCrs, CRS};
+    use std::ops::Mul;
+
+    use super::{prove, verify, SharedProof};
+    use crate::proof::{
+        Equation, PairingProduct,
+        Term::{Const, Var},
+    };
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type G2 = <E as Pairing>::G2Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    // e(X_0, g2) = e(g1, Y_0) and e(X_0, Y_0) e(X_1, g2) = t
+    fn equations(g1: G1, g2: G2, t: PairingOutput<E>) -> [Equation<E, PairingProduct>; 2] {
+        [
+            Equation::new()
+                .pair(Var(0), Const(g2))
+                .pair(Const(-g1), Var(0))
+                .target(PairingOutput::zero()),
+            Equation::new()
+                .pair(Var(0), Var(0))
+                .pair(Var(1), Const(g2))
+                .target(t),
+        ]
+    }
+
+    #[test]
+    fn test_shared_variables() {
+        let rng = &mut test_rng();
+        let crs = CRS::<E>::generate_crs(rng);
+        let (g1, g2) = (crs.g1_gen, crs.g2_gen);
+
+        let (s, u) = (Fr::rand(rng), Fr::rand(rng));
+        let x: [G1; 2] = [g1.mul(s).into(), g1.mul(u).into()];
+        let y: [G2; 1] = [g2.mul(s).into()];
+        // t = e(g1, g2)^(s^2 + u)
+        let t = E::pairing(g1, g2).mul(s * s + u);
+        let equs = equations(g1, g2, t);
+
+        let pf = prove(rng, &crs, &equs, &x, &y).unwrap();
+        assert!(verify(&crs, &equs, &pf));
+        assert_eq!(pf.size_in_bytes(), 2 * 96 + 192 + 2 * 576);
+
+        let mut bytes = Vec::new();
+        pf.serialize_compressed(&mut bytes).unwrap();
+        let pf2 = SharedProof::<E>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(verify(&crs, &equs, &pf2));
+
+        // another target, or a part of the equations
+        let equs_x = equations(g1, g2, E::pairing(g1, g2));
+        assert!(!verify(&crs, &equs_x, &pf));
+        assert!(!verify(&crs, &equs[..1], &pf));
+
+        // Y_0 of the first equation is not the Y_0 of the second one
+        let y2: [G2; 1] = [g2.mul(s + Fr::from(1u64)).into()];
+        assert!(prove(rng, &crs, &equs, &x, &y2).is_err());
+        // missing variables
+        assert!(prove(rng, &crs, &equs, &x[..1], &y).is_err());
+        assert!(prove(rng, &crs, &[], &x, &y).is_err());
+    }
+}
@@ -0,0 +1,565 @@
+//! This module provides zero-knowledge GS proofs for pairing product equations of the form
+//! Π e(X_i, B_i) = Π e(P_j, Q_j), where P_j and Q_j are public.
+//!
+//! A GS proof of an equation with a non-trivial target is only witness-indistinguishable, because
+//! a simulator cannot satisfy the target without a witness. The equation is therefore turned into
+//! homogeneous equations over the auxiliary variables W_j = P_j and D = g2:
+//! 1. Π e(X_i, B_i) Π e(W_j, Q_j)^-1 = 1,
+//! 2. e(W_j, g2) e(P_j, D)^-1 = 1 for each j,
+//! 3. e(g1, D) = e(g1, g2),
+//!
+//! which share the commitments to X_i, W_j and D. Under a hiding CRS, the trapdoor opens the commitment
+//! to D as both 1 and g2, so the simulator proves 1. and 2. with zero witnesses and 3. with D = g2.
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::{rand::RngCore, One, UniformRand, Zero};
+use groth_sahai::data_structures::{Com1, Com2};
+use groth_sahai::statement::PPE;
+use groth_sahai::{Matrix, CRS};
+
+use crate::crs::CrsTrapdoor;
+
+use super::{linear_combination1, linear_combination2};
+
+/// A zero-knowledge proof of Π e(X_i, B_i) = Π e(P_j, Q_j).
+#[derive(Clone)]
+pub struct ZkProof<E: Pairing> {
+    // commitments to [X_1, ..., X_k, W_1, ..., W_l]
+    pub(crate) xcoms: Vec<Com1<E>>,
+    // commitment to [D]
+    pub(crate) ycoms: Vec<Com2<E>>,
+    // proofs of equations 1., 2. (for each j) and 3.
+    pub(crate) proofs: Vec<EquationProof<E>>,
+}
+
+/// The proof (pi, theta) of one equation over the committed variables.
+#[derive(Clone)]
+pub(crate) struct EquationProof<E: Pairing> {
+    pub(crate) pi: [Com2<E>; 2],
+    pub(crate) theta: [Com1<E>; 2],
+}
+
+// An element of B_T = GT^(2x2).
+type ComT<E> = [[PairingOutput<E>; 2]; 2];
+
+impl<E: Pairing> ZkProof<E> {
+    /// The size in bytes of the group elements of the proof (commitments, pi and theta) in compressed form.
+    pub fn size_in_bytes(&self) -> usize {
+        elements_size::<E>(self.xcoms.len(), self.ycoms.len(), self.proofs.len())
+    }
+}
+
+// The size in bytes of the given numbers of commitments and equation proofs in compressed form.
+pub(super) fn elements_size<E: Pairing>(
+    num_xcoms: usize,
+    num_ycoms: usize,
+    num_proofs: usize,
+) -> usize {
+    let g1_size = E::G1Affine::zero().compressed_size();
+    let g2_size = E::G2Affine::zero().compressed_size();
+
+    // each element of B1 (resp. B2) consists of two elements of G1 (resp. G2),
+    // and each equation proof has two of each.
+    let b1_len = num_xcoms + 2 * num_proofs;
+    let b2_len = num_ycoms + 2 * num_proofs;
+    2 * (b1_len * g1_size + b2_len * g2_size)
+}
+
+// Com1 (resp. Com2) is serialized as a pair of elements of G1 (resp. G2),
+// and an equation proof as (pi, theta).
+type Com1Parts<E> = (<E as Pairing>::G1Affine, <E as Pairing>::G1Affine);
+type Com2Parts<E> = (<E as Pairing>::G2Affine, <E as Pairing>::G2Affine);
+type EquationProofParts<E> = (Com2Parts<E>, Com2Parts<E>, Com1Parts<E>, Com1Parts<E>);
+
+/// The commitments and equation proofs of a proof, as tuples of group elements.
+pub(super) type ProofParts<E> = (
+    Vec<Com1Parts<E>>,
+    Vec<Com2Parts<E>>,
+    Vec<EquationProofParts<E>>,
+);
+
+pub(super) fn to_parts<E: Pairing>(
+    xcoms: &[Com1<E>],
+    ycoms: &[Com2<E>],
+    proofs: &[EquationProof<E>],
+) -> ProofParts<E> {
+    (
+        xcoms.iter().map(|c| (c.0, c.1)).collect(),
+        ycoms.iter().map(|d| (d.0, d.1)).collect(),
+        proofs
+            .iter()
+            .map(|pf| {
+                let [pi0, pi1] = &pf.pi;
+                let [theta0, theta1] = &pf.theta;
+                (
+                    (pi0.0, pi0.1),
+                    (pi1.0, pi1.1),
+                    (theta0.0, theta0.1),
+                    (theta1.0, theta1.1),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn from_parts<E: Pairing>(
+    (xcoms, ycoms, proofs): ProofParts<E>,
+) -> (Vec<Com1<E>>, Vec<Com2<E>>, Vec<EquationProof<E>>) {
+    (
+        xcoms.into_iter().map(|(c0, c1)| Com1(c0, c1)).collect(),
+        ycoms.into_iter().map(|(d0, d1)| Com2(d0, d1)).collect(),
+        proofs
+            .into_iter()
+            .map(|(pi0, pi1, theta0, theta1)| EquationProof {
+                pi: [Com2(pi0.0, pi0.1), Com2(pi1.0, pi1.1)],
+                theta: [Com1(theta0.0, theta0.1), Com1(theta1.0, theta1.1)],
+            })
+            .collect(),
+    )
+}
+
+impl<E: Pairing> CanonicalSerialize for ZkProof<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for ZkProof<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        to_parts(&self.xcoms, &self.ycoms, &self.proofs).check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for ZkProof<E> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let parts = ProofParts::<E>::deserialize_with_mode(reader, compress, validate)?;
+        let (xcoms, ycoms, proofs) = from_parts(parts);
+        Ok(Self {
+            xcoms,
+            ycoms,
+            proofs,
+        })
+    }
+}
+
+/// Proves Π e(X_i, B_i) = Π e(P_j, Q_j) in zero-knowledge, where `target` contains the pairs (P_j, Q_j).
+///
+/// It fails if the values do not satisfy the equation.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_ff::Field;
+/// use ark_std::{test_rng, UniformRand};
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use std::ops::Mul;
+/// use transferable_ecash::proof::zk;
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+/// type G2 = <E as Pairing>::G2Affine;
+/// type Fr = <E as Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let crs = CRS::<E>::generate_crs(rng);
+///
+/// // e(X, B) = e(P, Q), where X = P^s and B = Q^(1/s)
+/// let (p, q, s) = (G1::rand(rng), G2::rand(rng), Fr::rand(rng));
+/// let (x, b): (G1, G2) = (p.mul(s).into(), q.mul(s.inverse().unwrap()).into());
+/// let pf = zk::prove(rng, &crs, &[x], &[b], &[(p, q)]).unwrap();
+/// assert!(zk::verify(&crs, &pf, &[b], &[(p, q)]));
+/// ```
+pub fn prove<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    x: &[E::G1Affine],
+    b: &[E::G2Affine],
+    target: &[(E::G1Affine, E::G2Affine)],
+) -> Result<ZkProof<E>, ()> {
+    if x.is_empty() || x.len() != b.len() || crs.u.len() != 2 || crs.v.len() != 2 {
+        return Err(());
+    }
+    let lhs = E::multi_pairing(x.iter().copied(), b.iter().copied());
+    let rhs = E::multi_pairing(
+        target.iter().map(|(p, _)| *p),
+        target.iter().map(|(_, q)| *q),
+    );
+    if lhs != rhs {
+        return Err(());
+    }
+
+    // X = [X_1, ..., X_k, W_1, ..., W_l], Y = [D]
+    let mut xvars = x.to_vec();
+    xvars.extend(target.iter().map(|(p, _)| *p));
+    let yvars = vec![crs.g2_gen];
+
+    let r = rand_matrix::<E, _>(rng, xvars.len());
+    let s = rand_matrix::<E, _>(rng, 1);
+    let xcoms = commit1(crs, &xvars, &r);
+    let ycoms = commit2(crs, &yvars, &s);
+
+    let proofs = equations(crs, b, target)
+        .iter()
+        .map(|equ| prove_equation(rng, crs, equ, &xvars, &r, &yvars, &s))
+        .collect();
+
+    Ok(ZkProof {
+        xcoms,
+        ycoms,
+        proofs,
+    })
+}
+
+/// Verifies the proof generated by `prove` (or `simulate`).
+pub fn verify<E: Pairing>(
+    crs: &CRS<E>,
+    proof: &ZkProof<E>,
+    b: &[E::G2Affine],
+    target: &[(E::G1Affine, E::G2Affine)],
+) -> bool {
+    if b.is_empty()
+        || crs.u.len() != 2
+        || crs.v.len() != 2
+        || proof.xcoms.len() != b.len() + target.len()
+        || proof.ycoms.len() != 1
+        || proof.proofs.len() != target.len() + 2
+    {
+        return false;
+    }
+
+    equations(crs, b, target)
+        .iter()
+        .zip(&proof.proofs)
+        .all(|(equ, pf)| verify_equation(crs, equ, &proof.xcoms, &proof.ycoms, pf))
+}
+
+/// Simulates a proof of Π e(X_i, B_i) = Π e(P_j, Q_j) without the witness X_i, using the trapdoor
+/// of a hiding CRS. The output is distributed like a proof generated by `prove` under the same CRS.
+///
+/// It fails if `td` is not the trapdoor of the hiding CRS `crs`.
+pub fn simulate<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    td: &CrsTrapdoor<E>,
+    b: &[E::G2Affine],
+    target: &[(E::G1Affine, E::G2Affine)],
+) -> Result<ZkProof<E>, ()> {
+    if b.is_empty() || !td.matches_hiding(crs) {
+        return Err(());
+    }
+
+    // commit to zeros
+    let xvars = vec![E::G1Affine::zero(); b.len() + target.len()];
+    let yvars = vec![E::G2Affine::zero()];
+    let r = rand_matrix::<E, _>(rng, xvars.len());
+    let s = rand_matrix::<E, _>(rng, 1);
+    let xcoms = commit1(crs, &xvars, &r);
+    let ycoms = commit2(crs, &yvars, &s);
+
+    // v2 = v1^t2 - (0, g2), hence the commitment to D = 0 with randomness (s1, s2)
+    // is also the commitment to D = g2 with randomness (s1 - t2, s2 + 1).
+    let yvars_g2 = vec![crs.g2_gen];
+    let s_g2 = vec![vec![s[0][0] - td.t2, s[0][1] + E::ScalarField::one()]];
+
+    let equs = equations(crs, b, target);
+    let last = equs.len() - 1;
+    let proofs = equs
+        .iter()
+        .enumerate()
+        .map(|(i, equ)| {
+            if i == last {
+                prove_equation(rng, crs, equ, &xvars, &r, &yvars_g2, &s_g2)
+            } else {
+                prove_equation(rng, crs, equ, &xvars, &r, &yvars, &s)
+            }
+        })
+        .collect();
+
+    Ok(ZkProof {
+        xcoms,
+        ycoms,
+        proofs,
+    })
+}
+
+// The equations 1., 2. and 3. over X = [X_1, ..., X_k, W_1, ..., W_l] and Y = [D].
+fn equations<E: Pairing>(
+    crs: &CRS<E>,
+    b: &[E::G2Affine],
+    target: &[(E::G1Affine, E::G2Affine)],
+) -> Vec<PPE<E>> {
+    let (k, l) = (b.len(), target.len());
+    let gamma: Matrix<E::ScalarField> = vec![vec![E::ScalarField::zero()]; k + l];
+    let mut equs = Vec::with_capacity(l + 2);
+
+    // 1. Π e(X_i, B_i) Π e(W_j, -Q_j) = 1
+    let mut b_consts = b.to_vec();
+    b_consts.extend(target.iter().map(|(_, q)| -*q));
+    equs.push(PPE {
+        a_consts: vec![E::G1Affine::zero()],
+        b_consts,
+        gamma: gamma.clone(),
+        target: PairingOutput::zero(),
+    });
+
+    // 2. e(W_j, g2) e(-P_j, D) = 1
+    for (j, (p, _)) in target.iter().enumerate() {
+        let mut b_consts = vec![E::G2Affine::zero(); k + l];
+        b_consts[k + j] = crs.g2_gen;
+        equs.push(PPE {
+            a_consts: vec![-*p],
+            b_consts,
+            gamma: gamma.clone(),
+            target: PairingOutput::zero(),
+        });
+    }
+
+    // 3. e(g1, D) = e(g1, g2)
+    equs.push(PPE {
+        a_consts: vec![crs.g1_gen],
+        b_consts: vec![E::G2Affine::zero(); k + l],
+        gamma,
+        target: E::pairing(crs.g1_gen, crs.g2_gen),
+    });
+
+    equs
+}
+
+// pi = R^T ι2(B) + R^T Γ ι2(Y) + (R^T Γ S + T) v
+// theta = S^T ι1(A) + S^T Γ^T ι1(X) - T^T u
+pub(super) fn prove_equation<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    crs: &CRS<E>,
+    equ: &PPE<E>,
+    x: &[E::G1Affine],
+    r: &Matrix<E::ScalarField>,
+    y: &[E::G2Affine],
+    s: &Matrix<E::ScalarField>,
+) -> EquationProof<E> {
+    let t = rand_matrix::<E, _>(rng, 2);
+    let (m, n) = (x.len(), y.len());
+    let gamma = &equ.gamma;
+
+    let pi = [0, 1].map(|k| {
+        let mut terms = Vec::new();
+        for i in 0..m {
+            terms.push((r[i][k], iota2(equ.b_consts[i])));
+            for j in 0..n {
+                terms.push((r[i][k] * gamma[i][j], iota2(y[j])));
+            }
+        }
+        for l in 0..2 {
+            let rgs = (0..m)
+                .flat_map(|i| (0..n).map(move |j| (i, j)))
+                .map(|(i, j)| r[i][k] * gamma[i][j] * s[j][l])
+                .sum::<E::ScalarField>();
+            terms.push((rgs + t[k][l], crs.v[l]));
+        }
+        linear_combination2(&terms)
+    });
+
+    let theta = [0, 1].map(|l| {
+        let mut terms = Vec::new();
+        for j in 0..n {
+            terms.push((s[j][l], iota1(equ.a_consts[j])));
+            for i in 0..m {
+                terms.push((s[j][l] * gamma[i][j], iota1(x[i])));
+            }
+        }
+        for k in 0..2 {
+            terms.push((-t[k][l], crs.u[k]));
+        }
+        linear_combination1(&terms)
+    });
+
+    EquationProof { pi, theta }
+}
+
+// ι1(A) d + c ι2(B) + c Γ d = ι_T(t) + u pi + theta v
+pub(super) fn verify_equation<E: Pairing>(
+    crs: &CRS<E>,
+    equ: &PPE<E>,
+    c: &[Com1<E>],
+    d: &[Com2<E>],
+    pf: &EquationProof<E>,
+) -> bool {
+    let (m, n) = (c.len(), d.len());
+    if equ.a_consts.len() != n || equ.b_consts.len() != m || equ.gamma.len() != m {
+        return false;
+    }
+
+    let mut lhs_pairs: Vec<(Com1<E>, Com2<E>)> = Vec::new();
+    for j in 0..n {
+        lhs_pairs.push((iota1(equ.a_consts[j]), d[j]));
+    }
+    for i in 0..m {
+        lhs_pairs.push((c[i], iota2(equ.b_consts[i])));
+        let gamma_d: Vec<_> = (0..n).map(|j| (equ.gamma[i][j], d[j])).collect();
+        lhs_pairs.push((c[i], linear_combination2(&gamma_d)));
+    }
+
+    let rhs_pairs = [
+        (crs.u[0], pf.pi[0]),
+        (crs.u[1], pf.pi[1]),
+        (pf.theta[0], crs.v[0]),
+        (pf.theta[1], crs.v[1]),
+    ];
+    let mut rhs = pairing_sum(&rhs_pairs);
+    rhs[1][1] += equ.target;
+
+    pairing_sum(&lhs_pairs) == rhs
+}
+
+// Σ F(c_i, d_i), where F((a0, a1), (b0, b1)) = [[e(a0, b0), e(a0, b1)], [e(a1, b0), e(a1, b1)]]
+fn pairing_sum<E: Pairing>(pairs: &[(Com1<E>, Com2<E>)]) -> ComT<E> {
+    let entry = |f: fn(&Com1<E>) -> E::G1Affine, g: fn(&Com2<E>) -> E::G2Affine| {
+        E::multi_pairing(
+            pairs.iter().map(|(c, _)| f(c)),
+            pairs.iter().map(|(_, d)| g(d)),
+        )
+    };
+    [
+        [entry(|c| c.0, |d| d.0), entry(|c| c.0, |d| d.1)],
+        [entry(|c| c.1, |d| d.0), entry(|c| c.1, |d| d.1)],
+    ]
+}
+
+// c_i = ι1(X_i) + r_i1 u1 + r_i2 u2
+pub(super) fn commit1<E: Pairing>(
+    crs: &CRS<E>,
+    x: &[E::G1Affine],
+    r: &Matrix<E::ScalarField>,
+) -> Vec<Com1<E>> {
+    x.iter()
+        .zip(r)
+        .map(|(x_i, r_i)| {
+            linear_combination1(&[
+                (E::ScalarField::one(), iota1(*x_i)),
+                (r_i[0], crs.u[0]),
+                (r_i[1], crs.u[1]),
+            ])
+        })
+        .collect()
+}
+
+// d_j = ι2(Y_j) + s_j1 v1 + s_j2 v2
+pub(super) fn commit2<E: Pairing>(
+    crs: &CRS<E>,
+    y: &[E::G2Affine],
+    s: &Matrix<E::ScalarField>,
+) -> Vec<Com2<E>> {
+    y.iter()
+        .zip(s)
+        .map(|(y_j, s_j)| {
+            linear_combination2(&[
+                (E::ScalarField::one(), iota2(*y_j)),
+                (s_j[0], crs.v[0]),
+                (s_j[1], crs.v[1]),
+            ])
+        })
+        .collect()
+}
+
+fn iota1<E: Pairing>(x: E::G1Affine) -> Com1<E> {
+    Com1(E::G1Affine::zero(), x)
+}
+
+fn iota2<E: Pairing>(y: E::G2Affine) -> Com2<E> {
+    Com2(E::G2Affine::zero(), y)
+}
+
+pub(super) fn rand_matrix<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    rows: usize,
+) -> Matrix<E::ScalarField> {
+    (0..rows)
+        .map(|_| vec![E::ScalarField::rand(rng), E::ScalarField::rand(rng)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_ff::Field;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::{test_rng, UniformRand};
+    use std::ops::Mul;
+
+    use crate::crs::{generate_crs_with_trapdoor, generate_hiding_crs_with_trapdoor};
+
+    use super::{prove, simulate, verify, ZkProof};
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+    type G2 = <E as Pairing>::G2Affine;
+    type Fr = <E as Pairing>::ScalarField;
+
+    #[test]
+    fn test_prove_and_simulate() {
+        let rng = &mut test_rng();
+        let (binding_crs, binding_td) = generate_crs_with_trapdoor::<E, _>(rng);
+        let (hiding_crs, hiding_td) = generate_hiding_crs_with_trapdoor::<E, _>(rng);
+
+        // e(X1, B1) e(X2, B2) = e(P1, Q1) e(P2, Q2)
+        // where X1 = P1^s, B1 = Q1^(1/s), X2 = P2, B2 = Q2
+        let (p1, q1, p2, q2) = (G1::rand(rng), G2::rand(rng), G1::rand(rng), G2::rand(rng));
+        let s = Fr::rand(rng);
+        let x = [p1.mul(s).into(), p2];
+        let b = [q1.mul(s.inverse().unwrap()).into(), q2];
+        let target = [(p1, q1), (p2, q2)];
+
+        for crs in [&binding_crs, &hiding_crs] {
+            let pf = prove(rng, crs, &x, &b, &target).unwrap();
+            assert!(verify(crs, &pf, &b, &target));
+            // a different statement
+            assert!(!verify(crs, &pf, &b, &[(p1, q1), (p1, q2)]));
+            assert!(!verify(crs, &pf, &[b[0], q1], &target));
+            assert!(!verify(crs, &pf, &b, &target[..1]));
+        }
+
+        // invalid witness
+        assert!(prove(rng, &binding_crs, &[p1, p2], &b, &target).is_err());
+
+        // the simulator does not know X, and is only available under the hiding CRS.
+        let pf = simulate(rng, &hiding_crs, &hiding_td, &b, &target).unwrap();
+        assert!(verify(&hiding_crs, &pf, &b, &target));
+        assert!(simulate(rng, &binding_crs, &binding_td, &b, &target).is_err());
+        assert!(simulate(rng, &hiding_crs, &binding_td, &b, &target).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let rng = &mut test_rng();
+        let (crs, _) = generate_crs_with_trapdoor::<E, _>(rng);
+        let (p, q) = (G1::rand(rng), G2::rand(rng));
+        let pf = prove(rng, &crs, &[p], &[q], &[(p, q)]).unwrap();
+
+        let mut bytes = Vec::new();
+        pf.serialize_compressed(&mut bytes).unwrap();
+        // 3 length prefixes and the group elements
+        assert_eq!(bytes.len(), 24 + pf.size_in_bytes());
+
+        let pf2 = ZkProof::<E>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(verify(&crs, &pf2, &[q], &[(p, q)]));
+        assert!(ZkProof::<E>::deserialize_compressed(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use super::compact_ciphertext::CompactCiphertext;
+
+/// The maximum length of a byte string accepted by `EncryptKey::encrypt_bytes`.
+pub const MAX_MESSAGE_BYTES: usize = 1 << 16;
+
+/// The errors of encrypting and decrypting byte strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BytesError {
+    /// The byte string is longer than `max` bytes.
+    TooLong { len: usize, max: usize },
+    /// A ciphertext of the byte string fails to decrypt.
+    Decryption,
+}
+
+const DOMAIN: &[u8] = b"transferable-ecash/rcca/bytes";
+
+// Every key encrypts a single byte string, so the nonce is fixed.
+const NONCE: [u8; 24] = [0; 24];
+
+/// A byte string encrypted by `EncryptKey::encrypt_bytes`.
+///
+/// `kem` is an RCCA encryption of n random points of G1. The hash of the points is the key of
+/// the XChaCha20-Poly1305 encryption `dem` of the byte string, whose associated data is the
+/// serialized `kem`, so `dem` cannot be moved under another `kem`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BytesCiphertext<E: Pairing> {
+    pub(crate) kem: CompactCiphertext<E>,
+    pub(crate) dem: Vec<u8>,
+}
+
+impl<E: Pairing> BytesCiphertext<E> {
+    // Encrypts the data under the key derived from the points encrypted by `kem`.
+    pub(crate) fn seal(kem: CompactCiphertext<E>, points: &[E::G1Affine], data: &[u8]) -> Self {
+        let key = data_key::<E>(points);
+        let dem = XChaCha20Poly1305::new(key.as_slice().into())
+            .encrypt(
+                XNonce::from_slice(&NONCE),
+                Payload {
+                    msg: data,
+                    aad: &associated_data(&kem),
+                },
+            )
+            .expect("data shorter than MAX_MESSAGE_BYTES should be encrypted");
+        Self { kem, dem }
+    }
+
+    // Decrypts `dem` with the points decrypted from `kem`.
+    pub(crate) fn open(&self, points: &[E::G1Affine]) -> Result<Vec<u8>, BytesError> {
+        let key = data_key::<E>(points);
+        XChaCha20Poly1305::new(key.as_slice().into())
+            .decrypt(
+                XNonce::from_slice(&NONCE),
+                Payload {
+                    msg: &self.dem,
+                    aad: &associated_data(&self.kem),
+                },
+            )
+            .map_err(|_| BytesError::Decryption)
+    }
+}
+
+// H(DOMAIN || points)
+fn data_key<E: Pairing>(points: &[E::G1Affine]) -> Zeroizing<[u8; 32]> {
+    let mut bytes = Zeroizing::new(Vec::new());
+    points
+        .serialize_compressed(&mut *bytes)
+        .expect("serialization to vec should not fail");
+    let digest = Sha256::new()
+        .chain_update(DOMAIN)
+        .chain_update(&*bytes)
+        .finalize();
+    Zeroizing::new(digest.into())
+}
+
+fn associated_data<E: Pairing>(kem: &CompactCiphertext<E>) -> Vec<u8> {
+    let mut aad = Vec::new();
+    kem.serialize_compressed(&mut aad)
+        .expect("serialization to vec should not fail");
+    aad
+}
@@ -1,32 +1,52 @@
 use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_serialize::CanonicalSerialize;
+use ark_std::Zero;
 
 use groth_sahai::prover::CProof;
 
-use crate::proof::check_proof_ayxb;
+use crate::lhsps::LinearlyHomomorphicSignature;
+use crate::proof::{check_proof_ayxb, proof_size, zk::ZkProof};
 
-use super::encrypt_key::EncryptKey;
+use super::{encrypt_key::EncryptKey, RccaError};
 
+#[derive(Clone)]
 pub struct Ciphertext<E: Pairing> {
     pub(crate) c: Vec<E::G1Affine>,
     pub(crate) cpf_b: CProof<E>,
     pub(crate) cpf_ps: Vec<CProof<E>>,
-    pub(crate) cpf_v: CProof<E>,
+    pub(crate) cpf_v: ZkProof<E>,
     pub(crate) cpf_fgh: Vec<CProof<E>>,
-    pub(crate) cpf_w: CProof<E>,
+    pub(crate) cpf_w: ZkProof<E>,
 }
 
 impl<E: Pairing> Ciphertext<E> {
     /// Check all proofs of the ciphertext.
-    pub fn check_proofs(&self, enc_key: &EncryptKey<E>) -> Result<(), ()> {
-        if self.cpf_ps.len() != self.c.len() - 1 {
-            return Err(());
+    pub fn check_proofs<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+    ) -> Result<(), RccaError> {
+        self.check_proofs_with(enc_key, E::ScalarField::zero())
+    }
+
+    // Checks all proofs of the ciphertext under the label hashed to t (t = 0 without a label).
+    pub(crate) fn check_proofs_with<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+        t: E::ScalarField,
+    ) -> Result<(), RccaError> {
+        let layout = enc_key.layout();
+        layout.check_ciphertext(&self.c)?;
+        // one proof for each of c_1, ..., c_n+1 and each of f, g, h_1, ..., h_n
+        let n = layout.n();
+        if self.cpf_ps.len() != n + 1 || self.cpf_fgh.len() != n + 2 {
+            return Err(RccaError::MalformedCiphertext);
         }
 
         // check all proofs
         let crs = &enc_key.crs;
         // cfp_b is proof of e(A, Y) + e(X, B) = e(g, g~^-b) + e(g^b, g~) = 0
         if !check_proof_ayxb(crs, &self.cpf_b, enc_key.g, crs.g2_gen) {
-            return Err(());
+            return Err(RccaError::InvalidProof);
         }
         // cfp_ps is proof of e(A, Y) + e(X, B) = e(c_i, g~^-b) + e(g^b, g~) = 0
         if self
@@ -36,13 +56,7 @@ impl<E: Pairing> Ciphertext<E> {
             .zip(self.cpf_ps.iter())
             .any(|(ci, cpf)| !check_proof_ayxb(crs, cpf, *ci, crs.g2_gen))
         {
-            return Err(());
-        }
-        // cpf_v is proof for message v = [c_0, c_1, 1, ..., 1]
-        let mut v = vec![self.c[0], self.c[1]];
-        v.extend(vec![E::G1Affine::zero(); self.c.len() - 2]);
-        if !enc_key.lhsps_vk.check_proof(crs, &self.cpf_v, &v) {
-            return Err(());
+            return Err(RccaError::InvalidProof);
         }
         // cpf_fgh is proof for message fgh = (f, g, h_1, ..., h_n)
         let mut fgh = vec![enc_key.f, enc_key.g];
@@ -52,15 +66,47 @@ impl<E: Pairing> Ciphertext<E> {
             .zip(self.cpf_fgh.iter())
             .any(|(fgh_i, cpf)| !check_proof_ayxb(crs, cpf, *fgh_i, crs.g2_gen))
         {
-            return Err(());
-        }
-        // cpf_w is proof for message w = [f, g, 1, 1, ..., 1]
-        let mut w = vec![enc_key.f, enc_key.g];
-        w.extend(vec![E::G1Affine::zero(); self.c.len() - 2]);
-        if !enc_key.lhsps_vk.check_proof(crs, &self.cpf_w, &w) {
-            return Err(());
+            return Err(RccaError::InvalidProof);
         }
 
-        Ok(())
+        check_lhsps_proofs(enc_key, &self.c, t, &self.cpf_v, &self.cpf_w)
+    }
+
+    /// The size in bytes of the ciphertext in compressed form. See `CompactCiphertext` for the sizes
+    /// compared with the compact ciphertext.
+    pub fn size_in_bytes(&self) -> usize {
+        let c_size = self.c.len() * E::G1Affine::zero().compressed_size();
+        let proofs = [&self.cpf_b]
+            .into_iter()
+            .chain(self.cpf_ps.iter())
+            .chain(self.cpf_fgh.iter());
+        c_size
+            + proofs.map(proof_size).sum::<usize>()
+            + self.cpf_v.size_in_bytes()
+            + self.cpf_w.size_in_bytes()
     }
 }
+
+// Checks the zero-knowledge proofs of validity of the lhsps signatures on v and w.
+fn check_lhsps_proofs<E: Pairing, S: LinearlyHomomorphicSignature<E>>(
+    enc_key: &EncryptKey<E, S>,
+    c: &[E::G1Affine],
+    t: E::ScalarField,
+    cpf_v: &ZkProof<E>,
+    cpf_w: &ZkProof<E>,
+) -> Result<(), RccaError> {
+    let crs = &enc_key.crs;
+    let layout = enc_key.layout();
+    // cpf_v is proof for message v = [c_0, c_1, 1, ..., 1, c_0^t]
+    let v = layout.v_labelled::<E>(c, t)?;
+    if !S::check_proof_zk(&enc_key.lhsps_vk, crs, cpf_v, S::DEFAULT_TAG, &v) {
+        return Err(RccaError::InvalidProof);
+    }
+    // cpf_w is proof for message w = [f, g, 1, 1, ..., 1, 1]
+    let w = layout.v1::<E>(enc_key.f, enc_key.g);
+    if !S::check_proof_zk(&enc_key.lhsps_vk, crs, cpf_w, S::DEFAULT_TAG, &w) {
+        return Err(RccaError::InvalidProof);
+    }
+
+    Ok(())
+}
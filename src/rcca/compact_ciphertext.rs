@@ -0,0 +1,252 @@
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use ark_std::Zero;
+use std::ops::Mul;
+
+use crate::lhsps::LinearlyHomomorphicSignature;
+use crate::proof::{
+    shared::{self, SharedProof},
+    Equation, PairingProduct,
+    Term::{Const, Var},
+};
+
+use super::{encrypt_key::EncryptKey, RccaError};
+
+/// A ciphertext whose validity is given by a single proof over shared commitments.
+///
+/// The proofs `cpf_b`, `cpf_ps` and `cpf_fgh` of `Ciphertext` commit to b and to the elements of v
+/// and w separately from `cpf_v` and `cpf_w`. Here, b, v, w and the lhsps signatures on v and w
+/// are committed once (see `ProofVariables`), and the proof shows that
+/// 1. X_b = g^b, Y_b = g~^b and X_1-b = g^(1-b), by e(X_b, g~) = e(g, Y_b) and e(X_b, g~) e(X_1-b, g~) = e(g, g~),
+/// 2. b is 0 or 1, by e(X_1-b, Y_b) = 1,
+/// 3. v = [c_0^b, c_1^b, g^(1-b), c_2^(1-b), ..., c_n+1^(1-b), c_0^(t b)] and w = [f^b, g^b, 1, h_1^(1-b), ..., h_n^(1-b), 1],
+///    by e(x^b, g~) = e(x, Y_b) and e(x^(1-b), g~) e(x, Y_b) = e(x, g~), where t is the hash of the label (t = 0 without a label),
+/// 4. the committed signatures are valid lhsps signatures on v and w.
+///
+/// The honest encryption uses b = 1 (see `RccaVectorLayout`).
+///
+/// `size_in_bytes` of the ciphertexts of dimension n over BLS12-381 with the one-time LHSPS:
+///
+/// | n  | `Ciphertext` | `CompactCiphertext` |
+/// |----|--------------|---------------------|
+/// | 5  | 28944        | 13392               |
+/// | 16 | 65376        | 28704               |
+/// | 64 | 224352       | 95520               |
+///
+/// i.e. 3312n + 12384 bytes versus 1392n + 6432 bytes.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CompactCiphertext<E: Pairing> {
+    pub(crate) c: Vec<E::G1Affine>,
+    pub(crate) proof: SharedProof<E>,
+}
+
+impl<E: Pairing> CompactCiphertext<E> {
+    // Proves the validity of the ciphertext vector c with b = 1 under the label hashed to t, where
+    // `sig_v` and `sig_w` are the lhsps signatures on v = [c_0, c_1, 1, ..., 1, c_0^t] and w = [f, g, 1, ..., 1].
+    pub(crate) fn prove<R: RngCore, S: LinearlyHomomorphicSignature<E>>(
+        rng: &mut R,
+        enc_key: &EncryptKey<E, S>,
+        c: Vec<E::G1Affine>,
+        t: E::ScalarField,
+        sig_v: &S::Signature,
+        sig_w: &S::Signature,
+    ) -> Self {
+        let vars = ProofVariables::new(enc_key);
+
+        // g^b = g, g^(1-b) = 1, c_i^(1-b) = 1 and h_i^(1-b) = 1
+        let mut x = vec![E::G1Affine::zero(); vars.num_x()];
+        x[ProofVariables::XB] = enc_key.g;
+        x[vars.ps(0)] = c[0];
+        x[vars.ps(1)] = c[1];
+        x[vars.fb()] = enc_key.f;
+        x[vars.label()] = c[0].mul(t).into();
+        for (j, s) in S::signature_elements(sig_v).into_iter().enumerate() {
+            x[vars.sig_v(j)] = s;
+        }
+        for (j, s) in S::signature_elements(sig_w).into_iter().enumerate() {
+            x[vars.sig_w(j)] = s;
+        }
+        // g~^b = g~
+        let y = [enc_key.crs.g2_gen];
+
+        let proof =
+            shared::prove(rng, &enc_key.crs, &equations(enc_key, &c, t, &vars), &x, &y).unwrap();
+        Self { c, proof }
+    }
+
+    /// Check all proofs of the ciphertext.
+    pub fn check_proofs<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+    ) -> Result<(), RccaError> {
+        self.check_proofs_with(enc_key, E::ScalarField::zero())
+    }
+
+    // Checks all proofs of the ciphertext under the label hashed to t (t = 0 without a label).
+    pub(crate) fn check_proofs_with<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+        t: E::ScalarField,
+    ) -> Result<(), RccaError> {
+        enc_key.layout().check_ciphertext(&self.c)?;
+
+        let equs = equations(enc_key, &self.c, t, &ProofVariables::new(enc_key));
+        if !shared::verify(&enc_key.crs, &equs, &self.proof) {
+            return Err(RccaError::InvalidProof);
+        }
+        Ok(())
+    }
+
+    /// The size in bytes of the ciphertext in compressed form.
+    pub fn size_in_bytes(&self) -> usize {
+        self.c.len() * E::G1Affine::zero().compressed_size() + self.proof.size_in_bytes()
+    }
+}
+
+// The indices of the committed variables for a scheme of dimension n whose lhsps signatures have
+// k elements:
+// X = [g^b, g^(1-b), ps_0, ..., ps_n+1, f^b, h_1^(1-b), ..., h_n^(1-b), sig_v, sig_w, c_0^(t b)] and Y = [g~^b],
+// where ps_i = c_i^b for i < 2 and c_i^(1-b) otherwise.
+struct ProofVariables {
+    n: usize,
+    k: usize,
+}
+
+impl ProofVariables {
+    const XB: usize = 0;
+    const X1B: usize = 1;
+    const YB: usize = 0;
+
+    fn new<E: Pairing, S: LinearlyHomomorphicSignature<E>>(enc_key: &EncryptKey<E, S>) -> Self {
+        Self {
+            n: enc_key.layout().n(),
+            k: S::equation_bases(&enc_key.lhsps_vk, S::DEFAULT_TAG).len(),
+        }
+    }
+
+    fn ps(&self, i: usize) -> usize {
+        2 + i
+    }
+
+    fn fb(&self) -> usize {
+        self.n + 4
+    }
+
+    fn hb(&self, i: usize) -> usize {
+        self.n + 5 + i
+    }
+
+    fn sig_v(&self, j: usize) -> usize {
+        2 * self.n + 5 + j
+    }
+
+    fn sig_w(&self, j: usize) -> usize {
+        2 * self.n + 5 + self.k + j
+    }
+
+    fn label(&self) -> usize {
+        2 * self.n + 5 + 2 * self.k
+    }
+
+    fn num_x(&self) -> usize {
+        2 * self.n + 6 + 2 * self.k
+    }
+
+    // the variable at slot s of v (see `RccaVectorLayout`)
+    fn v(&self, s: usize) -> usize {
+        match s {
+            0 | 1 => self.ps(s),
+            2 => Self::X1B,
+            _ if s == self.n + 3 => self.label(),
+            _ => self.ps(s - 1),
+        }
+    }
+
+    // the variable at slot s of w, which is None for the constant slots 2 and n+3
+    fn w(&self, s: usize) -> Option<usize> {
+        match s {
+            0 => Some(self.fb()),
+            1 => Some(Self::XB),
+            2 => None,
+            _ if s == self.n + 3 => None,
+            _ => Some(self.hb(s - 3)),
+        }
+    }
+}
+
+// The equations 1. to 4. of `CompactCiphertext` on the ciphertext vector c of dimension n + 2,
+// under the label hashed to t.
+fn equations<E: Pairing, S: LinearlyHomomorphicSignature<E>>(
+    enc_key: &EncryptKey<E, S>,
+    c: &[E::G1Affine],
+    t: E::ScalarField,
+    vars: &ProofVariables,
+) -> Vec<Equation<E, PairingProduct>> {
+    let (f, g, g2) = (enc_key.f, enc_key.g, enc_key.crs.g2_gen);
+    let (xb, x1b, yb) = (ProofVariables::XB, ProofVariables::X1B, ProofVariables::YB);
+    let new = Equation::<E, PairingProduct>::new;
+
+    let mut equs = vec![
+        // e(X_b, g~) e(g^-1, Y_b) = 1
+        new().pair(Var(xb), Const(g2)).pair(Const(-g), Var(yb)),
+        // e(X_b, g~) e(X_1-b, g~) = e(g, g~)
+        new()
+            .pair(Var(xb), Const(g2))
+            .pair(Var(x1b), Const(g2))
+            .target(E::pairing(g, g2)),
+        // e(X_1-b, Y_b) = 1
+        new().pair(Var(x1b), Var(yb)),
+    ];
+
+    // ps_i = c_i^b for i < 2, and c_i^(1-b) otherwise
+    for (i, c_i) in c.iter().enumerate() {
+        let equ = new().pair(Var(vars.ps(i)), Const(g2));
+        equs.push(if i < 2 {
+            equ.pair(Const(-*c_i), Var(yb))
+        } else {
+            equ.pair(Const(*c_i), Var(yb)).target(E::pairing(*c_i, g2))
+        });
+    }
+    // (c_0^t)^b
+    let c0_t: E::G1Affine = c[0].mul(t).into();
+    equs.push(
+        new()
+            .pair(Var(vars.label()), Const(g2))
+            .pair(Const(-c0_t), Var(yb)),
+    );
+    // f^b and h_i^(1-b)
+    equs.push(
+        new()
+            .pair(Var(vars.fb()), Const(g2))
+            .pair(Const(-f), Var(yb)),
+    );
+    for (i, h_i) in enc_key.h.iter().enumerate() {
+        equs.push(
+            new()
+                .pair(Var(vars.hb(i)), Const(g2))
+                .pair(Const(*h_i), Var(yb))
+                .target(E::pairing(*h_i, g2)),
+        );
+    }
+
+    // Π e(sig_j, B_j) Π e(v_s, pk_s)^-1 = 1, and the same for w
+    let bases = S::equation_bases(&enc_key.lhsps_vk, S::DEFAULT_TAG);
+    let pk = S::message_bases(&enc_key.lhsps_vk);
+    let mut equ_v = new();
+    let mut equ_w = new();
+    for (j, b_j) in bases.iter().enumerate() {
+        equ_v = equ_v.pair(Var(vars.sig_v(j)), Const(*b_j));
+        equ_w = equ_w.pair(Var(vars.sig_w(j)), Const(*b_j));
+    }
+    for (s, pk_s) in pk.iter().enumerate() {
+        equ_v = equ_v.pair(Var(vars.v(s)), Const(-*pk_s));
+        if let Some(w_s) = vars.w(s) {
+            equ_w = equ_w.pair(Var(w_s), Const(-*pk_s));
+        }
+    }
+    equs.push(equ_v);
+    equs.push(equ_w);
+
+    equs
+}
@@ -1,31 +1,130 @@
 use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use ark_std::rand::RngCore;
 use std::ops::{Mul, Neg};
 
-use super::{ciphertext::Ciphertext, encrypt_key::EncryptKey};
+use crate::dleq::DleqProof;
+use crate::lhsps::{LinearlyHomomorphicSignature, OneTime};
 
-pub struct DecryptKey<E: Pairing> {
+use super::{
+    bytes_ciphertext::{BytesCiphertext, BytesError},
+    ciphertext::Ciphertext,
+    compact_ciphertext::CompactCiphertext,
+    decryption_proof::{DecryptionProof, DOMAIN},
+    encrypt_key::EncryptKey,
+    labelled_ciphertext::{LabelledCiphertext, LabelledCompactCiphertext},
+    RccaError,
+};
+
+pub struct DecryptKey<E: Pairing, S: LinearlyHomomorphicSignature<E> = OneTime> {
     // used for proof verification in decryption.
-    pub(crate) enc_key: EncryptKey<E>,
+    pub(crate) enc_key: EncryptKey<E, S>,
 
     // secret key
     pub(crate) alpha: Vec<E::ScalarField>,
 }
 
-impl<E: Pairing> DecryptKey<E> {
+impl<E: Pairing, S: LinearlyHomomorphicSignature<E>> DecryptKey<E, S> {
     /// Decrypts a ciphertext.
     ///
     /// A deterministic decryption algorithm which takes a ciphertext, and
     /// outputs either a plaintext or an error
-    pub fn decrypt(&self, c: &Ciphertext<E>) -> Result<Vec<E::G1Affine>, ()> {
+    pub fn decrypt(&self, c: &Ciphertext<E>) -> Result<Vec<E::G1Affine>, RccaError> {
         // check all proofs
         c.check_proofs(&self.enc_key)?;
 
-        // compute M_i = c_i+1 / c_1^alpha_i
+        Ok(self.open(&c.c))
+    }
+
+    /// Decrypts a compact ciphertext.
+    ///
+    /// A deterministic decryption algorithm which takes a compact ciphertext, and
+    /// outputs either a plaintext or an error
+    pub fn decrypt_compact(&self, c: &CompactCiphertext<E>) -> Result<Vec<E::G1Affine>, RccaError> {
+        // check all proofs
+        c.check_proofs(&self.enc_key)?;
+
+        Ok(self.open(&c.c))
+    }
+
+    /// Decrypts a labelled ciphertext. It fails if the ciphertext is not bound to `label`.
+    pub fn decrypt_labelled(
+        &self,
+        c: &LabelledCiphertext<E>,
+        label: &[u8],
+    ) -> Result<Vec<E::G1Affine>, RccaError> {
+        c.check_proofs(&self.enc_key, label)?;
+
+        Ok(self.open(&c.ciphertext.c))
+    }
+
+    /// Decrypts a labelled compact ciphertext. It fails if the ciphertext is not bound to `label`.
+    pub fn decrypt_compact_labelled(
+        &self,
+        c: &LabelledCompactCiphertext<E>,
+        label: &[u8],
+    ) -> Result<Vec<E::G1Affine>, RccaError> {
+        c.check_proofs(&self.enc_key, label)?;
+
+        Ok(self.open(&c.ciphertext.c))
+    }
+
+    /// Decrypts the byte string encrypted by `EncryptKey::encrypt_bytes`.
+    pub fn decrypt_bytes(&self, c: &BytesCiphertext<E>) -> Result<Vec<u8>, BytesError> {
+        let points = self
+            .decrypt_compact(&c.kem)
+            .map_err(|_| BytesError::Decryption)?;
+        c.open(&points)
+    }
+
+    // compute M_i = c_i+1 / c_1^alpha_i
+    fn open(&self, c: &[E::G1Affine]) -> Vec<E::G1Affine> {
         let mut m = Vec::new();
-        for i in 0..c.c.len() - 2 {
-            m.push((c.c[i + 2] + c.c[1].mul(self.alpha[i]).neg()).into());
+        for i in 0..c.len() - 2 {
+            m.push((c[i + 2] + c[1].mul(self.alpha[i]).neg()).into());
         }
+        m
+    }
+
+    /// Decrypts a ciphertext, with a proof that the decryption is correct under the encryption key.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::rcca::{self, decryption_proof::verify_decryption};
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = rcca::key_gen::<E, _>(rng, 3);
+    /// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+    /// let c = ek.encrypt(rng, &m).unwrap();
+    /// let (m_d, pf) = dk.decrypt_with_proof(rng, &c).unwrap();
+    /// assert_eq!(m, m_d);
+    /// assert!(verify_decryption(&ek, &c, &m_d, &pf));
+    /// ```
+    pub fn decrypt_with_proof<R: RngCore>(
+        &self,
+        rng: &mut R,
+        c: &Ciphertext<E>,
+    ) -> Result<(Vec<E::G1Affine>, DecryptionProof<E>), RccaError> {
+        let m = self.decrypt(c)?;
+
+        // log_g(h_i) = log_c_1(c_i+1 / M_i)
+        let g = self.enc_key.g;
+        let proofs = m
+            .iter()
+            .zip(self.enc_key.h.iter().zip(&self.alpha))
+            .zip(&c.c[2..])
+            .map(|((mi, (hi, alpha_i)), ci)| {
+                let di: E::G1Affine = (*ci + mi.into_group().neg()).into();
+                DleqProof::prove(rng, DOMAIN, (g, *hi), (c.c[1], di), *alpha_i)
+            })
+            .collect();
 
-        Ok(m)
+        Ok((m, DecryptionProof { proofs }))
     }
 }
@@ -0,0 +1,41 @@
+use ark_ec::pairing::Pairing;
+use ark_ec::AffineRepr;
+use std::ops::Neg;
+
+use crate::dleq::DleqProof;
+use crate::lhsps::LinearlyHomomorphicSignature;
+
+use super::{ciphertext::Ciphertext, encrypt_key::EncryptKey};
+
+pub(crate) const DOMAIN: &[u8] = b"transferable-ecash/rcca/decryption";
+
+/// A proof that a vector is the decryption of a ciphertext, generated by `DecryptKey::decrypt_with_proof`.
+#[derive(Clone)]
+pub struct DecryptionProof<E: Pairing> {
+    pub(crate) proofs: Vec<DleqProof<E>>,
+}
+
+/// Verifies that `m` is the correct decryption of the ciphertext `c` under the encryption key `ek`.
+pub fn verify_decryption<E: Pairing, S: LinearlyHomomorphicSignature<E>>(
+    ek: &EncryptKey<E, S>,
+    c: &Ciphertext<E>,
+    m: &[E::G1Affine],
+    proof: &DecryptionProof<E>,
+) -> bool {
+    if m.len() != ek.h.len() || proof.proofs.len() != m.len() || c.c.len() != m.len() + 2 {
+        return false;
+    }
+    // decryption fails on a ciphertext with invalid proofs.
+    if c.check_proofs(ek).is_err() {
+        return false;
+    }
+
+    // log_g(h_i) = log_c_1(c_i+1 / M_i)
+    m.iter()
+        .zip(ek.h.iter().zip(&proof.proofs))
+        .zip(&c.c[2..])
+        .all(|((mi, (hi, pf)), ci)| {
+            let di: E::G1Affine = (*ci + mi.into_group().neg()).into();
+            pf.verify(DOMAIN, (ek.g, *hi), (c.c[1], di))
+        })
+}
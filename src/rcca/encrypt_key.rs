@@ -1,44 +1,126 @@
-use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ec::pairing::Pairing;
 use ark_std::rand::RngCore;
-use ark_std::{rand::Rng, One, UniformRand};
+use ark_std::{rand::Rng, One, UniformRand, Zero};
 use groth_sahai::{prover::CProof, CRS};
 use std::ops::{Mul, Neg};
 
-use crate::lhsps;
-use crate::proof::create_proof_ayxb;
+use crate::lhsps::{signed_vector::SignedVector, LinearlyHomomorphicSignature, OneTime};
+use crate::proof::{create_proof_ayxb, zk::ZkProof};
 
-use super::ciphertext::Ciphertext;
+use super::{
+    bytes_ciphertext::{BytesCiphertext, BytesError, MAX_MESSAGE_BYTES},
+    ciphertext::Ciphertext,
+    compact_ciphertext::CompactCiphertext,
+    labelled_ciphertext::{label_scalar, LabelledCiphertext, LabelledCompactCiphertext},
+    layout::RccaVectorLayout,
+    RccaError,
+};
 
-pub struct EncryptKey<E: Pairing> {
+pub struct EncryptKey<E: Pairing, S: LinearlyHomomorphicSignature<E> = OneTime> {
     pub(crate) f: E::G1Affine,
     pub(crate) g: E::G1Affine,
 
     pub(crate) h: Vec<E::G1Affine>,
     pub(crate) crs: CRS<E>,
 
-    pub(crate) lhsps_sig_v1: lhsps::signature::Signature<E>,
-    pub(crate) lhsps_sig_v2: lhsps::signature::Signature<E>,
-    pub(crate) lhsps_vk: lhsps::verifying_key::VerifyKey<E>,
+    // v1 = [f,g,1,1,...,1,1], v2 = [1,1,1,h1,h2,...,hn,1] and v3 = [1,1,1,1,...,1,f], with their
+    // lhsps signatures. See `RccaVectorLayout`.
+    pub(crate) lhsps_v1: SignedVector<E, S::Signature>,
+    pub(crate) lhsps_v2: SignedVector<E, S::Signature>,
+    pub(crate) lhsps_v3: SignedVector<E, S::Signature>,
+    pub(crate) lhsps_vk: S::VerifyKey,
 }
 
-impl<E: Pairing> EncryptKey<E> {
+impl<E: Pairing, S: LinearlyHomomorphicSignature<E>> Clone for EncryptKey<E, S> {
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f,
+            g: self.g,
+            h: self.h.clone(),
+            // TODO derive Clone for CRS
+            crs: CRS::<E> {
+                u: self.crs.u.clone(),
+                v: self.crs.v.clone(),
+                g1_gen: self.crs.g1_gen,
+                g2_gen: self.crs.g2_gen,
+                gt_gen: self.crs.gt_gen,
+            },
+            lhsps_v1: self.lhsps_v1.clone(),
+            lhsps_v2: self.lhsps_v2.clone(),
+            lhsps_v3: self.lhsps_v3.clone(),
+            lhsps_vk: self.lhsps_vk.clone(),
+        }
+    }
+}
+
+impl<E: Pairing, S: LinearlyHomomorphicSignature<E>> EncryptKey<E, S> {
+    /// The layout of the vectors signed by the LHSPS of this key.
+    pub fn layout(&self) -> RccaVectorLayout {
+        RccaVectorLayout::new(self.h.len())
+    }
+
     /// Encrypt a message.
     ///
     /// A randomized encryption algorithm which takes as input,
     /// - a message `m`,
     /// - some randomness `rng`,
     ///
-    /// and outputs a ciphertext.
-    pub fn encrypt<R: Rng>(&self, rng: &mut R, m: &[E::G1Affine]) -> Ciphertext<E> {
+    /// and outputs a ciphertext. It fails if `m` does not have the dimension n of the key.
+    pub fn encrypt<R: Rng>(
+        &self,
+        rng: &mut R,
+        m: &[E::G1Affine],
+    ) -> Result<Ciphertext<E>, RccaError> {
+        self.layout().check_message(m)?;
         let phi = E::ScalarField::rand(rng);
-        // c = [c0, c1, ..., cn+1]
-        //   = [f^phi, g^phi, m1^phi + h1^phi, m2^phi + h2^phi, ..., mn^phi + hn^phi]
-        let mut c = vec![self.f.mul(phi).into(), self.g.mul(phi).into()];
-        c.extend(
-            m.iter()
-                .zip(self.h.iter())
-                .map(|(mi, hi)| (*mi + hi.mul(phi)).into()),
-        );
+        Ok(self.encrypt_with(rng, phi, E::ScalarField::zero(), m))
+    }
+
+    /// Encrypt a message bound to a label (e.g. a coin identifier or a hop index).
+    ///
+    /// The label is hashed to t, and the lhsps signature on v = v1^phi v3^(phi t) is proven
+    /// instead of the one on v1^phi (see `RccaVectorLayout`). Without phi, the ciphertext cannot
+    /// be moved under another label nor stripped of its label. `DecryptKey::decrypt_labelled`
+    /// fails if the label does not match, and `DecryptKey::decrypt` fails on the ciphertext.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::rcca;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = rcca::key_gen::<E, _>(rng, 3);
+    /// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+    /// let c = ek.encrypt_labelled(rng, &m, b"coin-1/hop-0").unwrap();
+    /// assert_eq!(dk.decrypt_labelled(&c, b"coin-1/hop-0").unwrap(), m);
+    /// assert!(dk.decrypt_labelled(&c, b"coin-2/hop-0").is_err());
+    /// ```
+    pub fn encrypt_labelled<R: Rng>(
+        &self,
+        rng: &mut R,
+        m: &[E::G1Affine],
+        label: &[u8],
+    ) -> Result<LabelledCiphertext<E>, RccaError> {
+        self.layout().check_message(m)?;
+        let phi = E::ScalarField::rand(rng);
+        let ciphertext = self.encrypt_with(rng, phi, label_scalar::<E>(label), m);
+        Ok(LabelledCiphertext { ciphertext })
+    }
+
+    // Encrypts m with the randomness phi under the label hashed to t (t = 0 without a label).
+    fn encrypt_with<R: Rng>(
+        &self,
+        rng: &mut R,
+        phi: E::ScalarField,
+        t: E::ScalarField,
+        m: &[E::G1Affine],
+    ) -> Ciphertext<E> {
+        let c = self.encrypt_vector(phi, m);
 
         // generate gs-proof of e(g^b, g2) + e(g, g2^-b) = 0
         // TODO: trivial because b = 1. optimization point here.
@@ -72,24 +154,12 @@ impl<E: Pairing> EncryptKey<E> {
                 .unwrap()
             })
             .collect();
-        // v = [c_0^b, c_1^b, g^(1-b), c_2^(1-b), ..., c_n+1^(1-b)]
-        //   = [c_0, c_1, 1, 1, ..., 1]
-        let mut v = vec![
-            c[0],                // c_0^b = c_0
-            c[1],                // c_1^b = c_1
-            E::G1Affine::zero(), // g^(1-b) = 1
-        ];
-        v.extend(vec![E::G1Affine::zero(); c.len() - 2]); // c_2^(1-b) = 1, ..., c_n+1^(1-b) = 1
-
-        // generate lhsps signature on v = v1^phi + v2 ^ 0 = v1^phi, hence only lhsps_sig_v1 is needed
-        let sig_with_w = vec![(phi, self.lhsps_sig_v1)];
-        let sigv = self.lhsps_vk.sign_derive(&sig_with_w).unwrap();
-
+        let layout = self.layout();
+        // v = [c_0^b, c_1^b, g^(1-b), c_2^(1-b), ..., c_n+1^(1-b), c_0^(t b)]
+        //   = [c_0, c_1, 1, 1, ..., 1, c_0^t]
         // generate proof of validity of lhsps signature on v
-        let cpf_v = self
-            .lhsps_vk
-            .generate_proof(rng, &self.crs, &v, &sigv)
-            .unwrap();
+        let v = layout.v_labelled::<E>(&c, t).unwrap();
+        let cpf_v = self.prove_v_power(rng, phi, t, &v);
 
         // generate proof of (f^b, g^b, h_1^b, ..., h_n^b)
         let mut fgh = vec![self.f.mul(b).into(), self.g.mul(b).into()];
@@ -115,24 +185,11 @@ impl<E: Pairing> EncryptKey<E> {
             })
             .collect();
 
-        // w = (f^b, g^b, 1, h_1^(1-b), ..., h_n^(1-b))
-        //   = (f, g, 1, 1, ..., 1)
-        let mut w = vec![
-            self.f, // f^b = f
-            self.g, // g^b = g
-            E::G1Affine::zero(),
-        ];
-        w.extend(vec![E::G1Affine::zero(); c.len() - 2]); // h_i^(1-b) = 1
-
-        // generate lhsps signature on w = v1^b + v2^0 = v1^b, hence only lhsps_sig_v1 is needed
-        let sig_with_w = vec![(b, self.lhsps_sig_v1)];
-        let sigw = self.lhsps_vk.sign_derive(&sig_with_w).unwrap();
-
+        // w = (f^b, g^b, 1, h_1^(1-b), ..., h_n^(1-b), 1)
+        //   = (f, g, 1, 1, ..., 1, 1) = v1
         // generate proof of validity of lhsps signature on w
-        let cpf_w = self
-            .lhsps_vk
-            .generate_proof(rng, &self.crs, &w, &sigw)
-            .unwrap();
+        let w = layout.v1::<E>(self.f, self.g);
+        let cpf_w = self.prove_v_power(rng, b, E::ScalarField::zero(), &w);
 
         // Output ciphertext c = (ci for i in 1..n, cpf_b, cpf_ps, cpf_v, cpf_fgh, cpf_w)
         Ciphertext {
@@ -145,44 +202,190 @@ impl<E: Pairing> EncryptKey<E> {
         }
     }
 
+    /// Encrypt a message into a `CompactCiphertext`, whose validity is given by a single proof over shared commitments.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::rcca;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = rcca::key_gen::<E, _>(rng, 3);
+    /// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+    /// let c = ek.encrypt_compact(rng, &m).unwrap();
+    /// assert_eq!(dk.decrypt_compact(&c).unwrap(), m);
+    /// ```
+    pub fn encrypt_compact<R: Rng>(
+        &self,
+        rng: &mut R,
+        m: &[E::G1Affine],
+    ) -> Result<CompactCiphertext<E>, RccaError> {
+        self.layout().check_message(m)?;
+        Ok(self.encrypt_compact_with(rng, E::ScalarField::zero(), m))
+    }
+
+    /// Encrypt a message into a `LabelledCompactCiphertext`, i.e. a compact ciphertext bound to a
+    /// label as in `encrypt_labelled`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_ec::pairing::Pairing;
+    /// use ark_std::{test_rng, UniformRand};
+    /// use transferable_ecash::rcca;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type G1 = <E as Pairing>::G1Affine;
+    ///
+    /// let rng = &mut test_rng();
+    /// let (dk, ek) = rcca::key_gen::<E, _>(rng, 3);
+    /// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+    /// let c = ek.encrypt_compact_labelled(rng, &m, b"coin-1/hop-0").unwrap();
+    /// assert_eq!(dk.decrypt_compact_labelled(&c, b"coin-1/hop-0").unwrap(), m);
+    /// assert!(dk.decrypt_compact_labelled(&c, b"coin-2/hop-0").is_err());
+    /// ```
+    pub fn encrypt_compact_labelled<R: Rng>(
+        &self,
+        rng: &mut R,
+        m: &[E::G1Affine],
+        label: &[u8],
+    ) -> Result<LabelledCompactCiphertext<E>, RccaError> {
+        self.layout().check_message(m)?;
+        let ciphertext = self.encrypt_compact_with(rng, label_scalar::<E>(label), m);
+        Ok(LabelledCompactCiphertext { ciphertext })
+    }
+
+    // Encrypts m into a compact ciphertext under the label hashed to t (t = 0 without a label).
+    fn encrypt_compact_with<R: Rng>(
+        &self,
+        rng: &mut R,
+        t: E::ScalarField,
+        m: &[E::G1Affine],
+    ) -> CompactCiphertext<E> {
+        let phi = E::ScalarField::rand(rng);
+        let c = self.encrypt_vector(phi, m);
+        // v = v1^phi v3^(phi t) = [c_0, c_1, 1, 1, ..., 1, c_0^t] and w = v1 = (f, g, 1, 1, ..., 1, 1)
+        let v = self.v_power(phi, t);
+        CompactCiphertext::prove(rng, self, c, t, v.signature(), self.lhsps_v1.signature())
+    }
+
+    /// Encrypt a byte string (e.g. a memo or a receipt) of at most `bytes_ciphertext::MAX_MESSAGE_BYTES` bytes.
+    ///
+    /// n random points of G1 are encrypted into a compact ciphertext, and the bytes are encrypted
+    /// by XChaCha20-Poly1305 under a key derived from the points (see `BytesCiphertext`).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use transferable_ecash::rcca;
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    ///
+    /// let rng = &mut ark_std::test_rng();
+    /// let (dk, ek) = rcca::key_gen::<E, _>(rng, 2);
+    /// let c = ek.encrypt_bytes(rng, b"receipt #42: 3 coins").unwrap();
+    /// assert_eq!(dk.decrypt_bytes(&c).unwrap(), b"receipt #42: 3 coins");
+    /// ```
+    pub fn encrypt_bytes<R: Rng>(
+        &self,
+        rng: &mut R,
+        data: &[u8],
+    ) -> Result<BytesCiphertext<E>, BytesError> {
+        let n = self.h.len();
+        if n == 0 {
+            return Err(BytesError::TooLong {
+                len: data.len(),
+                max: 0,
+            });
+        }
+        if data.len() > MAX_MESSAGE_BYTES {
+            return Err(BytesError::TooLong {
+                len: data.len(),
+                max: MAX_MESSAGE_BYTES,
+            });
+        }
+
+        let points = (0..n).map(|_| E::G1Affine::rand(rng)).collect::<Vec<_>>();
+        let kem = self.encrypt_compact_with(rng, E::ScalarField::zero(), &points);
+        Ok(BytesCiphertext::seal(kem, &points, data))
+    }
+
+    // c = [c0, c1, ..., cn+1]
+    //   = [f^phi, g^phi, m1^phi + h1^phi, m2^phi + h2^phi, ..., mn^phi + hn^phi]
+    fn encrypt_vector(&self, phi: E::ScalarField, m: &[E::G1Affine]) -> Vec<E::G1Affine> {
+        let mut c = vec![self.f.mul(phi).into(), self.g.mul(phi).into()];
+        c.extend(
+            m.iter()
+                .zip(self.h.iter())
+                .map(|(mi, hi)| (*mi + hi.mul(phi)).into()),
+        );
+        c
+    }
+
+    // Derives v1^x v3^(x t) and its lhsps signature (hence v2 is not needed). Without a label
+    // (t = 0), it is v1^x.
+    fn v_power(&self, x: E::ScalarField, t: E::ScalarField) -> SignedVector<E, S::Signature> {
+        let mut sv_with_w = vec![(x, &self.lhsps_v1)];
+        if !t.is_zero() {
+            sv_with_w.push((x * t, &self.lhsps_v3));
+        }
+        S::derive(&self.lhsps_vk, S::DEFAULT_TAG, &sv_with_w).unwrap()
+    }
+
+    // Generates the zero-knowledge proof of validity of the lhsps signature on the vector m of
+    // the layout, signed as v1^x v3^(x t). It panics if m is not v1^x v3^(x t).
+    fn prove_v_power<R: Rng>(
+        &self,
+        rng: &mut R,
+        x: E::ScalarField,
+        t: E::ScalarField,
+        m: &[E::G1Affine],
+    ) -> ZkProof<E> {
+        let v = self.v_power(x, t);
+        assert_eq!(v.message(), m, "the vector does not match the layout");
+        S::generate_proof_zk(
+            &self.lhsps_vk,
+            rng,
+            &self.crs,
+            S::DEFAULT_TAG,
+            m,
+            v.signature(),
+        )
+        .unwrap()
+    }
+
     /// Re-randomize a ciphertext.
     ///
-    /// A randomized algorithm which mutates the input ciphertext `c` with some randomness.
-    pub fn rerandomize<R: RngCore>(&self, rng: &mut R, c: &mut Ciphertext<E>) {
-        let v = E::ScalarField::rand(rng);
-        // c_0' = c_0 * f^v, c_1' = c_1 * g^v, c_i' = c_i * h_i^v
-        c.c[0] = (c.c[0] + self.f.mul(v)).into();
-        c.c[1] = (c.c[1] + self.g.mul(v)).into();
-        c.c[2..]
-            .iter_mut()
-            .zip(self.h.iter())
-            .for_each(|(ci, hi)| *ci = (*ci + hi.mul(v)).into());
-        // randomize proofs: cpf_b, cpf_ps using cpf_fgh
-        // TODO
-
-        // update proof cpf_v
-        // TODO
-
-        // randomize proofs: cpf_b', cpf_ps' cpf_v', cpf_fgh, cpf_w
-        // TODO
-
-        todo!()
+    /// Re-randomizing c = [c_0, c_1, c_2, ..., c_n+1] into [c_0 f^v, c_1 g^v, c_2 h_1^v, ...] invalidates
+    /// the proofs of the ciphertext, and adapting them (see `adapt_proof`) is not implemented yet.
+    /// Hence it fails with `RccaError::Unsupported` and leaves `c` unchanged, after checking that
+    /// `c` is a valid ciphertext.
+    pub fn rerandomize<R: RngCore>(
+        &self,
+        _rng: &mut R,
+        c: &mut Ciphertext<E>,
+    ) -> Result<(), RccaError> {
+        c.check_proofs(self)?;
+        Err(RccaError::Unsupported)
     }
 
     /// Check if the ciphertext encrypts a given message.
     ///
     /// A deterministic algorithm which takes as input,
     /// - a message `m`,
-    /// - a ciphertext `c`m
+    /// - a ciphertext `c`
     ///
-    /// and outputs a bit.
-    pub fn verify(&self, _m: &[E::G1Affine], c: &Ciphertext<E>) -> bool {
-        if c.check_proofs(self).is_err() {
-            return false;
-        }
-        // check equations: c_0=g^v, c_1=f^v, c_i=h_i^v + m_i
-        // TODO
-        true
+    /// and outputs `Ok(())` if `c` encrypts `m`. It needs the equality proof of the paper, which is
+    /// not implemented yet, so it fails with `RccaError::Unsupported` after checking `m` and `c`.
+    pub fn verify(&self, m: &[E::G1Affine], c: &Ciphertext<E>) -> Result<(), RccaError> {
+        self.layout().check_message(m)?;
+        c.check_proofs(self)?;
+        Err(RccaError::Unsupported)
     }
 
     /// Adapt a proof to a rerandomization.
@@ -196,8 +399,14 @@ impl<E: Pairing> EncryptKey<E> {
     /// - a proof,
     /// - some randomness,
     ///
-    /// and outputs an equality proof.
-    pub fn adapt_proof<R: RngCore>(&self, rng: &mut R, c: &Ciphertext<E>) {
-        todo!()
+    /// and outputs an equality proof. It is not implemented yet, so it fails with
+    /// `RccaError::Unsupported` after checking that `c` is a valid ciphertext.
+    pub fn adapt_proof<R: RngCore>(
+        &self,
+        _rng: &mut R,
+        c: &Ciphertext<E>,
+    ) -> Result<(), RccaError> {
+        c.check_proofs(self)?;
+        Err(RccaError::Unsupported)
     }
 }
@@ -0,0 +1,73 @@
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+
+use crate::lhsps::LinearlyHomomorphicSignature;
+
+use super::{
+    ciphertext::Ciphertext, compact_ciphertext::CompactCiphertext, encrypt_key::EncryptKey,
+    RccaError,
+};
+
+const DOMAIN: &[u8] = b"transferable-ecash/rcca/label";
+
+/// A ciphertext bound to a label.
+///
+/// The label is hashed to a scalar t, and the lhsps signature proven on v covers the label slot
+/// c_0^t (see `RccaVectorLayout`). Deriving that signature needs the encryption randomness phi,
+/// so the ciphertext verifies neither under another label nor without a label.
+#[derive(Clone)]
+pub struct LabelledCiphertext<E: Pairing> {
+    pub(crate) ciphertext: Ciphertext<E>,
+}
+
+impl<E: Pairing> LabelledCiphertext<E> {
+    /// Check all proofs of the ciphertext under `label`.
+    pub fn check_proofs<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+        label: &[u8],
+    ) -> Result<(), RccaError> {
+        self.ciphertext
+            .check_proofs_with(enc_key, label_scalar::<E>(label))
+    }
+
+    /// The size in bytes of the ciphertext in compressed form.
+    pub fn size_in_bytes(&self) -> usize {
+        self.ciphertext.size_in_bytes()
+    }
+}
+
+/// A compact ciphertext bound to a label, in the same way as `LabelledCiphertext`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LabelledCompactCiphertext<E: Pairing> {
+    pub(crate) ciphertext: CompactCiphertext<E>,
+}
+
+impl<E: Pairing> LabelledCompactCiphertext<E> {
+    /// Check all proofs of the ciphertext under `label`.
+    pub fn check_proofs<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+        label: &[u8],
+    ) -> Result<(), RccaError> {
+        self.ciphertext
+            .check_proofs_with(enc_key, label_scalar::<E>(label))
+    }
+
+    /// The size in bytes of the ciphertext in compressed form.
+    pub fn size_in_bytes(&self) -> usize {
+        self.ciphertext.size_in_bytes()
+    }
+}
+
+// t = H(DOMAIN || len(label) || label)
+pub(crate) fn label_scalar<E: Pairing>(label: &[u8]) -> E::ScalarField {
+    let digest = Sha256::new()
+        .chain_update(DOMAIN)
+        .chain_update((label.len() as u64).to_le_bytes())
+        .chain_update(label)
+        .finalize();
+    E::ScalarField::from_le_bytes_mod_order(&digest)
+}
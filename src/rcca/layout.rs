@@ -0,0 +1,189 @@
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_std::Zero;
+use std::ops::Mul;
+
+use super::RccaError;
+
+/// The layout of the vectors signed by the LHSPS of the RCCA encryption scheme of dimension n.
+///
+/// All vectors have dimension n + 4 and share the same slots:
+///
+/// | slot       | 0     | 1     | 2         | 3 .. n+3                    | n+3 (label)  |
+/// |------------|-------|-------|-----------|-----------------------------|--------------|
+/// | v1         | f     | g     | 1         | 1, ..., 1                   | 1            |
+/// | v2         | 1     | 1     | 1         | h_1, ..., h_n               | 1            |
+/// | v3         | 1     | 1     | 1         | 1, ..., 1                   | f            |
+/// | v          | c_0^b | c_1^b | g^(1-b)   | c_2^(1-b), ..., c_n+1^(1-b) | c_0^(t b)    |
+/// | w          | f^b   | g^b   | 1         | h_1^(1-b), ..., h_n^(1-b)   | 1            |
+///
+/// t is the hash of the label of the ciphertext, and t = 0 without a label. With b = 1,
+/// v = v1^phi v3^(phi t) = [c_0, c_1, 1, ..., 1, c_0^t] and w = v1 = [f, g, 1, ..., 1].
+/// The ciphertext c = [c_0, c_1, ..., c_n+1] has dimension n + 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RccaVectorLayout {
+    n: usize,
+}
+
+impl RccaVectorLayout {
+    /// The layout of the scheme of dimension n.
+    pub fn new(n: usize) -> Self {
+        Self { n }
+    }
+
+    /// The dimension n of the messages.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The dimension of the signed vectors, i.e. the dimension of the LHSPS.
+    pub fn dim(&self) -> usize {
+        self.n + 4
+    }
+
+    /// The slot of the label, i.e. the last slot.
+    pub fn label_slot(&self) -> usize {
+        self.n + 3
+    }
+
+    /// The dimension of the ciphertext vector c.
+    pub fn ciphertext_len(&self) -> usize {
+        self.n + 2
+    }
+
+    /// Checks that the message has dimension n.
+    pub fn check_message<G>(&self, m: &[G]) -> Result<(), RccaError> {
+        if m.len() != self.n {
+            return Err(RccaError::MessageDimension {
+                expected: self.n,
+                actual: m.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that the ciphertext vector has dimension n + 2.
+    pub fn check_ciphertext<G>(&self, c: &[G]) -> Result<(), RccaError> {
+        if c.len() != self.ciphertext_len() {
+            return Err(RccaError::CiphertextDimension {
+                expected: self.ciphertext_len(),
+                actual: c.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// v1 = [f, g, 1, 1, ..., 1], which is also w with b = 1.
+    pub fn v1<E: Pairing>(&self, f: E::G1Affine, g: E::G1Affine) -> Vec<E::G1Affine> {
+        self.head::<E>(f, g)
+    }
+
+    /// v2 = [1, 1, 1, h_1, ..., h_n]. It fails if `h` does not have dimension n.
+    pub fn v2<E: Pairing>(&self, h: &[E::G1Affine]) -> Result<Vec<E::G1Affine>, RccaError> {
+        self.check_message(h)?;
+        let mut v2 = vec![E::G1Affine::zero(); 3];
+        v2.extend_from_slice(h);
+        v2.resize(self.dim(), E::G1Affine::zero());
+        Ok(v2)
+    }
+
+    /// v3 = [1, 1, 1, 1, ..., 1, f], which carries the label.
+    pub fn v3<E: Pairing>(&self, f: E::G1Affine) -> Vec<E::G1Affine> {
+        let mut v3 = vec![E::G1Affine::zero(); self.dim()];
+        v3[self.label_slot()] = f;
+        v3
+    }
+
+    /// v = [c_0, c_1, 1, 1, ..., 1] of the ciphertext vector c without a label. It fails if `c` does not have dimension n + 2.
+    pub fn v<E: Pairing>(&self, c: &[E::G1Affine]) -> Result<Vec<E::G1Affine>, RccaError> {
+        self.v_labelled::<E>(c, E::ScalarField::zero())
+    }
+
+    /// v = [c_0, c_1, 1, 1, ..., 1, c_0^t] of the ciphertext vector c under the label hashed to t.
+    /// It fails if `c` does not have dimension n + 2.
+    pub fn v_labelled<E: Pairing>(
+        &self,
+        c: &[E::G1Affine],
+        t: E::ScalarField,
+    ) -> Result<Vec<E::G1Affine>, RccaError> {
+        self.check_ciphertext(c)?;
+        let mut v = self.head::<E>(c[0], c[1]);
+        v[self.label_slot()] = c[0].mul(t).into();
+        Ok(v)
+    }
+
+    // [x0, x1, 1, 1, ..., 1] of dimension n + 4
+    fn head<E: Pairing>(&self, x0: E::G1Affine, x1: E::G1Affine) -> Vec<E::G1Affine> {
+        let mut v = vec![x0, x1];
+        v.resize(self.dim(), E::G1Affine::zero());
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{pairing::Pairing, AffineRepr};
+    use ark_std::{test_rng, UniformRand};
+    use std::ops::Mul;
+
+    use super::RccaVectorLayout;
+    use crate::rcca::RccaError;
+
+    type E = ark_bls12_381::Bls12_381;
+    type Fr = <E as Pairing>::ScalarField;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_layout_dimensions() {
+        let rng = &mut test_rng();
+        for n in 1..=32 {
+            let layout = RccaVectorLayout::new(n);
+            let (f, g) = (G1::rand(rng), G1::rand(rng));
+            let h: Vec<G1> = (0..n).map(|_| G1::rand(rng)).collect();
+
+            let v1 = layout.v1::<E>(f, g);
+            let v2 = layout.v2::<E>(&h).unwrap();
+            let v3 = layout.v3::<E>(f);
+            assert_eq!(v1.len(), layout.dim());
+            assert_eq!(v2.len(), layout.dim());
+            assert_eq!(v3.len(), layout.dim());
+
+            // v of c = [f^phi, g^phi, ...] is v1^phi
+            let phi = Fr::rand(rng);
+            let mut c = vec![f.mul(phi).into(), g.mul(phi).into()];
+            c.extend(h.iter().map(|hi| G1::from(hi.mul(phi))));
+            assert_eq!(c.len(), layout.ciphertext_len());
+            let v1_phi: Vec<G1> = v1.iter().map(|x| x.mul(phi).into()).collect();
+            assert_eq!(layout.v::<E>(&c).unwrap(), v1_phi);
+
+            // v of c under the label t is v1^phi v3^(phi t)
+            let t = Fr::rand(rng);
+            let v_t: Vec<G1> = v1_phi
+                .iter()
+                .zip(&v3)
+                .map(|(a, b)| (b.mul(phi * t) + a).into())
+                .collect();
+            assert_eq!(layout.v_labelled::<E>(&c, t).unwrap(), v_t);
+            assert_ne!(layout.v_labelled::<E>(&c, t).unwrap(), v1_phi);
+
+            // v1, v2 and v3 have disjoint supports
+            for (a, b) in [(&v1, &v2), (&v1, &v3), (&v2, &v3)] {
+                assert!(a.iter().zip(b).all(|(a, b)| a.is_zero() || b.is_zero()));
+            }
+
+            assert_eq!(
+                layout.v::<E>(&c[1..]),
+                Err(RccaError::CiphertextDimension {
+                    expected: n + 2,
+                    actual: n + 1
+                })
+            );
+            assert_eq!(
+                layout.v2::<E>(&c),
+                Err(RccaError::MessageDimension {
+                    expected: n,
+                    actual: n + 2
+                })
+            );
+        }
+    }
+}
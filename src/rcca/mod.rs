@@ -3,28 +3,73 @@
 
 use std::vec;
 
-use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ec::pairing::Pairing;
 use ark_std::{ops::Mul, rand::RngCore, UniformRand};
 use decrypt_key::DecryptKey;
 use encrypt_key::EncryptKey;
 use groth_sahai::{AbstractCrs, CRS};
+use layout::RccaVectorLayout;
 
-use crate::lhsps;
+use crate::lhsps::{signed_vector::SignedVector, LinearlyHomomorphicSignature, OneTime};
 
+pub mod bytes_ciphertext;
 pub mod ciphertext;
+pub mod compact_ciphertext;
 pub mod decrypt_key;
+pub mod decryption_proof;
 pub mod encrypt_key;
+pub mod labelled_ciphertext;
+pub mod layout;
+pub mod threshold;
 
+/// The errors of the RCCA encryption scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RccaError {
+    /// The message does not have the dimension n of the key.
+    MessageDimension { expected: usize, actual: usize },
+    /// The ciphertext does not have n + 2 elements.
+    CiphertextDimension { expected: usize, actual: usize },
+    /// The ciphertext does not have the expected number of proofs.
+    MalformedCiphertext,
+    /// A proof of the ciphertext does not verify (e.g. under another label).
+    InvalidProof,
+    /// The threshold t is zero or larger than the number of servers.
+    InvalidThreshold,
+    /// There are less than t valid partial decryptions.
+    NotEnoughShares,
+    /// The operation is not implemented for this scheme (e.g. rerandomization).
+    Unsupported,
+}
+
+/// Generates key pair for the RCCA encryption scheme of dimension n, with the one-time LHSPS.
 pub fn key_gen<E: Pairing, R: RngCore>(rng: &mut R, n: usize) -> (DecryptKey<E>, EncryptKey<E>) {
+    key_gen_with::<E, OneTime, R>(rng, n)
+}
+
+/// Generates key pair for the RCCA encryption scheme of dimension n, with the LHSPS implementation `S`.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::{lhsps::tagged::Tagged, rcca};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+///
+/// let rng = &mut test_rng();
+/// let (dk, ek) = rcca::key_gen_with::<E, Tagged, _>(rng, 3);
+/// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+/// let c = ek.encrypt(rng, &m).unwrap();
+/// assert_eq!(dk.decrypt(&c).unwrap(), m);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn key_gen_with<E: Pairing, S: LinearlyHomomorphicSignature<E>, R: RngCore>(
+    rng: &mut R,
+    n: usize,
+) -> (DecryptKey<E, S>, EncryptKey<E, S>) {
     let crs = CRS::<E>::generate_crs(rng);
-    let crs_cloned = // TODO derive Clone for CRS
-        CRS::<E> {
-            u: crs.u.clone(),
-            v: crs.v.clone(),
-            g1_gen: crs.g1_gen,
-            g2_gen: crs.g2_gen,
-            gt_gen: crs.gt_gen,
-        };
 
     let f = E::G1Affine::rand(rng);
     let g = E::G1Affine::rand(rng);
@@ -39,42 +84,41 @@ pub fn key_gen<E: Pairing, R: RngCore>(rng: &mut R, n: usize) -> (DecryptKey<E>,
         .map(|alpha_i| g.mul(alpha_i).into())
         .collect::<Vec<_>>();
     // **
-    // v1 = [f,g,1,1,...,1]
-    let mut v1 = vec![f, g];
-    v1.extend(vec![E::G1Affine::zero(); n + 1]);
-    // v2 = [1,1,1,h1,h2,...,hn]
-    let mut v2 = vec![E::G1Affine::zero(); 3];
-    v2.extend_from_slice(&h);
+    let layout = RccaVectorLayout::new(n);
+    // v1 = [f,g,1,1,...,1,1]
+    let v1 = layout.v1::<E>(f, g);
+    // v2 = [1,1,1,h1,h2,...,hn,1]
+    let v2 = layout.v2::<E>(&h).unwrap();
+    // v3 = [1,1,1,1,...,1,f]
+    let v3 = layout.v3::<E>(f);
 
     // Notice that the LHSPS signing key tk will never be published by the key
     // generation algorithm, it will only be used in the security proofs.
-    let (tk, lhsps_vk) = lhsps::setup::<E, _>(rng, n + 3);
-    let lhsps_sig_v1 = tk.sign(&v1).unwrap();
-    let lhsps_sig_v2 = tk.sign(&v2).unwrap();
+    let (tk, lhsps_vk) = S::setup(rng, layout.dim());
+    let basis = [v1, v2, v3];
+    let mut sigs = S::sign_basis(tk, rng, S::DEFAULT_TAG, &basis).unwrap();
+    let [v1, v2, v3] = basis;
+    let lhsps_v3 = SignedVector::new(v3, sigs.pop().unwrap());
+    let lhsps_v2 = SignedVector::new(v2, sigs.pop().unwrap());
+    let lhsps_v1 = SignedVector::new(v1, sigs.pop().unwrap());
+
+    let enc_key = EncryptKey {
+        f,
+        g,
+        h,
+        crs,
+        lhsps_v1,
+        lhsps_v2,
+        lhsps_v3,
+        lhsps_vk,
+    };
 
     (
         DecryptKey {
-            enc_key: EncryptKey {
-                f,
-                g,
-                h: h.clone(),
-                crs: crs_cloned,
-                lhsps_sig_v1,
-                lhsps_sig_v2,
-                lhsps_vk: lhsps_vk.clone(),
-            },
-
+            enc_key: enc_key.clone(),
             alpha,
         },
-        EncryptKey {
-            f,
-            g,
-            h,
-            crs,
-            lhsps_sig_v1,
-            lhsps_sig_v2,
-            lhsps_vk,
-        },
+        enc_key,
     )
 }
 
@@ -82,9 +126,17 @@ pub fn key_gen<E: Pairing, R: RngCore>(rng: &mut R, n: usize) -> (DecryptKey<E>,
 mod tests {
     use ark_bls12_381::Bls12_381;
     use ark_ec::pairing::Pairing;
-    use ark_std::UniformRand;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::{rand::Rng, UniformRand};
 
-    use crate::rcca::key_gen;
+    use crate::lhsps::{tagged::Tagged, LinearlyHomomorphicSignature, OneTime};
+    use crate::rcca::{
+        bytes_ciphertext::{BytesCiphertext, BytesError, MAX_MESSAGE_BYTES},
+        decryption_proof::verify_decryption,
+        key_gen, key_gen_with,
+        labelled_ciphertext::{LabelledCiphertext, LabelledCompactCiphertext},
+        threshold, RccaError,
+    };
 
     type E = Bls12_381;
     type G1 = <E as Pairing>::G1Affine;
@@ -94,8 +146,323 @@ mod tests {
         let rng = &mut ark_std::test_rng();
         let (sk, pk) = key_gen::<E, _>(rng, 5);
         let m = (0..5).map(|_| G1::rand(rng)).collect::<Vec<_>>();
-        let c = pk.encrypt(rng, &m);
+        let c = pk.encrypt(rng, &m).unwrap();
         let m_d = sk.decrypt(&c).unwrap();
         assert_eq!(m, m_d);
     }
+
+    #[test]
+    fn test_verify_decryption() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = key_gen::<E, _>(rng, 3);
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let c = pk.encrypt(rng, &m).unwrap();
+        let (m_d, pf) = sk.decrypt_with_proof(rng, &c).unwrap();
+        assert!(verify_decryption(&pk, &c, &m_d, &pf));
+
+        // claim another message
+        let mut m_x = m_d.clone();
+        m_x[1] = G1::rand(rng);
+        assert!(!verify_decryption(&pk, &c, &m_x, &pf));
+        assert!(!verify_decryption(&pk, &c, &m_d[..2], &pf));
+    }
+
+    #[test]
+    fn test_unsupported_operations() {
+        let rng = &mut ark_std::test_rng();
+        let (_, pk) = key_gen::<E, _>(rng, 3);
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let c = pk.encrypt(rng, &m).unwrap();
+
+        let mut c_r = c.clone();
+        assert_eq!(pk.rerandomize(rng, &mut c_r), Err(RccaError::Unsupported));
+        assert_eq!(c_r.c, c.c);
+        assert_eq!(pk.verify(&m, &c), Err(RccaError::Unsupported));
+        assert_eq!(pk.adapt_proof(rng, &c), Err(RccaError::Unsupported));
+
+        // the inputs are checked first
+        assert_eq!(
+            pk.verify(&m[..2], &c),
+            Err(RccaError::MessageDimension {
+                expected: 3,
+                actual: 2
+            })
+        );
+        c_r.c.pop();
+        let err = Err(RccaError::CiphertextDimension {
+            expected: 5,
+            actual: 4,
+        });
+        assert_eq!(pk.rerandomize(rng, &mut c_r), err);
+        assert_eq!(pk.adapt_proof(rng, &c_r), err);
+    }
+
+    #[test]
+    fn test_ciphertext_sizes() {
+        let rng = &mut ark_std::test_rng();
+        // (n, size of Ciphertext, size of CompactCiphertext) over BLS12-381
+        for (n, full, compact) in [(5, 28944, 13392), (16, 65376, 28704), (64, 224352, 95520)] {
+            let (sk, pk) = key_gen::<E, _>(rng, n);
+            let m = (0..n).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+
+            let c = pk.encrypt(rng, &m).unwrap();
+            assert_eq!(c.size_in_bytes(), full);
+            assert_eq!(sk.decrypt(&c).unwrap(), m);
+
+            let c = pk.encrypt_compact(rng, &m).unwrap();
+            assert_eq!(c.size_in_bytes(), compact);
+            assert_eq!(sk.decrypt_compact(&c).unwrap(), m);
+        }
+    }
+
+    #[test]
+    fn test_labelled_encryption() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = key_gen::<E, _>(rng, 3);
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+
+        let c = pk.encrypt_labelled(rng, &m, b"coin-1/hop-2").unwrap();
+        assert!(c.check_proofs(&pk, b"coin-1/hop-2").is_ok());
+        assert_eq!(sk.decrypt_labelled(&c, b"coin-1/hop-2").unwrap(), m);
+        let err = Err(RccaError::InvalidProof);
+        assert_eq!(sk.decrypt_labelled(&c, b"coin-1/hop-3"), err);
+        assert_eq!(sk.decrypt_labelled(&c, b""), err);
+        // the label cannot be stripped
+        assert_eq!(sk.decrypt(&c.ciphertext), err);
+        assert_eq!(c.ciphertext.check_proofs(&pk), Err(RccaError::InvalidProof));
+        // and an unlabelled ciphertext is not a labelled one
+        let c_u = LabelledCiphertext {
+            ciphertext: pk.encrypt(rng, &m).unwrap(),
+        };
+        assert_eq!(sk.decrypt_labelled(&c_u, b""), err);
+
+        // the compact ciphertexts
+        let c = pk
+            .encrypt_compact_labelled(rng, &m, b"coin-1/hop-2")
+            .unwrap();
+        assert_eq!(sk.decrypt_compact_labelled(&c, b"coin-1/hop-2").unwrap(), m);
+        assert_eq!(sk.decrypt_compact_labelled(&c, b"coin-1/hop-3"), err);
+        assert_eq!(sk.decrypt_compact(&c.ciphertext), err);
+        let c_u = LabelledCompactCiphertext {
+            ciphertext: pk.encrypt_compact(rng, &m).unwrap(),
+        };
+        assert_eq!(sk.decrypt_compact_labelled(&c_u, b""), err);
+
+        let mut bytes = Vec::new();
+        c.serialize_compressed(&mut bytes).unwrap();
+        let c = LabelledCompactCiphertext::<E>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(sk.decrypt_compact_labelled(&c, b"coin-1/hop-2").unwrap(), m);
+
+        // with the tagged LHSPS
+        let (sk, pk) = key_gen_with::<E, Tagged, _>(rng, 3);
+        let c = pk
+            .encrypt_compact_labelled(rng, &m, b"coin-1/hop-2")
+            .unwrap();
+        assert_eq!(sk.decrypt_compact_labelled(&c, b"coin-1/hop-2").unwrap(), m);
+        assert_eq!(sk.decrypt_compact_labelled(&c, b"coin-1/hop-3"), err);
+    }
+
+    #[test]
+    fn test_encrypt_bytes() {
+        let rng = &mut ark_std::test_rng();
+        let (sk, pk) = key_gen::<E, _>(rng, 3);
+
+        for len in [0, 1, 135, 1000] {
+            let data = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            let c = pk.encrypt_bytes(rng, &data).unwrap();
+            // the tag of XChaCha20-Poly1305 has 16 bytes
+            assert_eq!(c.dem.len(), len + 16);
+            assert_eq!(sk.decrypt_bytes(&c).unwrap(), data);
+
+            let mut bytes = Vec::new();
+            c.serialize_compressed(&mut bytes).unwrap();
+            let c = BytesCiphertext::<E>::deserialize_compressed(&bytes[..]).unwrap();
+            assert_eq!(sk.decrypt_bytes(&c).unwrap(), data);
+        }
+
+        let c = pk.encrypt_bytes(rng, &[1u8; 200]).unwrap();
+        // under another key
+        let (sk2, _) = key_gen::<E, _>(rng, 3);
+        assert_eq!(sk2.decrypt_bytes(&c), Err(BytesError::Decryption));
+        // modified or truncated
+        let mut c_x = c.clone();
+        c_x.dem[0] ^= 1;
+        assert_eq!(sk.decrypt_bytes(&c_x), Err(BytesError::Decryption));
+        let mut c_x = c.clone();
+        c_x.dem.pop();
+        assert_eq!(sk.decrypt_bytes(&c_x), Err(BytesError::Decryption));
+        // the bytes under the kem of another ciphertext
+        let c2 = pk.encrypt_bytes(rng, &[1u8; 200]).unwrap();
+        let c_x = BytesCiphertext {
+            kem: c2.kem,
+            dem: c.dem.clone(),
+        };
+        assert_eq!(sk.decrypt_bytes(&c_x), Err(BytesError::Decryption));
+
+        assert_eq!(
+            pk.encrypt_bytes(rng, &vec![0; MAX_MESSAGE_BYTES + 1]).err(),
+            Some(BytesError::TooLong {
+                len: MAX_MESSAGE_BYTES + 1,
+                max: MAX_MESSAGE_BYTES
+            })
+        );
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let rng = &mut ark_std::test_rng();
+        let n = 3;
+        let (sk, pk) = key_gen::<E, _>(rng, n);
+        let (shares, vks, _) = threshold::key_gen::<E, _>(rng, n, 2, 3).unwrap();
+        let m = (0..n).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let c = pk.encrypt(rng, &m).unwrap();
+
+        // messages of the wrong dimension
+        for len in [0, 1, n - 1, n + 1, 2 * n + 5] {
+            let m = (0..len).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+            let err = RccaError::MessageDimension {
+                expected: n,
+                actual: len,
+            };
+            assert_eq!(pk.encrypt(rng, &m).err(), Some(err));
+            assert_eq!(pk.encrypt_compact(rng, &m).err(), Some(err));
+            assert_eq!(pk.encrypt_labelled(rng, &m, b"").err(), Some(err));
+        }
+
+        // ciphertexts with truncated or padded vectors
+        for _ in 0..200 {
+            let mut c_x = c.clone();
+            let len = rng.gen_range(0..2 * n + 4);
+            let expected = match rng.gen_range(0..3) {
+                0 => {
+                    c_x.c.resize(len, G1::rand(rng));
+                    if len == n + 2 {
+                        continue;
+                    }
+                    RccaError::CiphertextDimension {
+                        expected: n + 2,
+                        actual: len,
+                    }
+                }
+                1 => {
+                    c_x.cpf_ps.resize(len, c.cpf_b.clone());
+                    if len == n + 1 {
+                        continue;
+                    }
+                    RccaError::MalformedCiphertext
+                }
+                _ => {
+                    c_x.cpf_fgh.resize(len, c.cpf_b.clone());
+                    if len == n + 2 {
+                        continue;
+                    }
+                    RccaError::MalformedCiphertext
+                }
+            };
+
+            assert_eq!(c_x.check_proofs(&pk), Err(expected));
+            assert_eq!(sk.decrypt(&c_x), Err(expected));
+            assert_eq!(sk.decrypt_with_proof(rng, &c_x).err(), Some(expected));
+            assert_eq!(pk.verify(&m, &c_x), Err(expected));
+            assert!(shares[0].partial_decrypt(rng, &c_x).is_err());
+            assert!(threshold::combine(2, &pk, &vks, &c_x, &[]).is_err());
+        }
+
+        // compact ciphertexts with truncated or padded vectors, or with another c_0 or c_1
+        let c_compact = pk.encrypt_compact(rng, &m).unwrap();
+        for len in [0, 1, n + 1, n + 3, 2 * n + 5] {
+            let mut c_x = c_compact.clone();
+            c_x.c.resize(len, G1::rand(rng));
+            let err = RccaError::CiphertextDimension {
+                expected: n + 2,
+                actual: len,
+            };
+            assert_eq!(sk.decrypt_compact(&c_x), Err(err));
+        }
+        for i in [0, 1] {
+            let mut c_x = c_compact.clone();
+            c_x.c[i] = G1::rand(rng);
+            assert_eq!(sk.decrypt_compact(&c_x), Err(RccaError::InvalidProof));
+        }
+        let (sk2, _) = key_gen::<E, _>(rng, n);
+        assert_eq!(
+            sk2.decrypt_compact(&c_compact),
+            Err(RccaError::InvalidProof)
+        );
+
+        // a ciphertext under a key of another dimension
+        let (sk_0, pk_0) = key_gen::<E, _>(rng, 0);
+        let err = RccaError::CiphertextDimension {
+            expected: 2,
+            actual: n + 2,
+        };
+        assert_eq!(sk_0.decrypt(&c), Err(err));
+        assert_eq!(sk_0.decrypt_compact(&c_compact), Err(err));
+        let c_0 = pk_0.encrypt(rng, &[]).unwrap();
+        assert_eq!(sk_0.decrypt(&c_0).unwrap(), vec![]);
+        assert!(matches!(
+            sk.decrypt(&c_0),
+            Err(RccaError::CiphertextDimension { .. })
+        ));
+
+        assert_eq!(
+            threshold::key_gen::<E, _>(rng, n, 0, 3).err(),
+            Some(RccaError::InvalidThreshold)
+        );
+        assert_eq!(
+            threshold::key_gen::<E, _>(rng, n, 4, 3).err(),
+            Some(RccaError::InvalidThreshold)
+        );
+    }
+
+    #[test]
+    fn test_vector_layout_across_dimensions() {
+        let rng = &mut ark_std::test_rng();
+        for n in 1..=32 {
+            let (sk, pk) = key_gen::<E, _>(rng, n);
+            let layout = pk.layout();
+            assert_eq!(pk.lhsps_v1.message(), layout.v1::<E>(pk.f, pk.g));
+            assert_eq!(pk.lhsps_v2.message(), layout.v2::<E>(&pk.h).unwrap());
+            assert_eq!(pk.lhsps_v3.message(), layout.v3::<E>(pk.f));
+            let m = (0..n).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+
+            // the full ciphertext
+            let c = pk.encrypt(rng, &m).unwrap();
+            assert_eq!(c.c.len(), layout.ciphertext_len());
+            assert!(c.check_proofs(&pk).is_ok());
+            assert_eq!(sk.decrypt(&c).unwrap(), m);
+
+            // the proof of v does not verify against a vector of another dimension
+            let v = layout.v::<E>(&c.c).unwrap();
+            assert!(OneTime::check_proof_zk(
+                &pk.lhsps_vk,
+                &pk.crs,
+                &c.cpf_v,
+                &(),
+                &v
+            ));
+            assert!(!OneTime::check_proof_zk(
+                &pk.lhsps_vk,
+                &pk.crs,
+                &c.cpf_v,
+                &(),
+                &v[..v.len() - 1]
+            ));
+
+            // the compact ciphertext
+            let c = pk.encrypt_compact(rng, &m).unwrap();
+            assert_eq!(c.c.len(), layout.ciphertext_len());
+            assert!(c.check_proofs(&pk).is_ok());
+            assert_eq!(sk.decrypt_compact(&c).unwrap(), m);
+        }
+
+        // with the tagged LHSPS
+        for n in [1, 5] {
+            let (sk, pk) = key_gen_with::<E, Tagged, _>(rng, n);
+            let m = (0..n).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+            let c = pk.encrypt_compact(rng, &m).unwrap();
+            assert!(c.check_proofs(&pk).is_ok());
+            assert_eq!(sk.decrypt_compact(&c).unwrap(), m);
+        }
+    }
 }
@@ -0,0 +1,274 @@
+//! This module implements distributed decryption for the RCCA encryption scheme.
+//!
+//! Every alpha_i of the decryption key is Shamir-shared among the servers. Each server checks
+//! the proofs of the ciphertext independently and returns the partial decryptions c_1^(alpha_i share)
+//! with Chaum-Pedersen proofs of correctness. A combiner recovers the plaintext vector from any
+//! t valid partial decryptions.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Zero;
+use ark_std::rand::RngCore;
+use std::ops::{Mul, Neg};
+
+use crate::dleq::DleqProof;
+use crate::lhsps::{LinearlyHomomorphicSignature, OneTime};
+use crate::shamir;
+
+use super::{ciphertext::Ciphertext, encrypt_key::EncryptKey, RccaError};
+
+const DOMAIN: &[u8] = b"transferable-ecash/rcca/threshold";
+
+/// A share of the decryption key alpha = (alpha_1, ..., alpha_n) held by one server.
+#[derive(Clone)]
+pub struct KeyShare<E: Pairing, S: LinearlyHomomorphicSignature<E> = OneTime> {
+    // used for proof verification in partial decryption.
+    pub(crate) enc_key: EncryptKey<E, S>,
+
+    // the evaluation point of the share, starting from 1.
+    pub(crate) index: u64,
+    pub(crate) alpha: Vec<E::ScalarField>,
+}
+
+/// The public verification key of a key share, i.e. (g^alpha_1, ..., g^alpha_n) of the shares.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ShareVerifyKey<E: Pairing> {
+    pub(crate) index: u64,
+    pub(crate) vk: Vec<E::G1Affine>,
+}
+
+/// The partial decryptions (c_1^alpha_1, ..., c_1^alpha_n) of the shares, with proofs of correctness.
+#[derive(Clone)]
+pub struct PartialDecryption<E: Pairing> {
+    pub(crate) index: u64,
+    pub(crate) d: Vec<E::G1Affine>,
+    pub(crate) proofs: Vec<DleqProof<E>>,
+}
+
+/// Generates a t-of-`servers` threshold key pair for the RCCA encryption scheme of dimension n.
+///
+/// It outputs the key shares, their verification keys and the encryption key.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_ec::pairing::Pairing;
+/// use ark_std::{test_rng, UniformRand};
+/// use transferable_ecash::rcca::threshold;
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type G1 = <E as Pairing>::G1Affine;
+///
+/// let rng = &mut test_rng();
+/// let (shares, vks, ek) = threshold::key_gen::<E, _>(rng, 3, 2, 3).unwrap();
+/// let m: Vec<G1> = (0..3).map(|_| G1::rand(rng)).collect();
+/// let c = ek.encrypt(rng, &m).unwrap();
+///
+/// let pds = vec![
+///     shares[1].partial_decrypt(rng, &c).unwrap(),
+///     shares[2].partial_decrypt(rng, &c).unwrap(),
+/// ];
+/// let m_d = threshold::combine(2, &ek, &vks, &c, &pds).unwrap();
+/// assert_eq!(m, m_d);
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn key_gen<E: Pairing, R: RngCore>(
+    rng: &mut R,
+    n: usize,
+    t: usize,
+    servers: usize,
+) -> Result<(Vec<KeyShare<E>>, Vec<ShareVerifyKey<E>>, EncryptKey<E>), RccaError> {
+    key_gen_with::<E, OneTime, R>(rng, n, t, servers)
+}
+
+/// Same as `key_gen`, but with the LHSPS implementation `S`.
+#[allow(clippy::type_complexity)]
+pub fn key_gen_with<E: Pairing, S: LinearlyHomomorphicSignature<E>, R: RngCore>(
+    rng: &mut R,
+    n: usize,
+    t: usize,
+    servers: usize,
+) -> Result<
+    (
+        Vec<KeyShare<E, S>>,
+        Vec<ShareVerifyKey<E>>,
+        EncryptKey<E, S>,
+    ),
+    RccaError,
+> {
+    if t == 0 || t > servers {
+        return Err(RccaError::InvalidThreshold);
+    }
+
+    // the dealer's key is dropped after sharing.
+    let (dk, enc_key) = super::key_gen_with::<E, S, _>(rng, n);
+
+    // shares[j][i] is the share of alpha_i for server j.
+    let mut alphas = vec![Vec::with_capacity(n); servers];
+    for alpha_i in dk.alpha.iter() {
+        shamir::split(rng, *alpha_i, t, servers)
+            .into_iter()
+            .zip(alphas.iter_mut())
+            .for_each(|(share, alpha)| alpha.push(share));
+    }
+
+    let shares: Vec<KeyShare<E, S>> = (1..=servers as u64)
+        .zip(alphas)
+        .map(|(index, alpha)| KeyShare {
+            enc_key: enc_key.clone(),
+            index,
+            alpha,
+        })
+        .collect();
+    let vks = shares.iter().map(|share| share.verify_key()).collect();
+
+    Ok((shares, vks, enc_key))
+}
+
+impl<E: Pairing, S: LinearlyHomomorphicSignature<E>> KeyShare<E, S> {
+    /// Returns the public verification key of this share.
+    pub fn verify_key(&self) -> ShareVerifyKey<E> {
+        let g = self.enc_key.g;
+        ShareVerifyKey {
+            index: self.index,
+            vk: self.alpha.iter().map(|a| g.mul(a).into()).collect(),
+        }
+    }
+
+    /// Checks all proofs of the ciphertext, and computes the partial decryptions
+    /// c_1^alpha_i of the shares with proofs of correctness.
+    pub fn partial_decrypt<R: RngCore>(
+        &self,
+        rng: &mut R,
+        c: &Ciphertext<E>,
+    ) -> Result<PartialDecryption<E>, RccaError> {
+        c.check_proofs(&self.enc_key)?;
+
+        let g = self.enc_key.g;
+        let c1 = c.c[1];
+        let (d, proofs) = self
+            .alpha
+            .iter()
+            .map(|a| {
+                let d_i = c1.mul(a).into();
+                // log_g(g^alpha_i) = log_c1(d_i)
+                let pf = DleqProof::prove(rng, DOMAIN, (g, g.mul(a).into()), (c1, d_i), *a);
+                (d_i, pf)
+            })
+            .unzip();
+
+        Ok(PartialDecryption {
+            index: self.index,
+            d,
+            proofs,
+        })
+    }
+}
+
+impl<E: Pairing> ShareVerifyKey<E> {
+    /// Checks that the partial decryption is correctly computed from the key share of this verification key.
+    pub fn verify<S: LinearlyHomomorphicSignature<E>>(
+        &self,
+        enc_key: &EncryptKey<E, S>,
+        c: &Ciphertext<E>,
+        pd: &PartialDecryption<E>,
+    ) -> bool {
+        if pd.index != self.index
+            || c.c.len() < 2
+            || pd.d.len() != self.vk.len()
+            || pd.proofs.len() != self.vk.len()
+        {
+            return false;
+        }
+
+        self.vk
+            .iter()
+            .zip(pd.d.iter().zip(&pd.proofs))
+            .all(|(vk_i, (d_i, pf))| pf.verify(DOMAIN, (enc_key.g, *vk_i), (c.c[1], *d_i)))
+    }
+}
+
+/// Checks all proofs of the ciphertext, and combines the partial decryptions into the plaintext vector.
+///
+/// Partial decryptions that do not verify against `vks` are ignored. It fails if there are
+/// less than `t` valid partial decryptions from distinct servers.
+pub fn combine<E: Pairing, S: LinearlyHomomorphicSignature<E>>(
+    t: usize,
+    enc_key: &EncryptKey<E, S>,
+    vks: &[ShareVerifyKey<E>],
+    c: &Ciphertext<E>,
+    pds: &[PartialDecryption<E>],
+) -> Result<Vec<E::G1Affine>, RccaError> {
+    if t == 0 {
+        return Err(RccaError::InvalidThreshold);
+    }
+    c.check_proofs(enc_key)?;
+
+    let mut valid: Vec<&PartialDecryption<E>> = Vec::new();
+    for pd in pds {
+        if valid.len() == t {
+            break;
+        }
+        if valid.iter().any(|v| v.index == pd.index) {
+            continue;
+        }
+        if vks.iter().any(|vk| vk.verify(enc_key, c, pd)) {
+            valid.push(pd);
+        }
+    }
+    if valid.len() < t {
+        return Err(RccaError::NotEnoughShares);
+    }
+
+    // c_1^alpha_i = Π (c_1^alpha_i share)^lambda
+    let indices: Vec<u64> = valid.iter().map(|pd| pd.index).collect();
+    let mut d = vec![E::G1::zero(); enc_key.h.len()];
+    for pd in valid {
+        let lambda = shamir::lagrange_at_zero::<E::ScalarField>(pd.index, &indices);
+        d.iter_mut()
+            .zip(&pd.d)
+            .for_each(|(acc, d_i)| *acc += d_i.mul(lambda));
+    }
+
+    // M_i = c_i+1 / c_1^alpha_i
+    Ok(c.c[2..]
+        .iter()
+        .zip(d)
+        .map(|(ci, di)| (ci.into_group() + di.neg()).into_affine())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand};
+
+    use super::{combine, key_gen};
+
+    type E = ark_bls12_381::Bls12_381;
+    type G1 = <E as Pairing>::G1Affine;
+
+    #[test]
+    fn test_threshold_decrypt() {
+        let rng = &mut test_rng();
+        let (shares, vks, ek) = key_gen::<E, _>(rng, 3, 2, 3).unwrap();
+        let m = (0..3).map(|_| G1::rand(rng)).collect::<Vec<_>>();
+        let c = ek.encrypt(rng, &m).unwrap();
+
+        let pds = shares
+            .iter()
+            .map(|share| share.partial_decrypt(rng, &c).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(combine(2, &ek, &vks, &c, &pds[..2]).unwrap(), m);
+        assert_eq!(combine(2, &ek, &vks, &c, &pds[1..]).unwrap(), m);
+
+        // not enough shares
+        assert!(combine(2, &ek, &vks, &c, &pds[..1]).is_err());
+
+        // a tampered partial decryption is rejected
+        let mut bad = pds[0].clone();
+        bad.d[1] = G1::rand(rng);
+        assert!(!vks[0].verify(&ek, &c, &bad));
+        assert!(combine(2, &ek, &vks, &c, &[bad, pds[1].clone()]).is_err());
+    }
+}
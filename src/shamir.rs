@@ -0,0 +1,58 @@
+//! This module provides Shamir secret sharing of scalars for internal use.
+
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+
+/// Splits the secret into n shares f(1), ..., f(n) of a random polynomial f of degree t-1 with f(0) = secret.
+pub(crate) fn split<F: PrimeField, R: RngCore>(
+    rng: &mut R,
+    secret: F,
+    t: usize,
+    n: usize,
+) -> Vec<F> {
+    let mut coeffs = vec![secret];
+    coeffs.extend((1..t).map(|_| F::rand(rng)));
+    (1..=n as u64)
+        .map(|i| {
+            let x = F::from(i);
+            // Horner's method
+            coeffs.iter().rev().fold(F::zero(), |acc, a| acc * x + a)
+        })
+        .collect()
+}
+
+/// Lagrange coefficient of `i` for interpolation at 0 over the distinct evaluation points `indices`.
+pub(crate) fn lagrange_at_zero<F: PrimeField>(i: u64, indices: &[u64]) -> F {
+    let xi = F::from(i);
+    indices
+        .iter()
+        .filter(|j| **j != i)
+        .fold(F::one(), |acc, j| {
+            let xj = F::from(*j);
+            acc * xj * (xj - xi).inverse().expect("indices are distinct")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::pairing::Pairing;
+    use ark_std::{test_rng, UniformRand, Zero};
+
+    use super::{lagrange_at_zero, split};
+
+    type Fr = <ark_bls12_381::Bls12_381 as Pairing>::ScalarField;
+
+    #[test]
+    fn test_reconstruct() {
+        let rng = &mut test_rng();
+        let secret = Fr::rand(rng);
+        let shares = split(rng, secret, 3, 5);
+
+        let indices = [2u64, 4, 5];
+        let recovered = indices.iter().fold(Fr::zero(), |acc, i| {
+            acc + shares[*i as usize - 1] * lagrange_at_zero::<Fr>(*i, &indices)
+        });
+        assert_eq!(secret, recovered);
+    }
+}
@@ -0,0 +1,566 @@
+//! This module implements the user tracing of a deposited coin.
+//!
+//! The bank holds the tracing key, i.e. the decryption key of the scheme E, and a registry of the
+//! identities g1^sk of the users. Every hop of a coin encrypts the identity of its holder, so the
+//! trace decrypts the hops and matches the identities with the registry. A `TraceProof` shows a judge
+//! that a particular user held the coin, without revealing the other hops.
+//!
+//! In reverse, the `UserTracer` indexes the deposited coins by the tracing tags e(g1^sk, g) = e(g1, pk)
+//! of their holders, so the coins of a user are found from the public key alone. A coin is only
+//! indexed if it passes `Coin::check`, the RCCA ciphertext of every hop decrypts to its serial number,
+//! and the identity proof of every hop verifies against the registered public key of its holder.
+
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::RngCore;
+use std::collections::HashMap;
+
+use crate::coin::{Coin, CoinContext};
+use crate::double_spending::public_key::PublicKey;
+use crate::encrypt_e::{
+    self,
+    decrypt_key::DecryptKey,
+    decryption_proof::{verify_decryption, DecryptionProof},
+    encrypt_key::EncryptKey,
+};
+use crate::params::Params;
+use crate::rcca;
+
+/// The tracing key of the bank, i.e. a decryption key of the scheme E built on the generator g1 of `Params`.
+pub struct TracingKey<E: Pairing> {
+    pub(crate) dk: DecryptKey<E>,
+}
+
+impl<E: Pairing> TracingKey<E> {
+    /// Generates a tracing key and its encryption key, which the holders use for the hops.
+    pub fn generate<R: RngCore>(rng: &mut R, params: &Params<E>) -> (Self, EncryptKey<E>) {
+        let (dk, ek) = encrypt_e::key_gen_with_params(rng, params);
+        (Self { dk }, ek)
+    }
+
+    /// Wraps a decryption key. It fails if the key is not built on the generator g1 of `params`.
+    pub fn new(params: &Params<E>, dk: DecryptKey<E>) -> Result<Self, ()> {
+        if !dk.encrypt_key().check_params(params) {
+            return Err(());
+        }
+        Ok(Self { dk })
+    }
+
+    /// Returns the encryption key of the tracing key.
+    pub fn encrypt_key(&self) -> EncryptKey<E> {
+        self.dk.encrypt_key()
+    }
+
+    /// Proves that the holder of the hop `hop_index` of the coin has the decrypted identity.
+    /// It returns `None` if the coin has no such hop.
+    pub fn prove_trace<R: RngCore>(
+        &self,
+        rng: &mut R,
+        coin: &Coin<E>,
+        hop_index: usize,
+    ) -> Option<TraceProof<E>> {
+        let hop = coin.hops.get(hop_index)?;
+        let ((identity, _), proof) = self.dk.decrypt_with_proof(rng, &hop.identity);
+        Some(TraceProof {
+            hop_index,
+            identity,
+            proof,
+        })
+    }
+}
+
+/// The public keys of the registered users, indexed by their identities g1^sk.
+pub struct UserRegistry<E: Pairing> {
+    pub(crate) users: HashMap<E::G1Affine, PublicKey<E>>,
+}
+
+impl<E: Pairing> Default for UserRegistry<E> {
+    fn default() -> Self {
+        Self {
+            users: HashMap::new(),
+        }
+    }
+}
+
+impl<E: Pairing> UserRegistry<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the user with the public key `pk` and the identity g1^sk (see `SecretKey::identity`).
+    /// It fails if the identity is not consistent with `pk`.
+    pub fn register(
+        &mut self,
+        params: &Params<E>,
+        pk: PublicKey<E>,
+        identity: E::G1Affine,
+    ) -> Result<(), ()> {
+        if !pk.is_identity(params, identity) {
+            return Err(());
+        }
+        self.users.insert(identity, pk);
+        Ok(())
+    }
+
+    /// Returns the public key of the user with the identity.
+    pub fn lookup(&self, identity: &E::G1Affine) -> Option<&PublicKey<E>> {
+        self.users.get(identity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+}
+
+/// Traces the coin, and outputs the hop indices with the public keys of the registered users who held the coin.
+///
+/// Hops of unregistered identities are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::{test_rng, UniformRand};
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use transferable_ecash::coin::{Coin, CoinContext, CoinSecret, Hop};
+/// use transferable_ecash::lhsps::tagged;
+/// use transferable_ecash::tracing::{trace_coin, TracingKey, UserRegistry};
+/// use transferable_ecash::{double_spending, params::Params, rcca};
+///
+/// type E = ark_bls12_381::Bls12_381;
+/// type Fr = <E as ark_ec::pairing::Pairing>::ScalarField;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (tk, _) = TracingKey::generate(rng, &params);
+/// let (_, deposit_key) = rcca::key_gen::<E, _>(rng, 2);
+/// let (bank_sk, bank_vk) = tagged::setup::<E, _>(rng, 2);
+/// let crs = CRS::generate_crs(rng);
+/// let ctx = CoinContext::new(params.clone(), crs, tk.encrypt_key(), deposit_key, bank_vk).unwrap();
+///
+/// let mut registry = UserRegistry::new();
+/// let (sk, pk) = double_spending::key_gen(rng, &params);
+/// registry.register(&params, pk.clone(), sk.identity(&params)).unwrap();
+///
+/// let n = Fr::rand(rng);
+/// let hop = Hop::new(rng, &ctx, &sk, n).unwrap();
+/// let message = hop.bank_message(sk.identity(&params));
+/// let bank_sig = bank_sk.sign(rng, &hop.bank_tag(), &message).unwrap();
+/// let coin = Coin::new(rng, &ctx, &sk, &bank_sig, hop, CoinSecret::new(n)).unwrap();
+///
+/// let trace = trace_coin(&coin, &tk, &registry);
+/// assert!(trace == vec![(0, pk.clone())]);
+///
+/// let pf = tk.prove_trace(rng, &coin, 0).unwrap();
+/// assert!(pf.verify(&ctx, &coin, &pk));
+/// ```
+pub fn trace_coin<E: Pairing>(
+    coin: &Coin<E>,
+    tracing_key: &TracingKey<E>,
+    registry: &UserRegistry<E>,
+) -> Vec<(usize, PublicKey<E>)> {
+    coin.hops
+        .iter()
+        .enumerate()
+        .filter_map(|(i, hop)| {
+            let (identity, _) = tracing_key.dk.decrypt(&hop.identity);
+            registry.lookup(&identity).map(|pk| (i, pk.clone()))
+        })
+        .collect()
+}
+
+/// A proof that a user held a coin, i.e. the hop `hop_index` of the coin decrypts to the identity of the user.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TraceProof<E: Pairing> {
+    pub(crate) hop_index: usize,
+    pub(crate) identity: E::G1Affine,
+    pub(crate) proof: DecryptionProof<E>,
+}
+
+impl<E: Pairing> TraceProof<E> {
+    pub fn hop_index(&self) -> usize {
+        self.hop_index
+    }
+
+    /// Verifies that the user with the public key `pk` held the coin, under the tracing key of `ctx`.
+    ///
+    /// The coin must pass `Coin::check`, and the hop must pass `Hop::verify` against `pk`, so a coin
+    /// fabricated from the identity of the user is rejected.
+    pub fn verify(&self, ctx: &CoinContext<E>, coin: &Coin<E>, pk: &PublicKey<E>) -> bool {
+        let hop = match coin.hops.get(self.hop_index) {
+            Some(hop) => hop,
+            None => return false,
+        };
+        coin.check(ctx)
+            && hop.verify(ctx, pk)
+            && pk.is_identity(&ctx.params, self.identity)
+            && verify_decryption(
+                &ctx.tracing_key,
+                &hop.identity,
+                (self.identity, E::G1Affine::zero()),
+                &self.proof,
+            )
+    }
+}
+
+// The tracing tag e(g1^sk, g) = e(g1, pk) of a user, which the bank derives from either the decrypted
+// identity of a hop or the public key, without the registry.
+fn tracing_tag<E: Pairing>(params: &Params<E>, pk: &PublicKey<E>) -> Vec<u8> {
+    tag_bytes(E::pairing(params.g1, pk.pk))
+}
+
+fn tracing_tag_of_identity<E: Pairing>(params: &Params<E>, identity: E::G1Affine) -> Vec<u8> {
+    tag_bytes(E::pairing(identity, params.g))
+}
+
+fn tag_bytes<E: Pairing>(tag: PairingOutput<E>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    tag.serialize_compressed(&mut bytes)
+        .expect("serialization to vec should not fail");
+    bytes
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DepositError {
+    /// The coin fails `Coin::check`.
+    InvalidCoin,
+    /// The RCCA ciphertext of the hop with the index does not decrypt to its serial number.
+    InvalidDeposit(usize),
+    /// The holder of the hop with the index is not registered.
+    UnknownHolder(usize),
+    /// The identity proof of the hop with the index does not verify against the public key of its holder.
+    InvalidIdentity(usize),
+}
+
+/// The store of deposited coins, indexed by the tracing tags of their holders.
+///
+/// Every hop is decrypted once on deposit, so `trace_user` only looks up the index.
+pub struct UserTracer<E: Pairing> {
+    pub(crate) params: Params<E>,
+    pub(crate) tracing_key: TracingKey<E>,
+    // the RCCA decryption key of the bank, for the serial numbers of the hops.
+    pub(crate) deposit_key: rcca::decrypt_key::DecryptKey<E>,
+    pub(crate) coins: Vec<Coin<E>>,
+    // tracing tag -> (coin id, hop index)
+    pub(crate) index: HashMap<Vec<u8>, Vec<(usize, usize)>>,
+}
+
+impl<E: Pairing> UserTracer<E> {
+    pub fn new(
+        params: &Params<E>,
+        tracing_key: TracingKey<E>,
+        deposit_key: rcca::decrypt_key::DecryptKey<E>,
+    ) -> Self {
+        Self {
+            params: params.clone(),
+            tracing_key,
+            deposit_key,
+            coins: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Checks, stores and indexes a deposited coin, and returns its id.
+    ///
+    /// It fails if the coin does not pass `Coin::check`, if the RCCA ciphertext of a hop does not
+    /// decrypt to its serial number (see `Hop::check_deposit`), or if the holder of a hop is not in the
+    /// registry or does not match the identity proof of the hop. Nothing is stored on failure.
+    pub fn deposit(
+        &mut self,
+        ctx: &CoinContext<E>,
+        registry: &UserRegistry<E>,
+        coin: Coin<E>,
+    ) -> Result<usize, DepositError> {
+        if !coin.check(ctx) {
+            return Err(DepositError::InvalidCoin);
+        }
+        let mut tags = Vec::with_capacity(coin.hops.len());
+        for (hop_index, hop) in coin.hops.iter().enumerate() {
+            if !hop.check_deposit(&self.deposit_key) {
+                return Err(DepositError::InvalidDeposit(hop_index));
+            }
+            let (identity, _) = self.tracing_key.dk.decrypt(&hop.identity);
+            let pk = registry
+                .lookup(&identity)
+                .ok_or(DepositError::UnknownHolder(hop_index))?;
+            if !hop.verify(ctx, pk) {
+                return Err(DepositError::InvalidIdentity(hop_index));
+            }
+            tags.push(tracing_tag_of_identity(&self.params, identity));
+        }
+
+        let coin_id = self.coins.len();
+        for (hop_index, tag) in tags.into_iter().enumerate() {
+            self.index
+                .entry(tag)
+                .or_default()
+                .push((coin_id, hop_index));
+        }
+        self.coins.push(coin);
+        Ok(coin_id)
+    }
+
+    /// Returns the deposited coin with the id.
+    pub fn coin(&self, coin_id: usize) -> Option<&Coin<E>> {
+        self.coins.get(coin_id)
+    }
+
+    /// Finds the deposited coins held by the user with the public key `pk`, with proofs that the user
+    /// held them, which are verified by `TraceProof::verify` against `pk`. Every coin is returned once,
+    /// in the order of deposit, with a proof for each hop of the user.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ark_std::{test_rng, UniformRand};
+    /// use groth_sahai::{AbstractCrs, CRS};
+    /// use transferable_ecash::coin::{Coin, CoinContext, CoinSecret, Hop};
+    /// use transferable_ecash::lhsps::tagged;
+    /// use transferable_ecash::tracing::{TracingKey, UserRegistry, UserTracer};
+    /// use transferable_ecash::{double_spending, params::Params, rcca};
+    ///
+    /// type E = ark_bls12_381::Bls12_381;
+    /// type Fr = <E as ark_ec::pairing::Pairing>::ScalarField;
+    ///
+    /// let rng = &mut test_rng();
+    /// let params = Params::<E>::rand(rng);
+    /// let (tk, _) = TracingKey::generate(rng, &params);
+    /// let (deposit_dk, deposit_key) = rcca::key_gen::<E, _>(rng, 2);
+    /// let (bank_sk, bank_vk) = tagged::setup::<E, _>(rng, 2);
+    /// let crs = CRS::generate_crs(rng);
+    /// let ctx = CoinContext::new(params.clone(), crs, tk.encrypt_key(), deposit_key, bank_vk).unwrap();
+    /// let mut tracer = UserTracer::new(&params, tk, deposit_dk);
+    ///
+    /// let mut registry = UserRegistry::new();
+    /// let (sk, pk) = double_spending::key_gen(rng, &params);
+    /// registry.register(&params, pk.clone(), sk.identity(&params)).unwrap();
+    /// let n = Fr::rand(rng);
+    /// let hop = Hop::new(rng, &ctx, &sk, n).unwrap();
+    /// let message = hop.bank_message(sk.identity(&params));
+    /// let bank_sig = bank_sk.sign(rng, &hop.bank_tag(), &message).unwrap();
+    /// let coin = Coin::new(rng, &ctx, &sk, &bank_sig, hop, CoinSecret::new(n)).unwrap();
+    /// let coin_id = tracer.deposit(&ctx, &registry, coin.without_secret()).unwrap();
+    ///
+    /// let coins = tracer.trace_user(rng, &pk);
+    /// assert_eq!(coins.len(), 1);
+    /// let (id, coin, pfs) = &coins[0];
+    /// assert_eq!(*id, coin_id);
+    /// assert_eq!(pfs.len(), 1);
+    /// assert!(pfs[0].verify(&ctx, coin, &pk));
+    /// ```
+    pub fn trace_user<R: RngCore>(
+        &self,
+        rng: &mut R,
+        pk: &PublicKey<E>,
+    ) -> Vec<(usize, &Coin<E>, Vec<TraceProof<E>>)> {
+        let hits = match self.index.get(&tracing_tag(&self.params, pk)) {
+            Some(hits) => hits,
+            None => return Vec::new(),
+        };
+        // the hits are ordered by coin id, then by hop index
+        let mut found: Vec<(usize, &Coin<E>, Vec<TraceProof<E>>)> = Vec::new();
+        for (coin_id, hop_index) in hits {
+            let coin = &self.coins[*coin_id];
+            let pf = match self.tracing_key.prove_trace(rng, coin, *hop_index) {
+                Some(pf) => pf,
+                None => continue,
+            };
+            match found.last_mut() {
+                Some((id, _, pfs)) if *id == *coin_id => pfs.push(pf),
+                _ => found.push((*coin_id, coin, vec![pf])),
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::test_rng;
+
+    use crate::coin::{
+        deposit_label,
+        tests::{bank_context, coin_held_by, context},
+    };
+    use crate::double_spending;
+    use crate::encrypt_e::{self, identity::encrypt_identity};
+
+    use super::{trace_coin, DepositError, TraceProof, TracingKey, UserRegistry, UserTracer};
+
+    type E = Bls12_381;
+
+    #[test]
+    fn test_trace_coin() {
+        let rng = &mut test_rng();
+        let (dk, bank_sk, ctx) = context(rng);
+        let params = &ctx.params;
+        let tk = TracingKey::new(params, dk).unwrap();
+
+        let users: Vec<_> = (0..4)
+            .map(|_| double_spending::key_gen::<E, _>(rng, params))
+            .collect();
+        let mut registry = UserRegistry::new();
+        // the last user is not registered
+        for (sk, pk) in &users[..3] {
+            registry
+                .register(params, pk.clone(), sk.identity(params))
+                .unwrap();
+        }
+        assert_eq!(registry.len(), 3);
+        // an inconsistent identity is rejected
+        assert!(registry
+            .register(params, users[0].1.clone(), users[1].0.identity(params))
+            .is_err());
+
+        // the coin is held by users 0, 1, 3, 2 and 1
+        let holders: Vec<_> = [0, 1, 3, 2, 1].iter().map(|i| &users[*i].0).collect();
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &holders);
+
+        let trace = trace_coin(&coin, &tk, &registry);
+        let expected = vec![
+            (0, users[0].1.clone()),
+            (1, users[1].1.clone()),
+            (3, users[2].1.clone()),
+            (4, users[1].1.clone()),
+        ];
+        assert!(trace == expected);
+
+        // a judge checks a single hop
+        let pf = tk.prove_trace(rng, &coin, 3).unwrap();
+        assert_eq!(pf.hop_index(), 3);
+        assert!(pf.verify(&ctx, &coin, &users[2].1));
+        assert!(!pf.verify(&ctx, &coin, &users[1].1));
+        let mut pf_moved = pf.clone();
+        pf_moved.hop_index = 1;
+        assert!(!pf_moved.verify(&ctx, &coin, &users[2].1));
+        assert!(tk.prove_trace(rng, &coin, 5).is_none());
+
+        let mut bytes = Vec::new();
+        pf.serialize_compressed(&mut bytes).unwrap();
+        let pf_d = TraceProof::<E>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(pf_d.verify(&ctx, &coin, &users[2].1));
+
+        // a coin fabricated with the identity of user 0, e.g. by the bank which learns the identity on
+        // deposit, has valid identity and decryption proofs, but fails the check of the coin
+        let (sk0, pk0) = &users[0];
+        let (identity, identity_proof) =
+            encrypt_identity(&ctx.tracing_key, params, pk0, sk0.identity(params), rng).unwrap();
+        let mut fake = coin.clone();
+        fake.hops[3].identity = identity;
+        fake.hops[3].identity_proof = identity_proof;
+        assert!(fake.hops[3].verify(&ctx, pk0));
+        let pf_fake = tk.prove_trace(rng, &fake, 3).unwrap();
+        assert!(!pf_fake.verify(&ctx, &fake, pk0));
+
+        // as does a coin without a valid proof of the signature of the bank
+        let mut fake = coin.clone();
+        fake.bank_proof = coin_held_by(rng, &ctx, &bank_sk, &[&users[2].0]).bank_proof;
+        assert!(!pf.verify(&ctx, &fake, &users[2].1));
+
+        // the hops do not trace under another tracing key
+        let (tk2, _) = TracingKey::generate(rng, params);
+        assert!(trace_coin(&coin, &tk2, &registry).is_empty());
+        let (dk_r, _) = encrypt_e::key_gen::<E, _>(rng);
+        assert!(TracingKey::new(params, dk_r).is_err());
+    }
+
+    #[test]
+    fn test_user_tracer() {
+        let rng = &mut test_rng();
+        let (dk, deposit_dk, bank_sk, ctx) = bank_context(rng);
+        let params = &ctx.params;
+        let mut tracer = UserTracer::new(params, TracingKey::new(params, dk).unwrap(), deposit_dk);
+
+        let users: Vec<_> = (0..4)
+            .map(|_| double_spending::key_gen::<E, _>(rng, params))
+            .collect();
+        // the last user is not registered
+        let mut registry = UserRegistry::new();
+        for (sk, pk) in &users[..3] {
+            registry
+                .register(params, pk.clone(), sk.identity(params))
+                .unwrap();
+        }
+        let (u0, u1, u3) = (&users[0].0, &users[1].0, &users[3].0);
+
+        // coin 0: users 0 -> 1, coin 1: users 1 -> 0 -> 1, coin 2: user 0
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[u0, u1]);
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, coin.without_secret()),
+            Ok(0)
+        );
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[u1, u0, u1]);
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, coin.without_secret()),
+            Ok(1)
+        );
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[u0]);
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, coin.without_secret()),
+            Ok(2)
+        );
+
+        // every coin is returned once, with the hops of the user
+        let found = tracer.trace_user(rng, &users[1].1);
+        let hops: Vec<_> = found
+            .iter()
+            .map(|(id, _, pfs)| (*id, pfs.iter().map(|pf| pf.hop_index()).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(hops, vec![(0, vec![1]), (1, vec![0, 2])]);
+        for (_, coin, pfs) in &found {
+            for pf in pfs {
+                assert!(pf.verify(&ctx, coin, &users[1].1));
+                assert!(!pf.verify(&ctx, coin, &users[0].1));
+            }
+        }
+
+        let found = tracer.trace_user(rng, &users[0].1);
+        let ids: Vec<_> = found.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert!(tracer.trace_user(rng, &users[2].1).is_empty());
+        assert!(tracer.coin(2).is_some() && tracer.coin(3).is_none());
+
+        // rejected deposits are not stored
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[u0, u1, u0]);
+        let mut bad = coin.without_secret();
+        bad.hops.swap(1, 2);
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, bad),
+            Err(DepositError::InvalidCoin)
+        );
+        let mut bad = coin.without_secret();
+        let hop = &mut bad.hops[2];
+        let other = &coin.hops[0].sn;
+        hop.deposit = ctx
+            .deposit_key
+            .encrypt_compact_labelled(
+                rng,
+                &[other.m, other.n],
+                &deposit_label(&hop.sn, &hop.identity),
+            )
+            .unwrap();
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, bad),
+            Err(DepositError::InvalidDeposit(2))
+        );
+        let mut bad = coin.without_secret();
+        bad.hops[1].identity_proof = bad.hops[0].identity_proof;
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, bad),
+            Err(DepositError::InvalidIdentity(1))
+        );
+        let coin = coin_held_by(rng, &ctx, &bank_sk, &[u0, u3]);
+        assert_eq!(
+            tracer.deposit(&ctx, &registry, coin.without_secret()),
+            Err(DepositError::UnknownHolder(1))
+        );
+        assert!(tracer.coin(3).is_none());
+        assert_eq!(tracer.trace_user(rng, &users[0].1).len(), 3);
+    }
+}
@@ -0,0 +1,966 @@
+//! This module implements the wallet of a user, which owns the double-spending secret key, draws the
+//! nonces n of the serial numbers and tags, and persists the coins in a `Storage`.
+//!
+//! A nonce must never be used for two different coins: two tags generated with the same nonce reveal
+//! the secret key of the holder. The wallet records every nonce in the storage before using it, and
+//! draws again if it was already recorded, so that a nonce is issued at most once even across restarts
+//! or with a faulty random number generator. After `MAX_NONCE_ATTEMPTS` recorded draws in a row, the
+//! random number generator is considered broken and no nonce is issued.
+//!
+//! A payment is a three-step protocol:
+//!
+//! 1. the receiver creates a hop to itself with `Wallet::receive_request`,
+//! 2. the payer transfers a coin to the hop with `Wallet::pay`,
+//! 3. the receiver checks and stores the coin with `Wallet::receive`.
+//!
+//! The nonces of the requested hops are kept in the storage until the coin is received, so a
+//! payment can be received after a restart.
+//!
+//! The storage also keeps the secret key of the wallet (see `Wallet::open`) and a counter of the coin
+//! ids, so that the id of a paid coin is never given to another coin.
+
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::rand::RngCore;
+use ark_std::UniformRand;
+use chacha20poly1305::aead::OsRng;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use crate::coin::{Coin, CoinContext, CoinError, CoinSecret, Hop};
+use crate::double_spending::secret_key::SecretKey;
+use crate::key_file::{self, KdfParams, KeyFileError};
+use crate::lhsps::tagged::signature::Signature;
+
+#[derive(Debug)]
+pub enum WalletError {
+    Io(io::Error),
+    Coin(CoinError),
+    /// The wallet does not hold a coin with the id.
+    UnknownCoin(u64),
+    /// The received coin is not valid, or does not end with a hop requested by the wallet.
+    InvalidCoin,
+    /// The bank refused to sign the withdrawal.
+    Withdrawal,
+    /// No fresh nonce was drawn in `MAX_NONCE_ATTEMPTS` attempts.
+    NoFreshNonce,
+    /// The storage holds the secret key of another user.
+    KeyMismatch,
+    /// The storage does not hold a secret key.
+    MissingKey,
+}
+
+/// The maximum number of draws of `Wallet::fresh_nonce` before it gives up.
+pub const MAX_NONCE_ATTEMPTS: usize = 16;
+
+impl From<io::Error> for WalletError {
+    fn from(e: io::Error) -> Self {
+        WalletError::Io(e)
+    }
+}
+
+impl From<CoinError> for WalletError {
+    fn from(e: CoinError) -> Self {
+        WalletError::Coin(e)
+    }
+}
+
+impl From<SerializationError> for WalletError {
+    fn from(e: SerializationError) -> Self {
+        WalletError::Coin(CoinError::Serialization(e))
+    }
+}
+
+/// The persistent storage of a wallet. Coins are stored serialized by `Coin::to_bytes`, with their
+/// secret parts, so the storage must protect them as well as the secret key.
+pub trait Storage {
+    /// Stores the serialized secret key of the wallet.
+    fn store_secret_key(&mut self, sk: &[u8]) -> io::Result<()>;
+
+    /// Returns the serialized secret key stored by `store_secret_key`, if any.
+    fn load_secret_key(&self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Returns the next coin id and advances the counter durably, so that an id is never returned
+    /// twice, even after the coin with the highest id is removed.
+    fn next_coin_id(&mut self) -> io::Result<u64>;
+
+    /// Inserts or replaces the coin with the id.
+    fn store_coin(&mut self, id: u64, coin: &[u8]) -> io::Result<()>;
+
+    fn remove_coin(&mut self, id: u64) -> io::Result<()>;
+
+    /// Returns all stored coins ordered by id.
+    fn load_coins(&self) -> io::Result<Vec<(u64, Vec<u8>)>>;
+
+    /// Records the nonce durably. Returns false if the nonce was already recorded.
+    fn record_nonce(&mut self, nonce: &[u8]) -> io::Result<bool>;
+
+    /// Stores the nonce of a requested hop until the coin is received.
+    fn store_pending(&mut self, nonce: &[u8]) -> io::Result<()>;
+
+    fn remove_pending(&mut self, nonce: &[u8]) -> io::Result<()>;
+
+    /// Returns the nonces of all requested hops which are not received yet.
+    fn load_pending(&self) -> io::Result<Vec<Vec<u8>>>;
+}
+
+/// A storage which keeps everything in memory, e.g. for tests or short-lived wallets.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    pub(crate) secret_key: Option<Vec<u8>>,
+    pub(crate) next_id: u64,
+    pub(crate) coins: BTreeMap<u64, Vec<u8>>,
+    pub(crate) nonces: HashSet<Vec<u8>>,
+    pub(crate) pending: BTreeSet<Vec<u8>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn store_secret_key(&mut self, sk: &[u8]) -> io::Result<()> {
+        self.secret_key = Some(sk.to_vec());
+        Ok(())
+    }
+
+    fn load_secret_key(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.secret_key.clone())
+    }
+
+    fn next_coin_id(&mut self) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        Ok(id)
+    }
+
+    fn store_coin(&mut self, id: u64, coin: &[u8]) -> io::Result<()> {
+        self.coins.insert(id, coin.to_vec());
+        Ok(())
+    }
+
+    fn remove_coin(&mut self, id: u64) -> io::Result<()> {
+        self.coins.remove(&id);
+        Ok(())
+    }
+
+    fn load_coins(&self) -> io::Result<Vec<(u64, Vec<u8>)>> {
+        Ok(self
+            .coins
+            .iter()
+            .map(|(id, coin)| (*id, coin.clone()))
+            .collect())
+    }
+
+    fn record_nonce(&mut self, nonce: &[u8]) -> io::Result<bool> {
+        Ok(self.nonces.insert(nonce.to_vec()))
+    }
+
+    fn store_pending(&mut self, nonce: &[u8]) -> io::Result<()> {
+        self.pending.insert(nonce.to_vec());
+        Ok(())
+    }
+
+    fn remove_pending(&mut self, nonce: &[u8]) -> io::Result<()> {
+        self.pending.remove(nonce);
+        Ok(())
+    }
+
+    fn load_pending(&self) -> io::Result<Vec<Vec<u8>>> {
+        Ok(self.pending.iter().cloned().collect())
+    }
+}
+
+/// A storage in a directory, where the secret key, the coins and the pending nonces are encrypted
+/// under a password in the format of `key_file`. The layout is:
+///
+/// - `secret_key`: the key file of the secret key,
+/// - `coins/<id>.coin`: the key file of the serialized coin with the id,
+/// - `next_id`: the next coin id, in decimal,
+/// - `nonces`: the SHA-256 hashes of the recorded nonces, one hex-encoded hash per line,
+/// - `pending/<hash>`: the key file of each pending nonce, named by its hex-encoded hash.
+///
+/// Files are written to a temporary file and renamed, and nonces are synced to the disk before
+/// `record_nonce` returns. A last line of `nonces` without its newline, left by a crash in
+/// `record_nonce`, is discarded on `open`: its nonce was never issued.
+///
+/// Every key file has its own salt, so every read and write of a key file derives a key with the
+/// `KdfParams` of the storage.
+pub struct FileStorage<E: Pairing> {
+    pub(crate) dir: PathBuf,
+    pub(crate) nonces: HashSet<Vec<u8>>,
+    pub(crate) next_id: u64,
+    password: Zeroizing<Vec<u8>>,
+    kdf: KdfParams,
+    _e: PhantomData<E>,
+}
+
+const SECRET_KEY_FILE: &str = "secret_key";
+const COINS_DIR: &str = "coins";
+const COIN_EXT: &str = "coin";
+const NEXT_ID_FILE: &str = "next_id";
+const NONCES_FILE: &str = "nonces";
+const PENDING_DIR: &str = "pending";
+
+impl<E: Pairing> FileStorage<E> {
+    /// Opens the storage in the directory `dir` with the password, creating it if it does not exist.
+    /// The key files are written with the default `KdfParams`.
+    ///
+    /// It fails if the stored secret key does not decrypt under the password.
+    pub fn open<P: AsRef<Path>>(dir: P, password: &[u8]) -> io::Result<Self> {
+        Self::open_with(dir, password, KdfParams::default())
+    }
+
+    /// Same as `open`, but the key files are written with the given KDF parameters.
+    pub fn open_with<P: AsRef<Path>>(dir: P, password: &[u8], kdf: KdfParams) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(COINS_DIR))?;
+        fs::create_dir_all(dir.join(PENDING_DIR))?;
+
+        let path = dir.join(NONCES_FILE);
+        let mut records = match fs::read(&path) {
+            Ok(records) => records,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        // truncate a torn last record, so that the next record starts on a new line
+        let complete = records
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        if complete < records.len() {
+            let file = fs::OpenOptions::new().write(true).open(&path)?;
+            file.set_len(complete as u64)?;
+            file.sync_all()?;
+            records.truncate(complete);
+        }
+
+        let mut nonces = HashSet::new();
+        for line in records.split(|b| *b == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let hash = std::str::from_utf8(line).ok().and_then(from_hex);
+            nonces.insert(hash.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid nonce record")
+            })?);
+        }
+
+        let mut storage = Self {
+            dir,
+            nonces,
+            next_id: 0,
+            password: Zeroizing::new(password.to_vec()),
+            kdf,
+            _e: PhantomData,
+        };
+        // checks the password
+        storage.load_secret_key()?;
+
+        let next_id = match fs::read_to_string(storage.dir.join(NEXT_ID_FILE)) {
+            Ok(next_id) => next_id
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid next coin id"))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        // the counter never falls behind the stored coins
+        let after_coins = storage.coin_ids()?.last().map_or(0, |id| id + 1);
+        storage.next_id = u64::max(next_id, after_coins);
+        Ok(storage)
+    }
+
+    fn coin_path(&self, id: u64) -> PathBuf {
+        self.dir.join(COINS_DIR).join(format!("{id}.{COIN_EXT}"))
+    }
+
+    fn pending_path(&self, nonce: &[u8]) -> PathBuf {
+        self.dir.join(PENDING_DIR).join(to_hex(&nonce_hash(nonce)))
+    }
+
+    // the ids of the stored coins in increasing order.
+    fn coin_ids(&self) -> io::Result<Vec<u64>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(self.dir.join(COINS_DIR))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(COIN_EXT) {
+                continue;
+            }
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok());
+            if let Some(id) = id {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    // encrypts the secret into a key file under the password of the storage.
+    fn write_secret(&self, path: &Path, secret: &[u8]) -> io::Result<()> {
+        let mut file = Vec::new();
+        key_file::save_encrypted_with::<E, _, _, _>(
+            &mut file,
+            &secret,
+            &self.password,
+            self.kdf,
+            &mut OsRng,
+        )
+        .map_err(key_file_error)?;
+        write_file(path, &file)
+    }
+
+    fn read_secret(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let file = fs::read(path)?;
+        key_file::load_encrypted::<E, Vec<u8>, _>(file.as_slice(), &self.password)
+            .map_err(key_file_error)
+    }
+}
+
+impl<E: Pairing> Storage for FileStorage<E> {
+    fn store_secret_key(&mut self, sk: &[u8]) -> io::Result<()> {
+        self.write_secret(&self.dir.join(SECRET_KEY_FILE), sk)
+    }
+
+    fn load_secret_key(&self) -> io::Result<Option<Vec<u8>>> {
+        match self.read_secret(&self.dir.join(SECRET_KEY_FILE)) {
+            Ok(sk) => Ok(Some(sk)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn next_coin_id(&mut self) -> io::Result<u64> {
+        let id = self.next_id;
+        write_file(
+            &self.dir.join(NEXT_ID_FILE),
+            (id + 1).to_string().as_bytes(),
+        )?;
+        self.next_id = id + 1;
+        Ok(id)
+    }
+
+    fn store_coin(&mut self, id: u64, coin: &[u8]) -> io::Result<()> {
+        self.write_secret(&self.coin_path(id), coin)
+    }
+
+    fn remove_coin(&mut self, id: u64) -> io::Result<()> {
+        match fs::remove_file(self.coin_path(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn load_coins(&self) -> io::Result<Vec<(u64, Vec<u8>)>> {
+        self.coin_ids()?
+            .into_iter()
+            .map(|id| Ok((id, self.read_secret(&self.coin_path(id))?)))
+            .collect()
+    }
+
+    fn record_nonce(&mut self, nonce: &[u8]) -> io::Result<bool> {
+        let hash = nonce_hash(nonce);
+        if self.nonces.contains(&hash) {
+            return Ok(false);
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(NONCES_FILE))?;
+        writeln!(file, "{}", to_hex(&hash))?;
+        file.sync_all()?;
+        self.nonces.insert(hash);
+        Ok(true)
+    }
+
+    fn store_pending(&mut self, nonce: &[u8]) -> io::Result<()> {
+        self.write_secret(&self.pending_path(nonce), nonce)
+    }
+
+    fn remove_pending(&mut self, nonce: &[u8]) -> io::Result<()> {
+        match fs::remove_file(self.pending_path(nonce)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn load_pending(&self) -> io::Result<Vec<Vec<u8>>> {
+        let mut pending = Vec::new();
+        for entry in fs::read_dir(self.dir.join(PENDING_DIR))? {
+            let path = entry?.path();
+            // skips the temporary files
+            if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(from_hex)
+                .is_some()
+            {
+                pending.push(self.read_secret(&path)?);
+            }
+        }
+        pending.sort();
+        Ok(pending)
+    }
+}
+
+// writes the file to a temporary file, which is synced and renamed.
+fn write_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+fn key_file_error(e: KeyFileError) -> io::Error {
+    match e {
+        KeyFileError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")),
+    }
+}
+
+// the nonces are recorded by their hashes, so the record does not reveal the secret parts of the coins.
+fn nonce_hash(nonce: &[u8]) -> Vec<u8> {
+    Sha256::digest(nonce).to_vec()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The wallet of a user.
+///
+/// # Example
+///
+/// ```rust
+/// use ark_std::test_rng;
+/// use groth_sahai::{AbstractCrs, CRS};
+/// use transferable_ecash::coin::CoinContext;
+/// use transferable_ecash::lhsps::tagged;
+/// use transferable_ecash::wallet::{MemoryStorage, Wallet};
+/// use transferable_ecash::{double_spending, encrypt_e, params::Params, rcca};
+///
+/// type E = ark_bls12_381::Bls12_381;
+///
+/// let rng = &mut test_rng();
+/// let params = Params::<E>::rand(rng);
+/// let (_, tracing_key) = encrypt_e::key_gen_with_params(rng, &params);
+/// let (_, deposit_key) = rcca::key_gen::<E, _>(rng, 2);
+/// let (bank_sk, bank_vk) = tagged::setup::<E, _>(rng, 2);
+/// let crs = CRS::generate_crs(rng);
+/// let ctx = CoinContext::new(params, crs, tracing_key, deposit_key, bank_vk).unwrap();
+///
+/// let (sk, _) = double_spending::key_gen(rng, ctx.params());
+/// let mut alice = Wallet::new(sk, MemoryStorage::new()).unwrap();
+/// let (sk, _) = double_spending::key_gen(rng, ctx.params());
+/// let mut bob = Wallet::new(sk, MemoryStorage::new()).unwrap();
+///
+/// let id = alice.withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m)).unwrap();
+///
+/// let hop = bob.receive_request(rng, &ctx).unwrap();
+/// let coin = alice.pay(rng, &ctx, id, hop).unwrap();
+/// let id = bob.receive(&ctx, coin).unwrap();
+///
+/// assert!(alice.coin_ids().is_empty());
+/// assert_eq!(bob.coin(id).unwrap().len_hops(), 2);
+/// ```
+pub struct Wallet<E: Pairing, S: Storage> {
+    pub(crate) sk: SecretKey<E>,
+    pub(crate) storage: S,
+    pub(crate) coins: BTreeMap<u64, Coin<E>>,
+    // the nonces of the requested hops which are not received yet.
+    pub(crate) pending: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing, S: Storage> Wallet<E, S> {
+    /// Creates the wallet of the user with the secret key `sk`, loading the coins and the pending
+    /// nonces from `storage`. The secret key is stored if the storage does not hold one yet.
+    ///
+    /// It fails if the storage holds the secret key of another user.
+    pub fn new(sk: SecretKey<E>, mut storage: S) -> Result<Self, WalletError> {
+        let mut bytes = Zeroizing::new(Vec::new());
+        sk.serialize_compressed(&mut *bytes)?;
+        match storage.load_secret_key()?.map(Zeroizing::new) {
+            Some(stored) if stored != bytes => return Err(WalletError::KeyMismatch),
+            Some(_) => {}
+            None => storage.store_secret_key(&bytes)?,
+        }
+        Self::load(sk, storage)
+    }
+
+    /// Opens the wallet with the secret key, the coins and the pending nonces of `storage`, e.g.
+    /// after a restart of a wallet created by `new`.
+    pub fn open(storage: S) -> Result<Self, WalletError> {
+        let bytes = storage
+            .load_secret_key()?
+            .map(Zeroizing::new)
+            .ok_or(WalletError::MissingKey)?;
+        let sk = SecretKey::deserialize_compressed(bytes.as_slice())?;
+        Self::load(sk, storage)
+    }
+
+    fn load(sk: SecretKey<E>, storage: S) -> Result<Self, WalletError> {
+        let mut coins = BTreeMap::new();
+        for (id, bytes) in storage.load_coins()? {
+            coins.insert(id, Coin::from_bytes(&bytes)?);
+        }
+        let pending: Vec<E::ScalarField> = storage
+            .load_pending()?
+            .iter()
+            .map(|bytes| E::ScalarField::deserialize_compressed(&bytes[..]))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            sk,
+            storage,
+            coins,
+            pending,
+        })
+    }
+
+    pub fn secret_key(&self) -> &SecretKey<E> {
+        &self.sk
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// The ids of the coins held by the wallet, in increasing order.
+    pub fn coin_ids(&self) -> Vec<u64> {
+        self.coins.keys().copied().collect()
+    }
+
+    pub fn coin(&self, id: u64) -> Option<&Coin<E>> {
+        self.coins.get(&id)
+    }
+
+    /// Withdraws a coin from the bank. `sign` is the signature of the bank with the tag-based LHSPS on
+    /// the message [M, g1^sk] under the tag of the hop (see `Hop::bank_message` and `Hop::bank_tag`).
+    /// Returns the id of the coin.
+    pub fn withdraw<R, F>(
+        &mut self,
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        sign: F,
+    ) -> Result<u64, WalletError>
+    where
+        R: RngCore,
+        F: FnOnce(&mut R, &[u8], &[E::G1Affine]) -> Result<Signature<E>, ()>,
+    {
+        let n = self.fresh_nonce(rng)?;
+        let hop = Hop::new(rng, ctx, &self.sk, n)?;
+        let message = hop.bank_message(self.sk.identity(ctx.params()));
+        let bank_sig = sign(rng, &hop.bank_tag(), &message).map_err(|_| WalletError::Withdrawal)?;
+        let coin = Coin::new(rng, ctx, &self.sk, &bank_sig, hop, CoinSecret::new(n))?;
+        self.insert(coin)
+    }
+
+    /// Creates the hop to this wallet that a payer transfers a coin to (see `pay`).
+    pub fn receive_request<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+    ) -> Result<Hop<E>, WalletError> {
+        let n = self.fresh_nonce(rng)?;
+        let hop = Hop::new(rng, ctx, &self.sk, n)?;
+        self.storage.store_pending(&nonce_bytes::<E>(n)?)?;
+        self.pending.push(n);
+        Ok(hop)
+    }
+
+    /// Pays the coin with the id to the hop requested by the receiver. The coin is removed from the
+    /// wallet, and the returned coin is sent to the receiver.
+    pub fn pay<R: RngCore>(
+        &mut self,
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        id: u64,
+        hop: Hop<E>,
+    ) -> Result<Coin<E>, WalletError> {
+        let coin = self.coins.get(&id).ok_or(WalletError::UnknownCoin(id))?;
+        if !hop.check(ctx) {
+            return Err(WalletError::Coin(CoinError::InvalidHop));
+        }
+        let paid = coin.transfer(rng, ctx, &self.sk, hop)?;
+
+        self.storage.remove_coin(id)?;
+        self.coins.remove(&id);
+        Ok(paid)
+    }
+
+    /// Receives a coin paid to a hop requested by `receive_request`. Returns the id of the coin.
+    ///
+    /// It fails if the coin does not pass `Coin::check`, e.g. the proof of the signature of the bank
+    /// does not verify under the key of `ctx`.
+    pub fn receive(&mut self, ctx: &CoinContext<E>, coin: Coin<E>) -> Result<u64, WalletError> {
+        if !coin.check(ctx) {
+            return Err(WalletError::InvalidCoin);
+        }
+        let last = coin.hops().last().ok_or(WalletError::InvalidCoin)?;
+        let pk = self.sk.public_key(ctx.params());
+        if !last.verify(ctx, &pk) {
+            return Err(WalletError::InvalidCoin);
+        }
+        let i = self
+            .pending
+            .iter()
+            .position(|n| self.sk.generate_serial_number(ctx.params(), *n).0 == last.sn)
+            .ok_or(WalletError::InvalidCoin)?;
+
+        let n = self.pending[i];
+        let id = self.insert(coin.with_secret(CoinSecret::new(n)))?;
+        self.storage.remove_pending(&nonce_bytes::<E>(n)?)?;
+        self.pending.swap_remove(i);
+        Ok(id)
+    }
+
+    // stores the coin before adding it to the wallet.
+    fn insert(&mut self, coin: Coin<E>) -> Result<u64, WalletError> {
+        let id = self.storage.next_coin_id()?;
+        self.storage.store_coin(id, &coin.to_bytes()?)?;
+        self.coins.insert(id, coin);
+        Ok(id)
+    }
+
+    // draws a nonce which has never been issued by the storage of this wallet.
+    fn fresh_nonce<R: RngCore>(&mut self, rng: &mut R) -> Result<E::ScalarField, WalletError> {
+        for _ in 0..MAX_NONCE_ATTEMPTS {
+            let n = E::ScalarField::rand(rng);
+            if self.storage.record_nonce(&nonce_bytes::<E>(n)?)? {
+                return Ok(n);
+            }
+        }
+        Err(WalletError::NoFreshNonce)
+    }
+}
+
+fn nonce_bytes<E: Pairing>(n: E::ScalarField) -> Result<Vec<u8>, SerializationError> {
+    let mut bytes = Vec::new();
+    n.serialize_compressed(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+    use std::collections::HashSet;
+    use std::io;
+    use std::path::Path;
+
+    use super::{
+        nonce_bytes, FileStorage, MemoryStorage, Storage, Wallet, WalletError, MAX_NONCE_ATTEMPTS,
+        NONCES_FILE,
+    };
+    use crate::coin::{tests::context, CoinContext};
+    use crate::double_spending;
+    use crate::key_file::KdfParams;
+    use crate::lhsps::tagged::signing_key::SigningKey;
+    use crate::params::Params;
+
+    type E = ark_bls12_381::Bls12_381;
+
+    // the cheapest KDF parameters, to keep the tests fast.
+    const KDF: KdfParams = KdfParams {
+        m_cost: 8,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn open(dir: &Path, password: &[u8]) -> io::Result<FileStorage<E>> {
+        FileStorage::open_with(dir, password, KDF)
+    }
+
+    // the contents of all files under the directory.
+    fn read_all(dir: &Path) -> Vec<Vec<u8>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(read_all(&path));
+            } else {
+                files.push(std::fs::read(&path).unwrap());
+            }
+        }
+        files
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_wallet_payments() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+
+        let mut wallets: Vec<_> = (0..3)
+            .map(|_| {
+                let (sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+                Wallet::new(sk, MemoryStorage::new()).unwrap()
+            })
+            .collect();
+
+        // 0 -> 1 -> 2 -> 0
+        let mut id = wallets[0]
+            .withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            let hop = wallets[to].receive_request(rng, &ctx).unwrap();
+            let coin = wallets[from].pay(rng, &ctx, id, hop).unwrap();
+            assert!(!coin.has_secret());
+            assert!(wallets[from].coin(id).is_none());
+            id = wallets[to].receive(&ctx, coin).unwrap();
+            assert!(wallets[to].storage().pending.is_empty());
+        }
+        assert_eq!(wallets[0].coin(id).unwrap().len_hops(), 4);
+        assert_eq!(wallets[0].coin_ids(), vec![1]);
+        assert_eq!(wallets[0].storage().coins.len(), 1);
+
+        // a coin paid to a hop requested by another wallet
+        let hop = wallets[1].receive_request(rng, &ctx).unwrap();
+        assert_eq!(wallets[1].storage().pending.len(), 1);
+        let coin = wallets[0].pay(rng, &ctx, id, hop).unwrap();
+        assert!(matches!(
+            wallets[2].receive(&ctx, coin.clone()),
+            Err(WalletError::InvalidCoin)
+        ));
+        // a coin with the proof of the bank signature of another coin
+        let other = wallets[2]
+            .withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        let mut bad = coin.clone();
+        bad.bank_proof = wallets[2].coin(other).unwrap().bank_proof.clone();
+        assert!(matches!(
+            wallets[1].receive(&ctx, bad),
+            Err(WalletError::InvalidCoin)
+        ));
+        id = wallets[1].receive(&ctx, coin.clone()).unwrap();
+        assert!(wallets[1].storage().pending.is_empty());
+        // a coin is received once
+        assert!(wallets[1].receive(&ctx, coin).is_err());
+
+        let hop = wallets[2].receive_request(rng, &ctx).unwrap();
+        assert!(matches!(
+            wallets[0].pay(rng, &ctx, id, hop),
+            Err(WalletError::UnknownCoin(_))
+        ));
+        assert!(matches!(
+            wallets[0].withdraw(rng, &ctx, |_, _, _| Err(())),
+            Err(WalletError::Withdrawal)
+        ));
+    }
+
+    #[test]
+    fn test_wallet_nonces_unique() {
+        let rng = &mut test_rng();
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, &Params::rand(rng));
+        let mut wallet = Wallet::<E, _>::new(sk, MemoryStorage::new()).unwrap();
+
+        // the same random stream is replayed, which the wallet must not follow
+        let mut nonces = HashSet::new();
+        for _ in 0..3 {
+            let rng = &mut StdRng::seed_from_u64(0);
+            for _ in 0..5 {
+                assert!(nonces.insert(wallet.fresh_nonce(rng).unwrap()));
+            }
+        }
+        assert_eq!(nonces.len(), 15);
+        assert_eq!(wallet.storage().nonces.len(), 15);
+
+        // a stream which only repeats itself exhausts the attempts
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, &Params::rand(rng));
+        let mut wallet = Wallet::<E, _>::new(sk, MemoryStorage::new()).unwrap();
+        for _ in 0..MAX_NONCE_ATTEMPTS {
+            assert!(wallet.fresh_nonce(&mut StdRng::seed_from_u64(1)).is_ok());
+        }
+        assert!(matches!(
+            wallet.fresh_nonce(&mut StdRng::seed_from_u64(1)),
+            Err(WalletError::NoFreshNonce)
+        ));
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+        let (payer_sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+        let mut payer = Wallet::new(payer_sk, MemoryStorage::new()).unwrap();
+
+        let dir =
+            std::env::temp_dir().join(format!("transferable-ecash-wallet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut wallet = Wallet::new(sk.clone(), open(&dir, b"password").unwrap()).unwrap();
+        let id = wallet
+            .withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        let n = wallet.fresh_nonce(&mut StdRng::seed_from_u64(0)).unwrap();
+        let hop = wallet.receive_request(rng, &ctx).unwrap();
+        drop(wallet);
+
+        // the coins, the nonces and the pending nonces survive reopening
+        let mut wallet = Wallet::<E, _>::new(sk.clone(), open(&dir, b"password").unwrap()).unwrap();
+        assert_eq!(wallet.coin_ids(), vec![id]);
+        assert!(wallet.coin(id).unwrap().has_secret());
+        assert!(wallet.coin(id).unwrap().check(&ctx));
+        assert_ne!(
+            wallet.fresh_nonce(&mut StdRng::seed_from_u64(0)).unwrap(),
+            n
+        );
+        assert_eq!(wallet.storage().nonces.len(), 4);
+
+        let paid = payer
+            .withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        let coin = payer.pay(rng, &ctx, paid, hop).unwrap();
+        let received = wallet.receive(&ctx, coin).unwrap();
+        assert!(wallet.storage().load_pending().unwrap().is_empty());
+
+        wallet.storage.remove_coin(id).unwrap();
+        assert_eq!(wallet.storage().load_coins().unwrap()[0].0, received);
+        drop(wallet);
+
+        // a torn last record is discarded, and the next record starts on a new line
+        let nonces = dir.join(NONCES_FILE);
+        let mut records = std::fs::read(&nonces).unwrap();
+        records.extend_from_slice(b"0a1b");
+        std::fs::write(&nonces, &records).unwrap();
+        let mut wallet = Wallet::<E, _>::new(sk.clone(), open(&dir, b"password").unwrap()).unwrap();
+        assert_eq!(wallet.storage().nonces.len(), 4);
+        wallet.fresh_nonce(rng).unwrap();
+        drop(wallet);
+        assert_eq!(open(&dir, b"password").unwrap().nonces.len(), 5);
+
+        // a corrupted complete record is an error
+        let mut records = std::fs::read(&nonces).unwrap();
+        records.extend_from_slice(b"zz\n");
+        std::fs::write(&nonces, &records).unwrap();
+        assert!(open(&dir, b"password").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_secrets() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+        let (sk2, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+
+        let dir = std::env::temp_dir().join(format!(
+            "transferable-ecash-wallet-secrets-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut wallet = Wallet::new(sk.clone(), open(&dir, b"password").unwrap()).unwrap();
+        let id = wallet
+            .withdraw(rng, &ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        wallet.receive_request(rng, &ctx).unwrap();
+
+        // neither the secret key, the secret part of the coin nor the pending nonce is written in plaintext
+        let mut sk_bytes = Vec::new();
+        sk.serialize_compressed(&mut sk_bytes).unwrap();
+        let secrets = [
+            sk_bytes,
+            nonce_bytes::<E>(wallet.coin(id).unwrap().secret.as_ref().unwrap().n).unwrap(),
+            nonce_bytes::<E>(wallet.pending[0]).unwrap(),
+        ];
+        for file in read_all(&dir) {
+            for secret in &secrets {
+                assert!(!contains(&file, secret));
+                assert!(!contains(&file, super::to_hex(secret).as_bytes()));
+            }
+        }
+        drop(wallet);
+
+        // the wallet is opened with the stored secret key
+        let wallet = Wallet::<E, _>::open(open(&dir, b"password").unwrap()).unwrap();
+        assert!(*wallet.secret_key() == sk);
+        assert_eq!(wallet.coin_ids(), vec![id]);
+        assert!(wallet.coin(id).unwrap().has_secret());
+        assert_eq!(wallet.pending.len(), 1);
+        drop(wallet);
+
+        // a wrong password, or the secret key of another user
+        assert!(open(&dir, b"wrong password").is_err());
+        assert!(matches!(
+            Wallet::new(sk2, open(&dir, b"password").unwrap()),
+            Err(WalletError::KeyMismatch)
+        ));
+        assert!(matches!(
+            Wallet::<E, _>::open(MemoryStorage::new()),
+            Err(WalletError::MissingKey)
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_coin_ids_not_reused() {
+        let rng = &mut test_rng();
+        let (_, bank_sk, ctx) = context(rng);
+        let (sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+        let (receiver_sk, _) = double_spending::key_gen::<E, _>(rng, ctx.params());
+        let mut receiver = Wallet::new(receiver_sk, MemoryStorage::new()).unwrap();
+
+        let dir = std::env::temp_dir().join(format!(
+            "transferable-ecash-wallet-ids-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // the coin with the highest id is paid out before a restart
+        let mut memory = Wallet::new(sk.clone(), MemoryStorage::new()).unwrap();
+        let paid = pay_new_coin(rng, &ctx, &bank_sk, &mut memory, &mut receiver);
+        let mut memory = Wallet::<E, _>::open(memory.storage().clone()).unwrap();
+        assert!(pay_new_coin(rng, &ctx, &bank_sk, &mut memory, &mut receiver) > paid);
+
+        let mut file = Wallet::new(sk, open(&dir, b"password").unwrap()).unwrap();
+        let paid = pay_new_coin(rng, &ctx, &bank_sk, &mut file, &mut receiver);
+        drop(file);
+        let mut file = Wallet::<E, _>::open(open(&dir, b"password").unwrap()).unwrap();
+        assert!(pay_new_coin(rng, &ctx, &bank_sk, &mut file, &mut receiver) > paid);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // withdraws a coin and pays it to the receiver, and returns the id that the coin had in the wallet.
+    fn pay_new_coin<R: RngCore, S: Storage>(
+        rng: &mut R,
+        ctx: &CoinContext<E>,
+        bank_sk: &SigningKey<E>,
+        wallet: &mut Wallet<E, S>,
+        receiver: &mut Wallet<E, MemoryStorage>,
+    ) -> u64 {
+        let id = wallet
+            .withdraw(rng, ctx, |rng, tag, m| bank_sk.sign(rng, tag, m))
+            .unwrap();
+        let hop = receiver.receive_request(rng, ctx).unwrap();
+        let coin = wallet.pay(rng, ctx, id, hop).unwrap();
+        receiver.receive(ctx, coin).unwrap();
+        assert!(wallet.coin_ids().is_empty());
+        id
+    }
+}